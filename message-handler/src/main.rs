@@ -9,6 +9,7 @@ use scheduler::types::{
     ActionAfterCompletion, FlexibleTimeWindow, FlexibleTimeWindowMode, ScheduleState, Target,
 };
 use serde::{Deserialize, Serialize};
+use wh_core::retry::{calculate_retry_delay, MAX_RETRY_ATTEMPTS};
 
 #[derive(Debug, Deserialize, Serialize)]
 pub struct MessageBody {
@@ -32,16 +33,6 @@ impl fmt::Display for HandlingError {
 
 impl StdError for HandlingError {}
 
-fn calculate_retry_delay(retry_attempt: u16) -> Duration {
-    match retry_attempt {
-        1 => Duration::minutes(5),
-        2 => Duration::minutes(5),
-        3 => Duration::minutes(10),
-        4 => Duration::minutes(20),
-        _ => Duration::minutes(20),
-    }
-}
-
 fn get_new_message(message: Option<&SqsMessage>) -> MessageBody {
     match message
         .and_then(|msg| msg.body.as_ref())
@@ -66,7 +57,7 @@ async fn handle_message_scheduling(event: LambdaEvent<SqsEvent>) -> Result<(), B
 
     let message_body = get_new_message(event.payload.records.first());
 
-    if message_body.retry_attempt > 5 {
+    if message_body.retry_attempt > MAX_RETRY_ATTEMPTS {
         eprintln!("MaxRetryAttemptsExceeded");
         return Err(Box::new(HandlingError(
             "MaxRetryAttemptsExceeded".to_string(),
@@ -39,6 +39,25 @@ new aws.ec2.VpcEndpoint("vpcEndpoint", {
   vpcEndpointType: "Gateway",
 });
 
+// Interface endpoint for reading the API keys from SSM Parameter Store without internet
+// access
+const ssmEndpointSecurityGroup = new aws.ec2.SecurityGroup("ssm-endpoint-sg", {
+  vpcId: vpc.id,
+  ingress: [
+    { protocol: "tcp", fromPort: 443, toPort: 443, cidrBlocks: [vpc.cidrBlock] },
+  ],
+  tags: { Name: "ssm-endpoint-sg" },
+});
+
+new aws.ec2.VpcEndpoint("ssmVpcEndpoint", {
+  vpcId: vpc.id,
+  serviceName: "com.amazonaws.eu-north-1.ssm",
+  vpcEndpointType: "Interface",
+  privateDnsEnabled: true,
+  subnetIds: [privateSubnet.id, privateSubnet2.id],
+  securityGroupIds: [ssmEndpointSecurityGroup.id],
+});
+
 const lambdaSecurityGroup = new aws.ec2.SecurityGroup("lambda-sg", {
   vpcId: vpc.id,
   ingress: [
@@ -79,6 +98,126 @@ new aws.iam.RolePolicyAttachment("dynamodb-readonly-policy-attachment", {
   policyArn: aws.iam.ManagedPolicy.AmazonDynamoDBReadOnlyAccess,
 });
 
+new aws.iam.RolePolicy("ssm-api-keys-policy", {
+  role: lambdaRole.id,
+  policy: aws.getCallerIdentityOutput().accountId.apply((accountId) =>
+    JSON.stringify({
+      Version: "2012-10-17",
+      Statement: [
+        {
+          Effect: "Allow",
+          Action: [
+            "ssm:GetParametersByPath",
+            "ssm:PutParameter",
+            "ssm:DeleteParameter",
+          ],
+          Resource: `arn:aws:ssm:eu-north-1:${accountId}:parameter/waterheater_calc/api_keys*`,
+        },
+        {
+          Effect: "Allow",
+          Action: ["ssm:GetParametersByPath"],
+          Resource: `arn:aws:ssm:eu-north-1:${accountId}:parameter/waterheater_calc/admin_keys*`,
+        },
+        {
+          Effect: "Allow",
+          Action: ["ssm:GetParameter"],
+          Resource: `arn:aws:ssm:eu-north-1:${accountId}:parameter/waterheater_calc/rate_limit_policy`,
+        },
+      ],
+    }),
+  ),
+});
+
+// Limits by API key tier and route, re-read by the Lambda every minute. Edited in place,
+// so later deploys don't reset it.
+new aws.ssm.Parameter(
+  "rate-limit-policy",
+  {
+    name: "/waterheater_calc/rate_limit_policy",
+    type: aws.ssm.ParameterType.String,
+    value: JSON.stringify({ anonymous: { requests_per_minute: 20 } }),
+    tags: commonTags,
+  },
+  { ignoreChanges: ["value"] },
+);
+
+// Registered devices, looked up by the hash of their token on every request
+const deviceTable = new aws.dynamodb.Table("waterheaterDevices", {
+  name: "waterheater_devices",
+  attributes: [
+    { name: "id", type: "S" },
+    { name: "token_hash", type: "S" },
+  ],
+  hashKey: "id",
+  globalSecondaryIndexes: [
+    {
+      name: "token_hash_index",
+      hashKey: "token_hash",
+      projectionType: "ALL",
+      writeCapacity: 1,
+      readCapacity: 1,
+    },
+  ],
+  billingMode: "PROVISIONED",
+  writeCapacity: 1,
+  readCapacity: 1,
+  tags: commonTags,
+});
+
+new aws.iam.RolePolicy("device-table-policy", {
+  role: lambdaRole.id,
+  policy: deviceTable.arn.apply((tableArn) =>
+    JSON.stringify({
+      Version: "2012-10-17",
+      Statement: [
+        {
+          Effect: "Allow",
+          Action: [
+            "dynamodb:GetItem",
+            "dynamodb:PutItem",
+            "dynamodb:UpdateItem",
+            "dynamodb:DeleteItem",
+            "dynamodb:Query",
+            "dynamodb:Scan",
+          ],
+          Resource: [tableArn, `${tableArn}/index/*`],
+        },
+      ],
+    }),
+  ),
+});
+
+// Rate limits of the `dynamodb` store, expired an hour after the last request of a client
+const rateLimitTable = new aws.dynamodb.Table("waterheaterRateLimits", {
+  name: "waterheater_calc_rate_limits",
+  attributes: [{ name: "client_id", type: "S" }],
+  hashKey: "client_id",
+  ttl: { attributeName: "expires_at", enabled: true },
+  billingMode: "PAY_PER_REQUEST",
+  tags: commonTags,
+});
+
+new aws.iam.RolePolicy("rate-limit-table-policy", {
+  role: lambdaRole.id,
+  policy: rateLimitTable.arn.apply((tableArn) =>
+    JSON.stringify({
+      Version: "2012-10-17",
+      Statement: [
+        {
+          Effect: "Allow",
+          Action: [
+            "dynamodb:GetItem",
+            "dynamodb:PutItem",
+            "dynamodb:DeleteItem",
+            "dynamodb:Scan",
+          ],
+          Resource: tableArn,
+        },
+      ],
+    }),
+  ),
+});
+
 const lambdaFunction = new aws.lambda.Function("waterheater-calc-lambda", {
   name: "waterheater-calc-lambda",
   code: new pulumi.asset.AssetArchive({
@@ -92,6 +231,8 @@ const lambdaFunction = new aws.lambda.Function("waterheater-calc-lambda", {
     variables: {
       REDIS_ENDPOINT: redisUrl,
       DEPLOY_ENV: "production",
+      DEVICE_TABLE_NAME: deviceTable.name,
+      RATE_LIMIT_TABLE_NAME: rateLimitTable.name,
     },
   },
   vpcConfig: {
@@ -0,0 +1,171 @@
+use axum::{
+    extract::{rejection::JsonRejection, Path, State},
+    http::StatusCode,
+    response::{IntoResponse, Response},
+    Json,
+};
+use tracing::{error, info};
+
+use wh_core::api::{ApiKeyInfo, ApiKeyRequest, CreatedApiKey, FieldError};
+
+use crate::devices::random_string;
+use crate::http::{body_validation_error, error_response, Unauthorized};
+use crate::AppState;
+
+const KEY_PREFIX: &str = "wh_";
+const KEY_LENGTH: usize = 40;
+const MAX_NAME_LENGTH: usize = 64;
+
+/// Names end up in SSM parameter names and file names, so only a safe subset is allowed
+fn validate_name(name: &str) -> Result<(), Vec<FieldError>> {
+    let valid = !name.is_empty()
+        && name.len() <= MAX_NAME_LENGTH
+        && name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_');
+
+    if valid {
+        Ok(())
+    } else {
+        Err(vec![FieldError {
+            field: "name".to_string(),
+            code: "invalid".to_string(),
+            message: format!(
+                "name must be 1 to {MAX_NAME_LENGTH} letters, digits, dashes or underscores"
+            ),
+        }])
+    }
+}
+
+/// Lists the names of the API keys. The keys themselves are only returned when created.
+#[utoipa::path(
+    get,
+    path = "/api/admin/api-keys",
+    responses(
+        (status = 200, description = "Names of the API keys", body = Vec<ApiKeyInfo>),
+        (status = 401, response = Unauthorized),
+        (status = 500, description = "The keys could not be read", body = ErrorResponse),
+    ),
+    security(("admin_key" = [])),
+)]
+pub async fn handle_list_api_keys(State(app_state): State<AppState>) -> Response {
+    match app_state.auth.api_keys.source().list_api_keys().await {
+        Ok(names) => Json(
+            names
+                .into_iter()
+                .map(|name| ApiKeyInfo { name })
+                .collect::<Vec<_>>(),
+        )
+        .into_response(),
+        Err(e) => {
+            error!("Failed to list API keys: {}", e);
+            error_response(StatusCode::INTERNAL_SERVER_ERROR, "Failed to list API keys")
+        }
+    }
+}
+
+/// Generates a new API key. It's accepted once the key cache of the instance handling
+/// the request has been refreshed, which happens right away on this instance.
+#[utoipa::path(
+    post,
+    path = "/api/admin/api-keys",
+    request_body(content = ApiKeyRequest, description = "Name of the new key"),
+    responses(
+        (status = 201, description = "Key created, the key is only returned here", body = CreatedApiKey),
+        (status = 400, description = "Invalid body, with the problem of each field in `errors`", body = ErrorResponse),
+        (status = 401, response = Unauthorized),
+        (status = 409, description = "A key with the name already exists", body = ErrorResponse),
+        (status = 500, description = "The key could not be stored", body = ErrorResponse),
+    ),
+    security(("admin_key" = [])),
+)]
+pub async fn handle_create_api_key(
+    State(app_state): State<AppState>,
+    request: Result<Json<ApiKeyRequest>, JsonRejection>,
+) -> Response {
+    let Json(request) = match request {
+        Ok(request) => request,
+        Err(rejection) => {
+            return error_response(StatusCode::BAD_REQUEST, &rejection.body_text());
+        }
+    };
+    if let Err(errors) = validate_name(&request.name) {
+        return body_validation_error(errors);
+    }
+
+    let source = app_state.auth.api_keys.source();
+
+    match source.list_api_keys().await {
+        Ok(names) if names.contains(&request.name) => {
+            return error_response(StatusCode::CONFLICT, "API key already exists");
+        }
+        Ok(_) => {}
+        Err(e) => {
+            error!("Failed to list API keys: {}", e);
+            return error_response(
+                StatusCode::INTERNAL_SERVER_ERROR,
+                "Failed to create API key",
+            );
+        }
+    }
+
+    let key = format!("{KEY_PREFIX}{}", random_string(KEY_LENGTH));
+
+    if let Err(e) = source.put_api_key(&request.name, &key).await {
+        error!("Failed to store API key: {}", e);
+        return error_response(
+            StatusCode::INTERNAL_SERVER_ERROR,
+            "Failed to create API key",
+        );
+    }
+
+    app_state.auth.api_keys.invalidate();
+    info!(name = request.name, "Created API key");
+
+    (
+        StatusCode::CREATED,
+        Json(CreatedApiKey {
+            name: request.name,
+            key,
+        }),
+    )
+        .into_response()
+}
+
+/// Deletes an API key by name
+#[utoipa::path(
+    delete,
+    path = "/api/admin/api-keys/{name}",
+    responses(
+        (status = 204, description = "Key deleted"),
+        (status = 401, response = Unauthorized),
+        (status = 404, description = "Unknown key", body = ErrorResponse),
+        (status = 500, description = "The key could not be deleted", body = ErrorResponse),
+    ),
+    params(("name" = String, Path, description = "Name of the key")),
+    security(("admin_key" = [])),
+)]
+pub async fn handle_delete_api_key(
+    State(app_state): State<AppState>,
+    Path(name): Path<String>,
+) -> Response {
+    if validate_name(&name).is_err() {
+        return error_response(StatusCode::NOT_FOUND, "Unknown API key");
+    }
+
+    match app_state.auth.api_keys.source().delete_api_key(&name).await {
+        Ok(true) => {
+            app_state.auth.api_keys.invalidate();
+            info!(name, "Deleted API key");
+            StatusCode::NO_CONTENT.into_response()
+        }
+        Ok(false) => error_response(StatusCode::NOT_FOUND, "Unknown API key"),
+        Err(e) => {
+            error!("Failed to delete API key: {}", e);
+            error_response(
+                StatusCode::INTERNAL_SERVER_ERROR,
+                "Failed to delete API key",
+            )
+        }
+    }
+}
@@ -0,0 +1,135 @@
+use axum::{
+    extract::{rejection::JsonRejection, Path, State},
+    http::StatusCode,
+    response::{IntoResponse, Response},
+    Json,
+};
+use tracing::error;
+
+use wh_core::api::{DeviceInfo, DeviceRegistration};
+
+use crate::http::{body_validation_error, error_response, Unauthorized};
+use crate::v3::devices::validate;
+use crate::AppState;
+
+/// Lists every registered device with its profile
+#[utoipa::path(
+    get,
+    path = "/api/admin/devices",
+    responses(
+        (status = 200, description = "Devices ordered by id", body = Vec<DeviceInfo>),
+        (status = 401, response = Unauthorized),
+        (status = 500, description = "The devices could not be read", body = ErrorResponse),
+    ),
+    security(("admin_key" = [])),
+)]
+pub async fn handle_list_devices(State(app_state): State<AppState>) -> Response {
+    match app_state.devices.list().await {
+        Ok(devices) => Json(
+            devices
+                .into_iter()
+                .map(DeviceInfo::from)
+                .collect::<Vec<_>>(),
+        )
+        .into_response(),
+        Err(e) => {
+            error!("Failed to list devices: {}", e);
+            error_response(StatusCode::INTERNAL_SERVER_ERROR, "Failed to list devices")
+        }
+    }
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/admin/devices/{device_id}",
+    responses(
+        (status = 200, description = "The device", body = DeviceInfo),
+        (status = 401, response = Unauthorized),
+        (status = 404, description = "Unknown device", body = ErrorResponse),
+        (status = 500, description = "The device could not be read", body = ErrorResponse),
+    ),
+    params(("device_id" = String, Path, description = "Id of the device")),
+    security(("admin_key" = [])),
+)]
+pub async fn handle_get_device(
+    State(app_state): State<AppState>,
+    Path(device_id): Path<String>,
+) -> Response {
+    match app_state.devices.get(&device_id).await {
+        Ok(Some(device)) => Json(DeviceInfo::from(device)).into_response(),
+        Ok(None) => error_response(StatusCode::NOT_FOUND, "Unknown device"),
+        Err(e) => {
+            error!("Failed to get device: {}", e);
+            error_response(StatusCode::INTERNAL_SERVER_ERROR, "Failed to get device")
+        }
+    }
+}
+
+/// Replaces the name, zone and profile of a device without its token, validated like
+/// `PUT /api/v3/devices/{device_id}`
+#[utoipa::path(
+    put,
+    path = "/api/admin/devices/{device_id}",
+    request_body(content = DeviceRegistration, description = "New name, zone and profile of the device"),
+    responses(
+        (status = 200, description = "Device updated", body = DeviceInfo),
+        (status = 400, description = "Invalid body, with the problem of each field in `errors`", body = ErrorResponse),
+        (status = 401, response = Unauthorized),
+        (status = 404, description = "Unknown device", body = ErrorResponse),
+        (status = 500, description = "The device could not be stored", body = ErrorResponse),
+    ),
+    params(("device_id" = String, Path, description = "Id of the device")),
+    security(("admin_key" = [])),
+)]
+pub async fn handle_replace_device(
+    State(app_state): State<AppState>,
+    Path(device_id): Path<String>,
+    registration: Result<Json<DeviceRegistration>, JsonRejection>,
+) -> Response {
+    let Json(registration) = match registration {
+        Ok(registration) => registration,
+        Err(rejection) => {
+            return error_response(StatusCode::BAD_REQUEST, &rejection.body_text());
+        }
+    };
+    if let Err(errors) = validate(&registration) {
+        return body_validation_error(errors);
+    }
+
+    match app_state.devices.update(&device_id, registration).await {
+        Ok(Some(device)) => Json(DeviceInfo::from(device)).into_response(),
+        Ok(None) => error_response(StatusCode::NOT_FOUND, "Unknown device"),
+        Err(e) => {
+            error!("Failed to update device: {}", e);
+            error_response(StatusCode::INTERNAL_SERVER_ERROR, "Failed to update device")
+        }
+    }
+}
+
+/// Deletes a device. Other server instances may accept its token for up to five minutes
+/// after, until their cached lookup of the token expires.
+#[utoipa::path(
+    delete,
+    path = "/api/admin/devices/{device_id}",
+    responses(
+        (status = 204, description = "Device deleted"),
+        (status = 401, response = Unauthorized),
+        (status = 404, description = "Unknown device", body = ErrorResponse),
+        (status = 500, description = "The device could not be deleted", body = ErrorResponse),
+    ),
+    params(("device_id" = String, Path, description = "Id of the device")),
+    security(("admin_key" = [])),
+)]
+pub async fn handle_delete_device(
+    State(app_state): State<AppState>,
+    Path(device_id): Path<String>,
+) -> Response {
+    match app_state.devices.delete(&device_id).await {
+        Ok(true) => StatusCode::NO_CONTENT.into_response(),
+        Ok(false) => error_response(StatusCode::NOT_FOUND, "Unknown device"),
+        Err(e) => {
+            error!("Failed to delete device: {}", e);
+            error_response(StatusCode::INTERNAL_SERVER_ERROR, "Failed to delete device")
+        }
+    }
+}
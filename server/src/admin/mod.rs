@@ -0,0 +1,4 @@
+pub(crate) mod api_keys;
+pub(crate) mod devices;
+pub(crate) mod rate_limits;
+pub(crate) mod router;
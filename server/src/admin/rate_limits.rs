@@ -0,0 +1,173 @@
+use axum::{
+    extract::{rejection::JsonRejection, Path, State},
+    http::StatusCode,
+    response::{IntoResponse, Response},
+    Json,
+};
+use tracing::{error, info};
+
+use wh_core::api::{ClientRateLimit, FieldError, RateLimitOverride};
+
+use crate::http::{body_validation_error, error_response, Unauthorized};
+use crate::rate_limit::Quota;
+use crate::AppState;
+
+fn validate(limit: &RateLimitOverride) -> Result<(), Vec<FieldError>> {
+    let mut errors = Vec::new();
+
+    if limit.capacity == 0 {
+        errors.push(FieldError {
+            field: "capacity".to_string(),
+            code: "out_of_range".to_string(),
+            message: "capacity must be at least 1".to_string(),
+        });
+    }
+    if !limit.refill_per_minute.is_finite() || limit.refill_per_minute <= 0.0 {
+        errors.push(FieldError {
+            field: "refill_per_minute".to_string(),
+            code: "out_of_range".to_string(),
+            message: "refill_per_minute must be greater than 0".to_string(),
+        });
+    }
+
+    if errors.is_empty() {
+        Ok(())
+    } else {
+        Err(errors)
+    }
+}
+
+/// Lists the clients with a rate limit other than the default
+#[utoipa::path(
+    get,
+    path = "/api/admin/rate-limits",
+    responses(
+        (status = 200, description = "Overrides ordered by client", body = Vec<ClientRateLimit>),
+        (status = 401, response = Unauthorized),
+        (status = 500, description = "The overrides could not be read", body = ErrorResponse),
+    ),
+    security(("admin_key" = [])),
+)]
+pub async fn handle_list_rate_limits(State(app_state): State<AppState>) -> Response {
+    match app_state.rate_limiter.overrides().await {
+        Ok(overrides) => Json(overrides).into_response(),
+        Err(e) => {
+            error!("Failed to list rate limits: {}", e);
+            error_response(
+                StatusCode::INTERNAL_SERVER_ERROR,
+                "Failed to list rate limits",
+            )
+        }
+    }
+}
+
+/// Sets the rate limit of a client, `device:<id>` for devices, `key:<name>` for API keys
+/// and otherwise the IP address. It applies from the next request of the client, on
+/// every route.
+#[utoipa::path(
+    put,
+    path = "/api/admin/rate-limits/{client}",
+    request_body(content = RateLimitOverride, description = "Rate limit of the client"),
+    responses(
+        (status = 200, description = "Rate limit set", body = ClientRateLimit),
+        (status = 400, description = "Invalid body, with the problem of each field in `errors`", body = ErrorResponse),
+        (status = 401, response = Unauthorized),
+        (status = 500, description = "The rate limit could not be stored", body = ErrorResponse),
+    ),
+    params(("client" = String, Path, description = "`device:<id>`, `key:<name>` or an IP address")),
+    security(("admin_key" = [])),
+)]
+pub async fn handle_set_rate_limit(
+    State(app_state): State<AppState>,
+    Path(client): Path<String>,
+    limit: Result<Json<RateLimitOverride>, JsonRejection>,
+) -> Response {
+    let Json(limit) = match limit {
+        Ok(limit) => limit,
+        Err(rejection) => {
+            return error_response(StatusCode::BAD_REQUEST, &rejection.body_text());
+        }
+    };
+    if let Err(errors) = validate(&limit) {
+        return body_validation_error(errors);
+    }
+
+    match app_state
+        .rate_limiter
+        .set_override(&client, Quota::from(limit))
+        .await
+    {
+        Ok(()) => {
+            info!(client, "Set rate limit override");
+            Json(ClientRateLimit {
+                client,
+                capacity: limit.capacity,
+                refill_per_minute: limit.refill_per_minute,
+            })
+            .into_response()
+        }
+        Err(e) => {
+            error!("Failed to set rate limit: {}", e);
+            error_response(
+                StatusCode::INTERNAL_SERVER_ERROR,
+                "Failed to set rate limit",
+            )
+        }
+    }
+}
+
+/// Returns a client to the default rate limit
+#[utoipa::path(
+    delete,
+    path = "/api/admin/rate-limits/{client}",
+    responses(
+        (status = 204, description = "Override deleted"),
+        (status = 401, response = Unauthorized),
+        (status = 404, description = "The client has the default rate limit", body = ErrorResponse),
+        (status = 500, description = "The override could not be deleted", body = ErrorResponse),
+    ),
+    params(("client" = String, Path, description = "`device:<id>`, `key:<name>` or an IP address")),
+    security(("admin_key" = [])),
+)]
+pub async fn handle_delete_rate_limit(
+    State(app_state): State<AppState>,
+    Path(client): Path<String>,
+) -> Response {
+    match app_state.rate_limiter.delete_override(&client).await {
+        Ok(true) => {
+            info!(client, "Deleted rate limit override");
+            StatusCode::NO_CONTENT.into_response()
+        }
+        Ok(false) => error_response(StatusCode::NOT_FOUND, "No rate limit override"),
+        Err(e) => {
+            error!("Failed to delete rate limit: {}", e);
+            error_response(
+                StatusCode::INTERNAL_SERVER_ERROR,
+                "Failed to delete rate limit",
+            )
+        }
+    }
+}
+
+/// Lists the rate limited clients seen within the last hour, with `@<route>` added for
+/// the requests to routes limited separately. Admins and API keys of unlimited tiers are
+/// not rate limited and so not listed.
+#[utoipa::path(
+    get,
+    path = "/api/admin/usage",
+    responses(
+        (status = 200, description = "Usage ordered by client", body = Vec<ClientUsage>),
+        (status = 401, response = Unauthorized),
+        (status = 500, description = "The usage could not be read", body = ErrorResponse),
+    ),
+    security(("admin_key" = [])),
+)]
+pub async fn handle_get_usage(State(app_state): State<AppState>) -> Response {
+    match app_state.rate_limiter.usage().await {
+        Ok(usage) => Json(usage).into_response(),
+        Err(e) => {
+            error!("Failed to get usage: {}", e);
+            error_response(StatusCode::INTERNAL_SERVER_ERROR, "Failed to get usage")
+        }
+    }
+}
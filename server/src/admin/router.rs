@@ -0,0 +1,49 @@
+use axum::routing::{delete, get, put, MethodRouter};
+use axum::Router;
+
+use crate::auth::require_admin;
+use crate::http::not_found;
+use crate::AppState;
+
+use super::api_keys::{handle_create_api_key, handle_delete_api_key, handle_list_api_keys};
+use super::devices::{
+    handle_delete_device, handle_get_device, handle_list_devices, handle_replace_device,
+};
+use super::rate_limits::{
+    handle_delete_rate_limit, handle_get_usage, handle_list_rate_limits, handle_set_rate_limit,
+};
+
+/// Routes of the admin API relative to `/api/admin`, documented in the OpenAPI document
+/// like the versioned routes.
+pub(crate) fn routes() -> Vec<(&'static str, MethodRouter<AppState>)> {
+    vec![
+        (
+            "/api-keys",
+            get(handle_list_api_keys).post(handle_create_api_key),
+        ),
+        ("/api-keys/:name", delete(handle_delete_api_key)),
+        ("/devices", get(handle_list_devices)),
+        (
+            "/devices/:device_id",
+            get(handle_get_device)
+                .put(handle_replace_device)
+                .delete(handle_delete_device),
+        ),
+        ("/rate-limits", get(handle_list_rate_limits)),
+        (
+            "/rate-limits/:client",
+            put(handle_set_rate_limit).delete(handle_delete_rate_limit),
+        ),
+        ("/usage", get(handle_get_usage)),
+    ]
+}
+
+pub fn admin_routes() -> Router<AppState> {
+    routes()
+        .into_iter()
+        .fold(Router::new(), |router, (path, method_router)| {
+            router.route(path, method_router)
+        })
+        .route_layer(axum::middleware::from_fn(require_admin))
+        .fallback(not_found)
+}
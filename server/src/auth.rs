@@ -0,0 +1,245 @@
+use std::collections::HashMap;
+use std::env;
+use std::net::SocketAddr;
+use std::str::FromStr;
+use std::time::Duration;
+
+use axum::{
+    body::Body,
+    extract::{ConnectInfo, Query, State},
+    http::Request,
+    middleware::Next,
+    response::Response,
+};
+use lambda_http::Error;
+use tracing::info;
+use utoipa::openapi::security::{ApiKey, ApiKeyValue, SecurityScheme};
+use utoipa::Modify;
+
+use crate::http::unauthorized;
+use crate::rate_limit::limit_credentials_lookup;
+use crate::secrets::ApiKeyCache;
+use crate::AppState;
+
+pub const API_KEY_HEADER: &str = "x-api-key";
+/// Admin keys are only accepted in a header, so that they don't end up in access logs
+pub const ADMIN_KEY_HEADER: &str = "x-admin-key";
+pub const ADMIN_PATH: &str = "/api/admin";
+/// Shelly scripts can't always set headers, so the key can also be in the query
+pub const API_KEY_QUERY_PARAM: &str = "api_key";
+
+const DEFAULT_REFRESH_SECONDS: u64 = 300;
+
+/// What a request without an API key may do
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AnonymousAccess {
+    /// Served without rate limiting
+    Allow,
+    /// Served, but rate limited
+    Limit,
+    /// Answered with 401
+    Deny,
+}
+
+impl FromStr for AnonymousAccess {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "allow" => Ok(AnonymousAccess::Allow),
+            "limit" => Ok(AnonymousAccess::Limit),
+            "deny" => Ok(AnonymousAccess::Deny),
+            other => Err(format!("Unsupported anonymous access: {other}").into()),
+        }
+    }
+}
+
+/// Who made the request, added to the request extensions by [`authenticate`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Caller {
+    /// Name of the API key
+    ApiKey(String),
+    Admin,
+    Anonymous(AnonymousAccess),
+}
+
+/// Anonymous access of each route, by path prefix
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AuthPolicy {
+    pub default: AnonymousAccess,
+    pub routes: Vec<(String, AnonymousAccess)>,
+}
+
+impl Default for AuthPolicy {
+    /// Everything is open and rate limited, as before API keys
+    fn default() -> Self {
+        AuthPolicy {
+            default: AnonymousAccess::Limit,
+            routes: Vec::new(),
+        }
+    }
+}
+
+impl AuthPolicy {
+    pub fn from_env() -> Result<Self, Error> {
+        let default = match env::var("ANONYMOUS_ACCESS") {
+            Ok(access) => access.parse()?,
+            Err(_) => AnonymousAccess::Limit,
+        };
+        let routes = match env::var("ANONYMOUS_ACCESS_ROUTES") {
+            Ok(routes) => parse_routes(&routes)?,
+            Err(_) => Vec::new(),
+        };
+
+        Ok(AuthPolicy { default, routes })
+    }
+
+    /// Access of the longest route prefix matching `path`
+    pub fn anonymous_access(&self, path: &str) -> AnonymousAccess {
+        self.routes
+            .iter()
+            .filter(|(prefix, _)| matches_route(path, prefix))
+            .max_by_key(|(prefix, _)| prefix.len())
+            .map(|(_, access)| *access)
+            .unwrap_or(self.default)
+    }
+}
+
+/// Whether `path` is the route `prefix` or below it. Whole segments are compared, so
+/// `/api/v2/prices` is no prefix of `/api/v2/pricesx`.
+pub fn matches_route(path: &str, prefix: &str) -> bool {
+    path.strip_prefix(prefix.trim_end_matches('/'))
+        .is_some_and(|rest| rest.is_empty() || rest.starts_with('/'))
+}
+
+/// Parses `/api/v3=deny,/api/v2/prices=allow`
+pub fn parse_routes(routes: &str) -> Result<Vec<(String, AnonymousAccess)>, Error> {
+    routes
+        .split(',')
+        .map(str::trim)
+        .filter(|route| !route.is_empty())
+        .map(|route| {
+            let (prefix, access) = route
+                .split_once('=')
+                .ok_or(format!("Route access should be prefix=access, was {route}"))?;
+            Ok((prefix.trim().to_string(), access.trim().parse()?))
+        })
+        .collect()
+}
+
+pub fn api_key_refresh_interval() -> Result<Duration, Error> {
+    match env::var("API_KEYS_REFRESH_SECONDS") {
+        Ok(seconds) => Ok(Duration::from_secs(seconds.parse()?)),
+        Err(_) => Ok(Duration::from_secs(DEFAULT_REFRESH_SECONDS)),
+    }
+}
+
+pub struct Auth {
+    pub api_keys: ApiKeyCache,
+    /// Keys of the admin API, which are not accepted as API keys
+    pub admin_keys: ApiKeyCache,
+    pub policy: AuthPolicy,
+}
+
+fn api_key(request: &Request<Body>) -> Option<String> {
+    if let Some(key) = request
+        .headers()
+        .get(API_KEY_HEADER)
+        .and_then(|value| value.to_str().ok())
+    {
+        return Some(key.to_string());
+    }
+
+    Query::<HashMap<String, String>>::try_from_uri(request.uri())
+        .ok()?
+        .0
+        .remove(API_KEY_QUERY_PARAM)
+}
+
+/// Checks the API key of the request, if any, against the cached keys. Requests with an
+/// invalid key are always rejected, so that a mistyped key doesn't silently count as
+/// anonymous, and counted against the IP address so that keys can't be guessed. The admin
+/// API only accepts admin keys.
+pub async fn authenticate(
+    State(state): State<AppState>,
+    ConnectInfo(addr): ConnectInfo<SocketAddr>,
+    mut request: Request<Body>,
+    next: Next,
+) -> Response {
+    if request.uri().path().starts_with(ADMIN_PATH) {
+        let admin_key = request
+            .headers()
+            .get(ADMIN_KEY_HEADER)
+            .and_then(|value| value.to_str().ok());
+
+        match admin_key {
+            Some(key) if state.auth.admin_keys.contains(key).await => {}
+            Some(_) => {
+                info!(path = request.uri().path(), "Invalid admin key");
+                if let Some(response) = limit_credentials_lookup(&state, addr.ip()).await {
+                    return response;
+                }
+                return unauthorized("Invalid admin key");
+            }
+            None => return unauthorized("Admin key required"),
+        }
+
+        request.extensions_mut().insert(Caller::Admin);
+        return next.run(request).await;
+    }
+
+    let access = state.auth.policy.anonymous_access(request.uri().path());
+
+    let caller = match api_key(&request) {
+        Some(key) => match state.auth.api_keys.name_of(&key).await {
+            Some(name) => Caller::ApiKey(name),
+            None => {
+                info!(path = request.uri().path(), "Invalid API key");
+                if let Some(response) = limit_credentials_lookup(&state, addr.ip()).await {
+                    return response;
+                }
+                return unauthorized("Invalid API key");
+            }
+        },
+        None if access == AnonymousAccess::Deny => {
+            return unauthorized("API key required");
+        }
+        None => Caller::Anonymous(access),
+    };
+
+    request.extensions_mut().insert(caller);
+
+    next.run(request).await
+}
+
+/// Rejects requests not authenticated with an admin key, for the admin router in case
+/// it's served without [`authenticate`]
+pub async fn require_admin(request: Request<Body>, next: Next) -> Response {
+    if request.extensions().get::<Caller>() != Some(&Caller::Admin) {
+        return unauthorized("Admin key required");
+    }
+
+    next.run(request).await
+}
+
+/// Adds the API key security schemes to the OpenAPI document
+pub struct SecurityAddon;
+
+impl Modify for SecurityAddon {
+    fn modify(&self, openapi: &mut utoipa::openapi::OpenApi) {
+        let components = openapi.components.get_or_insert_with(Default::default);
+
+        components.add_security_scheme(
+            "api_key",
+            SecurityScheme::ApiKey(ApiKey::Header(ApiKeyValue::new(API_KEY_HEADER))),
+        );
+        components.add_security_scheme(
+            "api_key_query",
+            SecurityScheme::ApiKey(ApiKey::Query(ApiKeyValue::new(API_KEY_QUERY_PARAM))),
+        );
+        components.add_security_scheme(
+            "admin_key",
+            SecurityScheme::ApiKey(ApiKey::Header(ApiKeyValue::new(ADMIN_KEY_HEADER))),
+        );
+    }
+}
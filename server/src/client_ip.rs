@@ -0,0 +1,214 @@
+//! The IP address of the client, which rate limits and logs are keyed by. Forwarded
+//! addresses are only believed when added by a trusted proxy, as any client can send
+//! `X-Forwarded-For` itself.
+
+use std::env;
+use std::net::{IpAddr, SocketAddr};
+use std::str::FromStr;
+
+use axum::body::Body;
+use axum::extract::{ConnectInfo, State};
+use axum::http::{HeaderMap, Request};
+use axum::middleware::Next;
+use axum::response::Response;
+use ipnet::IpNet;
+use lambda_http::request::RequestContext;
+use lambda_http::Error;
+use lambda_http::RequestExt;
+
+use crate::AppState;
+
+/// Header the trusted proxies add the address they received the request from to
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ForwardedHeader {
+    /// `X-Forwarded-For: 203.0.113.7, 10.0.0.2`
+    #[default]
+    XForwardedFor,
+    /// `Forwarded: for=203.0.113.7, for="[2001:db8::7]:4711"` of RFC 7239
+    Forwarded,
+}
+
+impl FromStr for ForwardedHeader {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "x-forwarded-for" => Ok(ForwardedHeader::XForwardedFor),
+            "forwarded" => Ok(ForwardedHeader::Forwarded),
+            other => Err(format!("Unsupported forwarded header: {other}").into()),
+        }
+    }
+}
+
+/// Proxies in front of the server. Without any the forwarded headers are ignored and the
+/// client is the peer of the connection, or the source IP seen by API Gateway.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct TrustedProxies {
+    /// Proxies trusted wherever they are in the chain
+    pub networks: Vec<IpNet>,
+    /// Number of proxies right in front of the server that are trusted whatever their
+    /// address, e.g. 1 behind a single load balancer
+    pub hops: usize,
+    pub header: ForwardedHeader,
+}
+
+impl TrustedProxies {
+    /// `TRUSTED_PROXIES` as comma separated CIDRs or addresses, `TRUSTED_PROXY_HOPS` and
+    /// `TRUSTED_PROXY_HEADER`
+    pub fn from_env() -> Result<Self, Error> {
+        let networks = env::var("TRUSTED_PROXIES")
+            .unwrap_or_default()
+            .split(',')
+            .map(str::trim)
+            .filter(|network| !network.is_empty())
+            .map(parse_network)
+            .collect::<Result<_, _>>()?;
+
+        let hops = match env::var("TRUSTED_PROXY_HOPS") {
+            Ok(hops) => hops.parse()?,
+            Err(_) => 0,
+        };
+
+        let header = match env::var("TRUSTED_PROXY_HEADER") {
+            Ok(header) => header.parse()?,
+            Err(_) => ForwardedHeader::default(),
+        };
+
+        Ok(TrustedProxies {
+            networks,
+            hops,
+            header,
+        })
+    }
+
+    /// Walks the forwarded addresses from the `peer` of the connection towards the
+    /// client, for as long as the address is of a trusted proxy. The first address not
+    /// of one is the client.
+    pub fn client_ip(&self, headers: &HeaderMap, peer: IpAddr) -> IpAddr {
+        let mut forwarded = self.forwarded(headers);
+
+        // API Gateway adds the source IP to X-Forwarded-For, it's the peer already
+        if forwarded.last() == Some(&Some(peer)) {
+            forwarded.pop();
+        }
+
+        let mut client = peer;
+        for (hop, address) in forwarded.into_iter().rev().enumerate() {
+            if !self.is_trusted(client, hop) {
+                break;
+            }
+
+            match address {
+                Some(address) => client = address,
+                // Hidden or malformed, nothing further can be believed
+                None => break,
+            }
+        }
+
+        client
+    }
+
+    fn is_trusted(&self, address: IpAddr, hop: usize) -> bool {
+        hop < self.hops
+            || self
+                .networks
+                .iter()
+                .any(|network| network.contains(&address))
+    }
+
+    /// Forwarded addresses from the client to the last proxy
+    fn forwarded(&self, headers: &HeaderMap) -> Vec<Option<IpAddr>> {
+        let name = match self.header {
+            ForwardedHeader::XForwardedFor => "x-forwarded-for",
+            ForwardedHeader::Forwarded => "forwarded",
+        };
+
+        let mut addresses = Vec::new();
+
+        // Every header of the name, in order, as proxies may add their own instead of
+        // appending to the existing one
+        for value in headers.get_all(name) {
+            let Ok(value) = value.to_str() else {
+                addresses.push(None);
+                continue;
+            };
+
+            for element in value.split(',') {
+                addresses.push(match self.header {
+                    ForwardedHeader::XForwardedFor => element.trim().parse().ok(),
+                    ForwardedHeader::Forwarded => forwarded_for(element),
+                });
+            }
+        }
+
+        addresses
+    }
+}
+
+fn parse_network(network: &str) -> Result<IpNet, Error> {
+    match network.parse::<IpNet>() {
+        Ok(network) => Ok(network),
+        Err(_) => match network.parse::<IpAddr>() {
+            Ok(address) => Ok(IpNet::from(address)),
+            Err(_) => Err(format!("Invalid trusted proxy: {network}").into()),
+        },
+    }
+}
+
+/// Address in the `for` parameter of an element of the `Forwarded` header, which may be
+/// quoted and have a port. `None` for `unknown` and obfuscated identifiers.
+fn forwarded_for(element: &str) -> Option<IpAddr> {
+    let value = element.split(';').find_map(|pair| {
+        let (key, value) = pair.split_once('=')?;
+        key.trim().eq_ignore_ascii_case("for").then(|| value.trim())
+    })?;
+
+    let node = value.trim_matches('"');
+
+    if let Some(rest) = node.strip_prefix('[') {
+        // [2001:db8::7] or [2001:db8::7]:4711
+        let (address, _) = rest.split_once(']')?;
+        return address.parse().ok();
+    }
+
+    // 203.0.113.7 or 203.0.113.7:4711
+    node.split(':').next()?.parse().ok()
+}
+
+/// Source IP of the request as seen by API Gateway, when running in Lambda
+fn source_ip(request: &Request<Body>) -> Option<IpAddr> {
+    let source_ip = match request.request_context_ref()? {
+        RequestContext::ApiGatewayV2(context) => context.http.source_ip.as_ref(),
+        RequestContext::ApiGatewayV1(context) => context.identity.source_ip.as_ref(),
+        RequestContext::WebSocket(context) => context.identity.source_ip.as_ref(),
+        RequestContext::Alb(_) => None,
+    }?;
+
+    source_ip.parse().ok()
+}
+
+/// Replaces the `ConnectInfo` of the request with the address of the client, for the
+/// middleware and handlers after this one
+pub async fn inject_client_ip(
+    State(state): State<AppState>,
+    mut request: Request<Body>,
+    next: Next,
+) -> Response {
+    // Localhost when neither is known, e.g. in tests
+    let peer = source_ip(&request)
+        .or_else(|| {
+            request
+                .extensions()
+                .get::<ConnectInfo<SocketAddr>>()
+                .map(|ConnectInfo(addr)| addr.ip())
+        })
+        .unwrap_or(IpAddr::from([127, 0, 0, 1]));
+
+    let client_ip = state.trusted_proxies.client_ip(request.headers(), peer);
+
+    request
+        .extensions_mut()
+        .insert(ConnectInfo(SocketAddr::new(client_ip, 0)));
+
+    next.run(request).await
+}
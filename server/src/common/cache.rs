@@ -0,0 +1,231 @@
+use std::collections::HashMap;
+use std::future::Future;
+use std::hash::Hash;
+use std::sync::{Arc, Mutex, RwLock};
+use std::time::Duration;
+
+use async_trait::async_trait;
+use chrono::{DateTime, NaiveDate, Utc};
+use tokio::sync::OnceCell;
+use tokio::time::Instant;
+use tracing::error;
+
+use wh_core::price_series::PriceSeries;
+use wh_core::repository::{DeliveryDay, PricingRepository, RepositoryError};
+use wh_core::time_provider::TimeProvider;
+use wh_core::types::BiddingZone;
+use wh_core::units::CentsPerKwh;
+use wh_core::util::next_publication_time;
+
+struct CacheEntry<V> {
+    value: Arc<OnceCell<V>>,
+    expires_at: DateTime<Utc>,
+}
+
+/// Values derived from the pricing, kept until the next publication time when the
+/// storage date changes. Concurrent loads of the same key share a single load, and
+/// failed loads are not cached.
+pub struct ExpiringCache<K, V, T: TimeProvider> {
+    time_provider: T,
+    entries: Mutex<HashMap<K, CacheEntry<V>>>,
+}
+
+impl<K: Eq + Hash, V: Clone, T: TimeProvider> ExpiringCache<K, V, T> {
+    pub fn new(time_provider: T) -> Self {
+        ExpiringCache {
+            time_provider,
+            entries: Mutex::new(HashMap::new()),
+        }
+    }
+
+    fn entry(&self, key: K) -> Arc<OnceCell<V>> {
+        let now = self.time_provider.now();
+        let mut entries = self.entries.lock().unwrap();
+
+        entries.retain(|_, entry| entry.expires_at > now);
+
+        entries
+            .entry(key)
+            .or_insert_with(|| CacheEntry {
+                value: Arc::new(OnceCell::new()),
+                expires_at: next_publication_time(now),
+            })
+            .value
+            .clone()
+    }
+
+    pub async fn get_or_try_load<E, F, Fut>(&self, key: K, load: F) -> Result<V, E>
+    where
+        F: FnOnce() -> Fut,
+        Fut: Future<Output = Result<V, E>>,
+    {
+        let entry = self.entry(key);
+        let value = entry.get_or_try_init(load).await?;
+
+        Ok(value.clone())
+    }
+
+    pub fn invalidate(&self, key: &K) {
+        self.entries.lock().unwrap().remove(key);
+    }
+}
+
+/// How long to wait before fetching again after a failed refresh
+const RETRY_BACKOFF: Duration = Duration::from_secs(5);
+
+/// A value fetched on first use and again once `refresh_interval` has passed, such as
+/// configuration kept outside the server. If a refresh fails the previous value is kept,
+/// and the fetch is retried after a short backoff.
+pub struct RefreshingCache<T> {
+    /// What is refreshed, for the logs
+    name: &'static str,
+    refresh_interval: Duration,
+    value: RwLock<Arc<T>>,
+    /// When the value was last fetched, and for how long that keeps it fresh
+    refreshed_at: Mutex<Option<(Instant, Duration)>>,
+    refreshing: tokio::sync::Mutex<()>,
+}
+
+impl<T> RefreshingCache<T> {
+    /// Starts with `initial` until the first fetch
+    pub fn new(name: &'static str, initial: T, refresh_interval: Duration) -> Self {
+        RefreshingCache {
+            name,
+            refresh_interval,
+            value: RwLock::new(Arc::new(initial)),
+            refreshed_at: Mutex::new(None),
+            refreshing: tokio::sync::Mutex::new(()),
+        }
+    }
+
+    /// The value, fetched again with `fetch` first if it's stale
+    pub async fn get<F, Fut, E>(&self, fetch: F) -> Arc<T>
+    where
+        F: FnOnce() -> Fut,
+        Fut: Future<Output = Result<T, E>>,
+        E: std::fmt::Display,
+    {
+        self.refresh_if_stale(fetch).await;
+
+        self.cached()
+    }
+
+    /// The value as it is, without refreshing it
+    pub fn cached(&self) -> Arc<T> {
+        self.value.read().unwrap().clone()
+    }
+
+    /// Makes the next [`RefreshingCache::get`] fetch the value again
+    pub fn invalidate(&self) {
+        *self.refreshed_at.lock().unwrap() = None;
+    }
+
+    fn is_fresh(&self) -> bool {
+        self.refreshed_at
+            .lock()
+            .unwrap()
+            .is_some_and(|(at, fresh_for)| at.elapsed() < fresh_for)
+    }
+
+    async fn refresh_if_stale<F, Fut, E>(&self, fetch: F)
+    where
+        F: FnOnce() -> Fut,
+        Fut: Future<Output = Result<T, E>>,
+        E: std::fmt::Display,
+    {
+        if self.is_fresh() {
+            return;
+        }
+
+        // Only one request refreshes, the others wait for it and use the new value
+        let _refreshing = self.refreshing.lock().await;
+        if self.is_fresh() {
+            return;
+        }
+
+        let fresh_for = match fetch().await {
+            Ok(value) => {
+                *self.value.write().unwrap() = Arc::new(value);
+                self.refresh_interval
+            }
+            Err(e) => {
+                error!("Failed to refresh {}: {}", self.name, e);
+                RETRY_BACKOFF.min(self.refresh_interval)
+            }
+        };
+
+        *self.refreshed_at.lock().unwrap() = Some((Instant::now(), fresh_for));
+    }
+}
+
+/// Caches the pricing of each zone and date in memory. Missing pricing is not cached,
+/// so the pricing is picked up as soon as the worker has stored it.
+pub struct CachedPricingRepository<T: TimeProvider> {
+    inner: Arc<dyn PricingRepository>,
+    cache: ExpiringCache<(BiddingZone, NaiveDate), PriceSeries<CentsPerKwh>, T>,
+}
+
+impl<T: TimeProvider> CachedPricingRepository<T> {
+    pub fn new(inner: Arc<dyn PricingRepository>, time_provider: T) -> Self {
+        CachedPricingRepository {
+            inner,
+            cache: ExpiringCache::new(time_provider),
+        }
+    }
+}
+
+#[async_trait]
+impl<T: TimeProvider + Send + Sync> PricingRepository for CachedPricingRepository<T> {
+    async fn get_pricing(
+        &self,
+        zone: &BiddingZone,
+        date: NaiveDate,
+    ) -> Result<Option<PriceSeries<CentsPerKwh>>, RepositoryError> {
+        let pricing = self
+            .cache
+            .get_or_try_load((*zone, date), || async {
+                // Missing pricing is returned as an error to leave the entry empty
+                match self.inner.get_pricing(zone, date).await {
+                    Ok(Some(pricing)) => Ok(pricing),
+                    Ok(None) => Err(None),
+                    Err(e) => Err(Some(e)),
+                }
+            })
+            .await;
+
+        match pricing {
+            Ok(pricing) => Ok(Some(pricing)),
+            Err(None) => Ok(None),
+            Err(Some(e)) => Err(e),
+        }
+    }
+
+    async fn store_pricing(
+        &self,
+        zone: &BiddingZone,
+        date: NaiveDate,
+        pricing: &PriceSeries<CentsPerKwh>,
+    ) -> Result<(), RepositoryError> {
+        self.inner.store_pricing(zone, date, pricing).await?;
+        self.cache.invalidate(&(*zone, date));
+
+        Ok(())
+    }
+
+    async fn get_delivery_days(
+        &self,
+        zone: &BiddingZone,
+        from: NaiveDate,
+        to: NaiveDate,
+    ) -> Result<Vec<DeliveryDay>, RepositoryError> {
+        self.inner.get_delivery_days(zone, from, to).await
+    }
+
+    async fn store_delivery_day(
+        &self,
+        zone: &BiddingZone,
+        day: &DeliveryDay,
+    ) -> Result<(), RepositoryError> {
+        self.inner.store_delivery_day(zone, day).await
+    }
+}
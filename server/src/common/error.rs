@@ -1,6 +1,6 @@
-use aws_sdk_dynamodb::operation::get_item::GetItemError;
 use aws_sdk_ssm::{error::SdkError, operation::get_parameters_by_path::GetParametersByPathError};
 use thiserror::Error;
+use wh_core::repository::RepositoryError;
 
 #[derive(Error, Debug)]
 pub enum ApplicationError {
@@ -8,8 +8,16 @@ pub enum ApplicationError {
     Api(#[from] reqwest::Error),
     #[error("Error parsing data: {0}")]
     Service(String),
-    #[error("DynamoDB Put Item operation failed: {0}")]
-    Database(#[from] Box<GetItemError>),
+    #[error("Pricing storage operation failed: {0}")]
+    Repository(#[from] RepositoryError),
     #[error("SSM Get Parameters operation failure: {0}")]
-    Ssm(#[from] SdkError<GetParametersByPathError>),
+    Ssm(#[from] Box<SdkError<GetParametersByPathError>>),
+    #[error("SSM operation failed: {0}")]
+    SsmOperation(#[from] Box<aws_sdk_ssm::Error>),
+    #[error("DynamoDB operation failed: {0}")]
+    DynamoDb(#[from] Box<aws_sdk_dynamodb::Error>),
+    #[error("Redis operation failed: {0}")]
+    Redis(#[from] redis::RedisError),
+    #[error("Failed to connect to Redis: {0}")]
+    RedisPool(#[from] deadpool_redis::PoolError),
 }
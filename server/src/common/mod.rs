@@ -1,2 +1,3 @@
-pub(crate) mod db;
+pub(crate) mod cache;
 pub(crate) mod error;
+pub(crate) mod query;
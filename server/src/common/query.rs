@@ -0,0 +1,69 @@
+use serde::Deserialize;
+use utoipa::IntoParams;
+
+use wh_core::api::FieldError;
+use wh_core::schedule::HeatingPeriod;
+
+/// `hours/start/end` of a heating period as sent, so that every invalid parameter can be
+/// reported instead of only the first one serde fails on
+#[derive(Deserialize, IntoParams)]
+pub struct PeriodQuery {
+    /// Number of hours in the period, at most the length of the period
+    #[param(value_type = u32, required = true, minimum = 1, maximum = 24)]
+    hours: Option<String>,
+    /// First hour of the period in 24h format
+    #[param(value_type = u32, required = true, minimum = 0, maximum = 23)]
+    start: Option<String>,
+    /// The hour when the period ends in 24h format, 24 is midnight. The period is the
+    /// whole day when the same as `start`.
+    #[param(value_type = u32, required = true, minimum = 0, maximum = 24)]
+    end: Option<String>,
+}
+
+fn parse_field(field: &str, value: &Option<String>, errors: &mut Vec<FieldError>) -> Option<u32> {
+    let Some(value) = value else {
+        errors.push(FieldError {
+            field: field.to_string(),
+            code: "missing".to_string(),
+            message: format!("{field} is required"),
+        });
+        return None;
+    };
+
+    match value.parse() {
+        Ok(value) => Some(value),
+        Err(_) => {
+            errors.push(FieldError {
+                field: field.to_string(),
+                code: "invalid".to_string(),
+                message: format!("{field} must be a non-negative integer, was {value:?}"),
+            });
+            None
+        }
+    }
+}
+
+impl PeriodQuery {
+    pub fn validate(&self) -> Result<HeatingPeriod, Vec<FieldError>> {
+        let mut errors = Vec::new();
+
+        let hours = parse_field("hours", &self.hours, &mut errors);
+        let start = parse_field("start", &self.start, &mut errors);
+        let end = parse_field("end", &self.end, &mut errors);
+
+        let (Some(hours), Some(start), Some(end)) = (hours, start, end) else {
+            return Err(errors);
+        };
+
+        HeatingPeriod::new(hours, start, end).map_err(|errors| {
+            errors
+                .into_iter()
+                .map(|error| FieldError {
+                    field: error.field().to_string(),
+                    code: "out_of_range".to_string(),
+                    message: error.to_string(),
+                })
+                .collect()
+        })
+    }
+}
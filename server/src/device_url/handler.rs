@@ -0,0 +1,99 @@
+use axum::{
+    extract::{Path, State},
+    http::{HeaderValue, StatusCode},
+    response::{IntoResponse, Response},
+    Json,
+};
+use chrono::Utc;
+use tracing::{error, info};
+
+use wh_core::api::{Period, ScheduleResponse};
+
+use crate::http::{error_response, TooManyRequests, Unauthorized};
+use crate::v2::fallback::FALLBACK_HEADER;
+use crate::v2::service::plan_device;
+use crate::AppState;
+
+/// Decision for the stored profile of a device. Like the v2 API the status is 200 when
+/// the device should be on and 400 when off, so the Shelly scripts only need the URL.
+#[utoipa::path(
+    get,
+    path = "/d/{device_id}",
+    responses(
+        (status = 200, description = "The device should be on", body = ScheduleResponse,
+            headers(("x-pricing-fallback" = String, description = "Fallback used when the published pricing was not available"))),
+        (status = 400, description = "The device should be off", body = ScheduleResponse,
+            headers(("x-pricing-fallback" = String, description = "Fallback used when the published pricing was not available"))),
+        (status = 401, response = Unauthorized),
+        (status = 404, description = "Unknown device, or the device has no profile", body = ErrorResponse),
+        (status = 429, response = TooManyRequests),
+        (status = 503, description = "No pricing or fallback available to decide from", body = ErrorResponse),
+    ),
+    params(("device_id" = String, Path, description = "Id of the device")),
+)]
+pub async fn handle_device_decision(
+    State(app_state): State<AppState>,
+    Path(device_id): Path<String>,
+) -> Response {
+    let device = match app_state.devices.get(&device_id).await {
+        Ok(Some(device)) => device,
+        Ok(None) => return error_response(StatusCode::NOT_FOUND, "Unknown device"),
+        Err(e) => {
+            error!("Failed to load device: {}", e);
+            return error_response(StatusCode::SERVICE_UNAVAILABLE, "Device not available");
+        }
+    };
+
+    let (Some(zone), Some(profile)) = (device.zone, &device.profile) else {
+        return error_response(StatusCode::NOT_FOUND, "Device has no profile");
+    };
+
+    let plan = match plan_device(
+        app_state.pricing_repository.as_ref(),
+        &app_state.decision_tables,
+        &app_state.pricing_fallback,
+        zone,
+        profile,
+        Utc::now(),
+    )
+    .await
+    {
+        Ok(plan) => plan,
+        Err(_) => {
+            return error_response(StatusCode::SERVICE_UNAVAILABLE, "Pricing not available");
+        }
+    };
+
+    info!(
+        device = device.id,
+        %zone,
+        enabled = plan.enabled,
+        "Device decision requested"
+    );
+
+    let status = if plan.enabled {
+        StatusCode::OK
+    } else {
+        StatusCode::BAD_REQUEST
+    };
+
+    let mut response = (
+        status,
+        Json(ScheduleResponse {
+            zone,
+            enabled: plan.enabled,
+            period: plan.period.map(|(start, end)| Period { start, end }),
+            next_change: plan.next_change,
+            fallback: plan.fallback.map(|fallback| fallback.to_string()),
+        }),
+    )
+        .into_response();
+
+    if let Some(fallback) = plan.fallback {
+        response
+            .headers_mut()
+            .insert(FALLBACK_HEADER, HeaderValue::from_static(fallback.as_str()));
+    }
+
+    response
+}
@@ -0,0 +1,2 @@
+pub(crate) mod handler;
+pub(crate) mod router;
@@ -0,0 +1,22 @@
+use axum::routing::{get, MethodRouter};
+use axum::Router;
+
+use crate::http::not_found;
+use crate::AppState;
+
+use super::handler::handle_device_decision;
+
+/// Routes of the short device URLs relative to `/d`, documented in the OpenAPI document
+/// like the versioned routes.
+pub(crate) fn routes() -> Vec<(&'static str, MethodRouter<AppState>)> {
+    vec![("/:device_id", get(handle_device_decision))]
+}
+
+pub fn device_url_routes() -> Router<AppState> {
+    routes()
+        .into_iter()
+        .fold(Router::new(), |router, (path, method_router)| {
+            router.route(path, method_router)
+        })
+        .fallback(not_found)
+}
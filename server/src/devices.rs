@@ -0,0 +1,254 @@
+use std::collections::HashMap;
+use std::fmt;
+use std::net::{IpAddr, SocketAddr};
+use std::sync::{Arc, RwLock};
+use std::time::{Duration, Instant};
+
+use axum::{
+    body::Body,
+    extract::{ConnectInfo, Query, State},
+    http::Request,
+    middleware::Next,
+    response::Response,
+};
+use chrono::Utc;
+use rand::distributions::Alphanumeric;
+use rand::Rng;
+use sha2::{Digest, Sha256};
+use tracing::{error, info, info_span, Instrument};
+use wh_core::api::DeviceRegistration;
+use wh_core::device::{Device, DeviceRepository};
+
+use crate::common::error::ApplicationError;
+use crate::http::unauthorized;
+use crate::rate_limit::limit_credentials_lookup;
+use crate::AppState;
+
+pub const DEVICE_TOKEN_HEADER: &str = "x-device-token";
+/// Like API keys, the token can also be in the query for Shelly scripts
+pub const DEVICE_TOKEN_QUERY_PARAM: &str = "device_token";
+
+const TOKEN_PREFIX: &str = "whd_";
+const TOKEN_LENGTH: usize = 40;
+const DEVICE_ID_LENGTH: usize = 12;
+
+// Lookups of the same token are cached, as devices poll every few minutes
+const CACHE_TTL: Duration = Duration::from_secs(300);
+
+/// Who requests are counted and logged for: the device when the request has a valid
+/// device token, otherwise the IP address. Requests with an API key are counted by the
+/// name of the key instead when rate limited.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum ClientId {
+    Device(String),
+    ApiKey(String),
+    Ip(IpAddr),
+}
+
+impl fmt::Display for ClientId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ClientId::Device(id) => write!(f, "device:{id}"),
+            ClientId::ApiKey(name) => write!(f, "key:{name}"),
+            ClientId::Ip(ip) => write!(f, "{ip}"),
+        }
+    }
+}
+
+pub(crate) fn random_string(length: usize) -> String {
+    rand::thread_rng()
+        .sample_iter(Alphanumeric)
+        .take(length)
+        .map(char::from)
+        .collect()
+}
+
+/// Tokens are stored as SHA-256 hashes, they are random enough not to need a salt
+pub fn hash_token(token: &str) -> String {
+    hex::encode(Sha256::digest(token.as_bytes()))
+}
+
+/// Devices of the valid tokens looked up. Unknown tokens are not cached, so that random
+/// tokens can't fill the cache, and expired entries are removed once per TTL.
+struct TokenCache {
+    /// Devices and when they were looked up, by the hash of the token
+    devices: HashMap<String, (Device, Instant)>,
+    pruned_at: Instant,
+}
+
+pub struct DeviceRegistry {
+    repository: Arc<dyn DeviceRepository>,
+    tokens: RwLock<TokenCache>,
+}
+
+impl DeviceRegistry {
+    pub fn new(repository: Arc<dyn DeviceRepository>) -> Self {
+        DeviceRegistry {
+            repository,
+            tokens: RwLock::new(TokenCache {
+                devices: HashMap::new(),
+                pruned_at: Instant::now(),
+            }),
+        }
+    }
+
+    /// Stores a new device and returns it with its token
+    pub async fn register(
+        &self,
+        registration: DeviceRegistration,
+    ) -> Result<(Device, String), ApplicationError> {
+        let token = format!("{TOKEN_PREFIX}{}", random_string(TOKEN_LENGTH));
+        let device = Device {
+            id: random_string(DEVICE_ID_LENGTH).to_lowercase(),
+            name: registration.name,
+            zone: registration.zone,
+            profile: registration.profile,
+            registered_at: Utc::now(),
+        };
+
+        self.repository
+            .store_device(&hash_token(&token), &device)
+            .await?;
+
+        info!(device = device.id, "Registered device");
+
+        Ok((device, token))
+    }
+
+    pub async fn get(&self, id: &str) -> Result<Option<Device>, ApplicationError> {
+        Ok(self.repository.get_device(id).await?)
+    }
+
+    /// Replaces the name, zone and profile of the device, `None` if it doesn't exist
+    pub async fn update(
+        &self,
+        id: &str,
+        registration: DeviceRegistration,
+    ) -> Result<Option<Device>, ApplicationError> {
+        let Some(device) = self.repository.get_device(id).await? else {
+            return Ok(None);
+        };
+
+        let device = Device {
+            name: registration.name,
+            zone: registration.zone,
+            profile: registration.profile,
+            ..device
+        };
+
+        if !self.repository.update_device(&device).await? {
+            return Ok(None);
+        }
+
+        info!(device = device.id, "Updated device");
+
+        Ok(Some(device))
+    }
+
+    pub async fn list(&self) -> Result<Vec<Device>, ApplicationError> {
+        Ok(self.repository.list_devices().await?)
+    }
+
+    /// Removes the device, returning false if it doesn't exist. Only the token lookups
+    /// cached by this instance are dropped, other instances keep accepting the token
+    /// until their cached lookup expires, within [`CACHE_TTL`].
+    pub async fn delete(&self, id: &str) -> Result<bool, ApplicationError> {
+        if !self.repository.delete_device(id).await? {
+            return Ok(false);
+        }
+
+        // Only hashes of the tokens are cached, so the entry of the device can't be found
+        self.tokens.write().unwrap().devices.clear();
+
+        info!(device = id, "Deleted device");
+
+        Ok(true)
+    }
+
+    /// The device of `token` if it has been looked up within the TTL
+    pub fn cached(&self, token: &str) -> Option<Device> {
+        let tokens = self.tokens.read().unwrap();
+        let (device, cached_at) = tokens.devices.get(&hash_token(token))?;
+
+        (cached_at.elapsed() < CACHE_TTL).then(|| device.clone())
+    }
+
+    /// The device of `token`, `None` if the token is unknown
+    pub async fn find_by_token(&self, token: &str) -> Result<Option<Device>, ApplicationError> {
+        if let Some(device) = self.cached(token) {
+            return Ok(Some(device));
+        }
+
+        let token_hash = hash_token(token);
+        let Some(device) = self.repository.get_device_by_token(&token_hash).await? else {
+            return Ok(None);
+        };
+
+        let mut tokens = self.tokens.write().unwrap();
+        if tokens.pruned_at.elapsed() >= CACHE_TTL {
+            tokens
+                .devices
+                .retain(|_, (_, cached_at)| cached_at.elapsed() < CACHE_TTL);
+            tokens.pruned_at = Instant::now();
+        }
+        tokens
+            .devices
+            .insert(token_hash, (device.clone(), Instant::now()));
+
+        Ok(Some(device))
+    }
+}
+
+fn device_token(request: &Request<Body>) -> Option<String> {
+    if let Some(token) = request
+        .headers()
+        .get(DEVICE_TOKEN_HEADER)
+        .and_then(|value| value.to_str().ok())
+    {
+        return Some(token.to_string());
+    }
+
+    Query::<HashMap<String, String>>::try_from_uri(request.uri())
+        .ok()?
+        .0
+        .remove(DEVICE_TOKEN_QUERY_PARAM)
+}
+
+/// Adds the [`ClientId`] of the request to its extensions and to the logs written while
+/// handling it. An unknown device token is rejected instead of falling back to the IP.
+pub async fn identify(
+    State(state): State<AppState>,
+    ConnectInfo(addr): ConnectInfo<SocketAddr>,
+    mut request: Request<Body>,
+    next: Next,
+) -> Response {
+    let client_id = match device_token(&request) {
+        Some(token) => {
+            // Tokens not cached cost a query each, so their lookups are limited by IP
+            if state.devices.cached(&token).is_none() {
+                if let Some(response) = limit_credentials_lookup(&state, addr.ip()).await {
+                    return response;
+                }
+            }
+
+            match state.devices.find_by_token(&token).await {
+                Ok(Some(device)) => ClientId::Device(device.id),
+                Ok(None) => {
+                    info!(path = request.uri().path(), "Invalid device token");
+                    return unauthorized("Invalid device token");
+                }
+                Err(e) => {
+                    // Counted by IP rather than failing the request when storage is down
+                    error!("Failed to look up device token: {}", e);
+                    ClientId::Ip(addr.ip())
+                }
+            }
+        }
+        None => ClientId::Ip(addr.ip()),
+    };
+
+    let span = info_span!("request", client = %client_id);
+    request.extensions_mut().insert(client_id);
+
+    next.run(request).instrument(span).await
+}
@@ -1,9 +1,106 @@
+use axum::response::{IntoResponse, Response};
 use axum::Json;
 use reqwest::StatusCode;
+use utoipa::ToResponse;
+use wh_core::api::{ErrorResponse, FieldError};
 
-pub async fn not_found() -> (StatusCode, Json<serde_json::Value>) {
+/// The path does not exist
+#[derive(ToResponse)]
+#[response(example = json!({ "status": "Not Found" }))]
+pub struct NotFound(ErrorResponse);
+
+/// The API key is invalid, or the route requires one. Keys are sent in the `x-api-key`
+/// header or the `api_key` query parameter.
+#[derive(ToResponse)]
+#[response(example = json!({ "status": "Unauthorized", "message": "API key required" }))]
+pub struct Unauthorized(pub ErrorResponse);
+
+/// The client has run out of requests. Clients are identified by their API key or device
+/// token, or by their IP address without either. Allowed requests carry the same
+/// `ratelimit-*` headers.
+#[derive(ToResponse)]
+#[response(
+    content_type = "text/plain",
+    example = json!("Too Many Requests"),
+    headers(
+        ("retry-after" = u64, description = "Seconds until the next request is allowed"),
+        ("ratelimit-limit" = u32, description = "Requests that can be made at once"),
+        ("ratelimit-remaining" = u32, description = "Requests left"),
+        ("ratelimit-reset" = u64, description = "Seconds until all requests are available again")
+    )
+)]
+pub struct TooManyRequests(pub String);
+
+impl IntoResponse for NotFound {
+    fn into_response(self) -> Response {
+        (StatusCode::NOT_FOUND, Json(self.0)).into_response()
+    }
+}
+
+impl IntoResponse for Unauthorized {
+    fn into_response(self) -> Response {
+        (StatusCode::UNAUTHORIZED, Json(self.0)).into_response()
+    }
+}
+
+impl IntoResponse for TooManyRequests {
+    fn into_response(self) -> Response {
+        (StatusCode::TOO_MANY_REQUESTS, self.0).into_response()
+    }
+}
+
+/// JSON error body with the reason phrase of `status`
+pub fn error_response(status: StatusCode, message: &str) -> Response {
+    (
+        status,
+        Json(ErrorResponse {
+            status: status.canonical_reason().unwrap_or_default().to_string(),
+            message: Some(message.to_string()),
+            errors: Vec::new(),
+        }),
+    )
+        .into_response()
+}
+
+/// 401 with the reason the request was not accepted
+pub fn unauthorized(message: &str) -> Response {
+    Unauthorized(ErrorResponse {
+        status: "Unauthorized".to_string(),
+        message: Some(message.to_string()),
+        errors: Vec::new(),
+    })
+    .into_response()
+}
+
+fn bad_request(message: &str, errors: Vec<FieldError>) -> Response {
     (
-        StatusCode::NOT_FOUND,
-        Json(serde_json::json!({ "status": "Not Found" })),
+        StatusCode::BAD_REQUEST,
+        Json(ErrorResponse {
+            status: StatusCode::BAD_REQUEST
+                .canonical_reason()
+                .unwrap_or_default()
+                .to_string(),
+            message: Some(message.to_string()),
+            errors,
+        }),
     )
+        .into_response()
+}
+
+/// 400 listing the invalid parameters of the request
+pub fn validation_error(errors: Vec<FieldError>) -> Response {
+    bad_request("Invalid query parameters", errors)
+}
+
+/// 400 listing the invalid fields of the request body
+pub fn body_validation_error(errors: Vec<FieldError>) -> Response {
+    bad_request("Invalid request body", errors)
+}
+
+pub async fn not_found() -> NotFound {
+    NotFound(ErrorResponse {
+        status: "Not Found".to_string(),
+        message: None,
+        errors: Vec::new(),
+    })
 }
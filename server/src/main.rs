@@ -11,17 +11,56 @@ use deadpool_redis::{Config, Pool, Runtime};
 use lazy_static::lazy_static;
 use utoipa::OpenApi;
 use utoipa_swagger_ui::SwaggerUi;
+use wh_core::device::dynamodb::{DynamoDeviceRepository, DEVICE_TABLE_NAME};
+use wh_core::repository::dynamodb::{
+    DynamoPricingRepository, PRICE_ARCHIVE_TABLE_NAME, PRICING_TABLE_NAME,
+};
+use wh_core::repository::PricingRepository;
+use wh_core::time_provider::SystemTimeProvider;
 
-use crate::rate_limit::rate_limit;
+use crate::admin::router::admin_routes;
+use crate::admin::{api_keys as admin_api_keys, devices as admin_devices, rate_limits};
+use crate::auth::{
+    api_key_refresh_interval, authenticate, Auth, AuthPolicy, SecurityAddon, ADMIN_PATH,
+};
+use crate::client_ip::{inject_client_ip, TrustedProxies};
+use crate::common::cache::CachedPricingRepository;
+use crate::device_url::handler as device_decision;
+use crate::device_url::router::device_url_routes;
+use crate::devices::{identify, DeviceRegistry};
+use crate::rate_limit::dynamodb::{DynamoRateLimiter, RATE_LIMIT_TABLE_NAME};
+use crate::rate_limit::fallback::FallbackRateLimiter;
+use crate::rate_limit::memory::InMemoryRateLimiter;
+use crate::rate_limit::policy::PolicyCache;
+use crate::rate_limit::redis::RedisRateLimiter;
+use crate::rate_limit::{rate_limit, RateLimitConfig, RateLimitStore, RateLimiter};
+use crate::secrets::{
+    ApiKeyCache, SsmApiKeySource, StaticApiKeySource, ADMIN_KEYS_PATH, API_KEYS_PATH,
+};
+use crate::standalone::StandaloneConfig;
+use crate::v2::decision_table::DecisionTables;
+use crate::v2::fallback::{FallbackPolicy, PricingFallback};
 use crate::v2::handler as waterheater_calc;
+use crate::v2::prices;
 use crate::v2::router::v2_routes;
+use crate::v3::devices as device_registration;
+use crate::v3::handler as schedule;
+use crate::v3::router::v3_routes;
 
+mod admin;
+mod auth;
+mod client_ip;
 mod common;
+mod device_url;
+mod devices;
 mod http;
-mod middleware;
 mod rate_limit;
+mod scheduler;
+mod secrets;
+mod standalone;
 mod tests;
 mod v2;
+mod v3;
 
 lazy_static! {
     static ref REDIS_POOL: Arc<Pool> = Arc::new(create_redis_pool());
@@ -29,8 +68,14 @@ lazy_static! {
 
 #[derive(Clone)]
 struct AppState {
-    pub redis_pool: Arc<Pool>,
-    dynamo_client: aws_sdk_dynamodb::Client,
+    pub rate_limiter: Arc<dyn RateLimiter>,
+    rate_limit_policy: Arc<PolicyCache>,
+    pricing_repository: Arc<dyn PricingRepository>,
+    decision_tables: Arc<DecisionTables>,
+    pricing_fallback: Arc<PricingFallback>,
+    auth: Arc<Auth>,
+    devices: Arc<DeviceRegistry>,
+    trusted_proxies: Arc<TrustedProxies>,
 }
 
 fn create_redis_pool() -> Pool {
@@ -50,6 +95,110 @@ fn create_redis_pool() -> Pool {
     cfg.create_pool(Some(Runtime::Tokio1)).unwrap()
 }
 
+async fn create_rate_limiter(config: RateLimitConfig) -> Arc<dyn RateLimiter> {
+    match config.store {
+        RateLimitStore::Redis => Arc::new(FallbackRateLimiter::new(
+            Arc::new(RedisRateLimiter::new(REDIS_POOL.clone(), config.algorithm)),
+            config.breaker,
+        )),
+        RateLimitStore::DynamoDb => Arc::new(FallbackRateLimiter::new(
+            Arc::new(DynamoRateLimiter::new(
+                aws_sdk_dynamodb::Client::new(&aws_config::load_from_env().await),
+                env::var("RATE_LIMIT_TABLE_NAME").unwrap_or(RATE_LIMIT_TABLE_NAME.into()),
+                config.algorithm,
+            )),
+            config.breaker,
+        )),
+        RateLimitStore::Memory => Arc::new(InMemoryRateLimiter::new(config.algorithm)),
+    }
+}
+
+#[derive(OpenApi)]
+#[openapi(
+    paths(
+        waterheater_calc::handle_enable_water_heater,
+        prices::handle_get_prices,
+        schedule::handle_get_schedule,
+        device_registration::handle_register_device,
+        device_registration::handle_update_device,
+        device_decision::handle_device_decision,
+        admin_api_keys::handle_list_api_keys,
+        admin_api_keys::handle_create_api_key,
+        admin_api_keys::handle_delete_api_key,
+        admin_devices::handle_list_devices,
+        admin_devices::handle_get_device,
+        admin_devices::handle_replace_device,
+        admin_devices::handle_delete_device,
+        rate_limits::handle_list_rate_limits,
+        rate_limits::handle_set_rate_limit,
+        rate_limits::handle_delete_rate_limit,
+        rate_limits::handle_get_usage
+    ),
+    components(
+        schemas(
+            wh_core::types::BiddingZone,
+            wh_core::units::Currency,
+            wh_core::api::PriceRangeResponse,
+            wh_core::api::DeliveryDayPrices,
+            wh_core::api::PriceSlot,
+            wh_core::api::ScheduleResponse,
+            wh_core::api::Period,
+            wh_core::api::DeviceRegistration,
+            wh_core::api::RegisteredDevice,
+            wh_core::api::DeviceInfo,
+            wh_core::device::DeviceProfile,
+            wh_core::device::DeviceMode,
+            wh_core::tariff::Tariff,
+            wh_core::api::ApiKeyRequest,
+            wh_core::api::ApiKeyInfo,
+            wh_core::api::CreatedApiKey,
+            wh_core::api::RateLimitOverride,
+            wh_core::api::ClientRateLimit,
+            wh_core::api::ClientUsage,
+            wh_core::api::ErrorResponse
+        ),
+        responses(http::NotFound, http::Unauthorized, http::TooManyRequests)
+    ),
+    modifiers(&SecurityAddon),
+    security((), ("api_key" = []), ("api_key_query" = [])),
+    tags(
+        (name = "waterheater_calc", description = "Easy-to-use API designed to be used with ready-made Shelly scripts for controlling
+            for example a waterheater to be turned on at certain hours of the day.")
+    )
+)]
+struct ApiDoc;
+
+fn create_app(state: AppState) -> Router {
+    Router::new()
+        .nest("/api/v2", v2_routes())
+        .nest("/api/v3", v3_routes())
+        .nest("/d", device_url_routes())
+        .nest(ADMIN_PATH, admin_routes())
+        .merge(
+            SwaggerUi::new("/api/v2/swagger-ui").url("/api-docs/openapi.json", ApiDoc::openapi()),
+        )
+        .layer(
+            ServiceBuilder::new()
+                .layer(axum::middleware::from_fn_with_state(
+                    state.clone(),
+                    inject_client_ip,
+                ))
+                .layer(axum::middleware::from_fn_with_state(
+                    state.clone(),
+                    identify,
+                ))
+                .layer(axum::middleware::from_fn_with_state(
+                    state.clone(),
+                    authenticate,
+                ))
+                .layer(axum::middleware::from_fn_with_state(
+                    state.clone(),
+                    rate_limit,
+                )),
+        )
+        .with_state(state)
+}
+
 #[tokio::main]
 async fn main() -> Result<(), Error> {
     set_var("AWS_LAMBDA_HTTP_IGNORE_STAGE_IN_PATH", "true");
@@ -62,41 +211,92 @@ async fn main() -> Result<(), Error> {
         .with_target(false)
         .init();
 
+    // Running outside of Lambda, e.g. on a home server. In all-in-one mode the pricing
+    // is also fetched by the same process instead of the worker Lambda.
+    let runtime = env::var("SERVER_RUNTIME").unwrap_or_default();
+
+    if runtime == "standalone" || runtime == "all-in-one" {
+        let config = StandaloneConfig::from_env()?;
+        let pricing_repository: Arc<dyn PricingRepository> = Arc::new(
+            CachedPricingRepository::new(config.pricing_repository()?, SystemTimeProvider),
+        );
+
+        if runtime == "all-in-one" {
+            tokio::spawn(scheduler::run_price_fetch_scheduler(
+                pricing_repository.clone(),
+                config.fetch_time,
+            ));
+        }
+
+        let state = AppState {
+            rate_limiter: create_rate_limiter(RateLimitConfig::from_env(RateLimitStore::Memory)?)
+                .await,
+            rate_limit_policy: Arc::new(PolicyCache::from_env(None)?),
+            pricing_repository,
+            decision_tables: Arc::new(DecisionTables::new(SystemTimeProvider)),
+            pricing_fallback: Arc::new(PricingFallback::new(FallbackPolicy::from_env()?)),
+            auth: Arc::new(Auth {
+                api_keys: ApiKeyCache::new(
+                    StaticApiKeySource::new(config.api_keys.clone()),
+                    api_key_refresh_interval()?,
+                ),
+                admin_keys: ApiKeyCache::new(
+                    StaticApiKeySource::new(config.admin_api_keys.clone()),
+                    api_key_refresh_interval()?,
+                ),
+                policy: AuthPolicy::from_env()?,
+            }),
+            devices: Arc::new(DeviceRegistry::new(config.device_repository()?)),
+            trusted_proxies: Arc::new(TrustedProxies::from_env()?),
+        };
+
+        return standalone::serve(create_app(state), config.port).await;
+    }
+
     let config = aws_config::load_from_env().await;
     let client = aws_sdk_dynamodb::Client::new(&config);
 
+    let table_name = env::var("PRICING_TABLE_NAME").unwrap_or(PRICING_TABLE_NAME.into());
+    let archive_table_name =
+        env::var("PRICE_ARCHIVE_TABLE_NAME").unwrap_or(PRICE_ARCHIVE_TABLE_NAME.into());
+
     let state = AppState {
-        redis_pool: REDIS_POOL.clone(),
-        dynamo_client: client,
+        rate_limiter: create_rate_limiter(RateLimitConfig::from_env(RateLimitStore::Redis)?).await,
+        rate_limit_policy: Arc::new(PolicyCache::from_env(Some(aws_sdk_ssm::Client::new(
+            &config,
+        )))?),
+        pricing_repository: Arc::new(CachedPricingRepository::new(
+            Arc::new(
+                DynamoPricingRepository::new(client.clone(), table_name)
+                    .with_archive_table(archive_table_name),
+            ),
+            SystemTimeProvider,
+        )),
+        decision_tables: Arc::new(DecisionTables::new(SystemTimeProvider)),
+        pricing_fallback: Arc::new(PricingFallback::new(FallbackPolicy::from_env()?)),
+        auth: Arc::new(Auth {
+            api_keys: ApiKeyCache::new(
+                SsmApiKeySource::new(
+                    aws_sdk_ssm::Client::new(&config),
+                    env::var("API_KEYS_SSM_PATH").unwrap_or(API_KEYS_PATH.into()),
+                ),
+                api_key_refresh_interval()?,
+            ),
+            admin_keys: ApiKeyCache::new(
+                SsmApiKeySource::new(
+                    aws_sdk_ssm::Client::new(&config),
+                    env::var("ADMIN_KEYS_SSM_PATH").unwrap_or(ADMIN_KEYS_PATH.into()),
+                ),
+                api_key_refresh_interval()?,
+            ),
+            policy: AuthPolicy::from_env()?,
+        }),
+        devices: Arc::new(DeviceRegistry::new(Arc::new(DynamoDeviceRepository::new(
+            client,
+            env::var("DEVICE_TABLE_NAME").unwrap_or(DEVICE_TABLE_NAME.into()),
+        )))),
+        trusted_proxies: Arc::new(TrustedProxies::from_env()?),
     };
 
-    #[derive(OpenApi)]
-    #[openapi(
-        paths(waterheater_calc::handle_enable_water_heater),
-        components(
-            schemas(wh_core::types::BiddingZone)
-        ),
-        tags(
-            (name = "waterheater_calc", description = "Easy-to-use API designed to be used with ready-made Shelly scripts for controlling
-                for example a waterheater to be turned on at certain hours of the day.")
-        )
-    )]
-    struct ApiDoc;
-
-    let app = Router::new()
-        .nest("/api/v2", v2_routes())
-        .merge(
-            SwaggerUi::new("/api/v2/swagger-ui").url("/api-docs/openapi.json", ApiDoc::openapi()),
-        )
-        .layer(
-            ServiceBuilder::new()
-                .layer(axum::middleware::from_fn(middleware::inject_connect_info))
-                .layer(axum::middleware::from_fn_with_state(
-                    state.clone(),
-                    rate_limit,
-                )),
-        )
-        .with_state(state);
-
-    run(app).await
+    run(create_app(state)).await
 }
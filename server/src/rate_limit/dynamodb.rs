@@ -0,0 +1,332 @@
+use std::collections::HashMap;
+
+use async_trait::async_trait;
+use aws_sdk_dynamodb::types::{AttributeValue, KeysAndAttributes, ReturnValue};
+use chrono::Utc;
+use wh_core::api::{ClientRateLimit, ClientUsage};
+
+use crate::common::error::ApplicationError;
+use crate::devices::ClientId;
+
+use super::{
+    client_rate_limit, client_usage, is_overridable, state_key, Algorithm, LimitState, Quota,
+    RateLimitDecision, RateLimiter, STATE_TTL_SECONDS,
+};
+
+pub const RATE_LIMIT_TABLE_NAME: &str = "waterheater_calc_rate_limits";
+
+const OVERRIDE_PREFIX: &str = "override:";
+// A client racing itself this often is over its limit anyway
+const MAX_WRITE_ATTEMPTS: u32 = 3;
+
+/// Stores one item per client keyed by `client_id`, removed by the `expires_at` TTL
+/// an hour after the last request. Overrides are items keyed by `override:<client>`.
+/// The state is read and written back on the condition that no other request of the
+/// client wrote it in between.
+///
+/// The state and the override of the client are read in one batch, so a request costs
+/// two round trips, a read and a conditional write, and another two for each concurrent
+/// request of the client it conflicts with. Redis counts a request in one.
+pub struct DynamoRateLimiter {
+    client: aws_sdk_dynamodb::Client,
+    table_name: String,
+    algorithm: Algorithm,
+}
+
+impl DynamoRateLimiter {
+    pub fn new(
+        client: aws_sdk_dynamodb::Client,
+        table_name: impl Into<String>,
+        algorithm: Algorithm,
+    ) -> Self {
+        DynamoRateLimiter {
+            client,
+            table_name: table_name.into(),
+            algorithm,
+        }
+    }
+
+    fn algorithm_name(&self) -> &'static str {
+        match self.algorithm {
+            Algorithm::TokenBucket => "token-bucket",
+            Algorithm::SlidingWindow => "sliding-window",
+        }
+    }
+
+    async fn get_item(
+        &self,
+        client_id: String,
+    ) -> Result<Option<HashMap<String, AttributeValue>>, ApplicationError> {
+        let output = self
+            .client
+            .get_item()
+            .table_name(&self.table_name)
+            .key("client_id", AttributeValue::S(client_id))
+            .consistent_read(true)
+            .send()
+            .await
+            .map_err(|e| ApplicationError::DynamoDb(Box::new(e.into())))?;
+
+        Ok(output.item)
+    }
+
+    /// Items of `client_ids` that exist, by client id, in one consistent batch read
+    async fn get_items(
+        &self,
+        client_ids: &[&str],
+    ) -> Result<HashMap<String, HashMap<String, AttributeValue>>, ApplicationError> {
+        let keys = client_ids
+            .iter()
+            .map(|client_id| {
+                HashMap::from([(
+                    "client_id".to_string(),
+                    AttributeValue::S(client_id.to_string()),
+                )])
+            })
+            .collect();
+        let keys = KeysAndAttributes::builder()
+            .set_keys(Some(keys))
+            .consistent_read(true)
+            .build()
+            .map_err(|e| ApplicationError::DynamoDb(Box::new(e.into())))?;
+
+        let output = self
+            .client
+            .batch_get_item()
+            .request_items(&self.table_name, keys)
+            .send()
+            .await
+            .map_err(|e| ApplicationError::DynamoDb(Box::new(e.into())))?;
+
+        let mut items = HashMap::new();
+        for item in output
+            .responses
+            .into_iter()
+            .flat_map(|r| r.into_values())
+            .flatten()
+        {
+            if let Some(client_id) = string(&item, "client_id") {
+                items.insert(client_id.to_string(), item);
+            }
+        }
+
+        // Keys left unprocessed by a throttled batch are read one at a time
+        let unprocessed = output
+            .unprocessed_keys
+            .into_iter()
+            .flat_map(|keys| keys.into_values())
+            .flat_map(|keys| keys.keys)
+            .filter_map(|key| key.get("client_id")?.as_s().ok().cloned());
+        for client_id in unprocessed {
+            if let Some(item) = self.get_item(client_id.clone()).await? {
+                items.insert(client_id, item);
+            }
+        }
+
+        Ok(items)
+    }
+
+    /// Every item of the table, overrides included
+    async fn scan(&self) -> Result<Vec<HashMap<String, AttributeValue>>, ApplicationError> {
+        let mut items = Vec::new();
+        let mut exclusive_start_key = None;
+
+        loop {
+            let output = self
+                .client
+                .scan()
+                .table_name(&self.table_name)
+                .set_exclusive_start_key(exclusive_start_key)
+                .send()
+                .await
+                .map_err(|e| ApplicationError::DynamoDb(Box::new(e.into())))?;
+
+            items.extend(output.items().iter().cloned());
+
+            exclusive_start_key = output.last_evaluated_key;
+            if exclusive_start_key.is_none() {
+                return Ok(items);
+            }
+        }
+    }
+
+    /// State of the client, `None` when it was counted with another algorithm
+    fn parse_state(&self, item: &HashMap<String, AttributeValue>) -> Option<LimitState> {
+        if string(item, "algorithm")? != self.algorithm_name() {
+            return None;
+        }
+
+        Some(LimitState {
+            count: number(item, "count")?,
+            window_start: number(item, "window_start")?,
+            previous_count: number(item, "previous_count")?,
+            remaining: number(item, "remaining")?,
+            requests: number(item, "requests")? as u64,
+            last_request: number(item, "last_request")?,
+        })
+    }
+}
+
+fn string<'a>(item: &'a HashMap<String, AttributeValue>, name: &str) -> Option<&'a str> {
+    item.get(name)?.as_s().ok().map(String::as_str)
+}
+
+fn number(item: &HashMap<String, AttributeValue>, name: &str) -> Option<f64> {
+    item.get(name)?.as_n().ok()?.parse().ok()
+}
+
+fn parse_quota(item: &HashMap<String, AttributeValue>) -> Option<Quota> {
+    Some(Quota {
+        capacity: number(item, "capacity")? as u32,
+        refill_per_minute: number(item, "refill_per_minute")?,
+    })
+}
+
+fn n(value: impl ToString) -> AttributeValue {
+    AttributeValue::N(value.to_string())
+}
+
+#[async_trait]
+impl RateLimiter for DynamoRateLimiter {
+    async fn check(
+        &self,
+        client: &ClientId,
+        route: Option<&str>,
+        mut quota: Quota,
+        now: f64,
+    ) -> Result<RateLimitDecision, ApplicationError> {
+        let key = state_key(client, route);
+        let override_key = format!("{OVERRIDE_PREFIX}{client}");
+
+        let mut item = if is_overridable(route) {
+            let mut items = self.get_items(&[&key, &override_key]).await?;
+            if let Some(limit) = items.get(&override_key).and_then(parse_quota) {
+                quota = limit;
+            }
+            items.remove(&key)
+        } else {
+            self.get_item(key.clone()).await?
+        };
+        let mut attempts = 0;
+
+        loop {
+            attempts += 1;
+            let state = item.as_ref().and_then(|item| self.parse_state(item));
+            let (decision, next) = self.algorithm.apply(state, quota, now);
+
+            let request = self
+                .client
+                .put_item()
+                .table_name(&self.table_name)
+                .item("client_id", AttributeValue::S(key.clone()))
+                .item("algorithm", AttributeValue::S(self.algorithm_name().into()))
+                .item("count", n(next.count))
+                .item("window_start", n(next.window_start))
+                .item("previous_count", n(next.previous_count))
+                .item("remaining", n(next.remaining))
+                .item("requests", n(next.requests))
+                .item("last_request", n(next.last_request))
+                .item("expires_at", n((now + STATE_TTL_SECONDS) as i64));
+
+            let request = match item.as_ref().and_then(|item| item.get("last_request")) {
+                Some(last_request) => request
+                    .condition_expression("last_request = :last_request")
+                    .expression_attribute_values(":last_request", last_request.clone()),
+                None => request.condition_expression("attribute_not_exists(client_id)"),
+            };
+
+            match request.send().await {
+                Ok(_) => return Ok(decision),
+                Err(e)
+                    if e.as_service_error()
+                        .is_some_and(|e| e.is_conditional_check_failed_exception()) =>
+                {
+                    // Too many concurrent requests of the client, deny rather than count
+                    // the request twice
+                    if attempts == MAX_WRITE_ATTEMPTS {
+                        return Ok(RateLimitDecision {
+                            allowed: false,
+                            ..decision
+                        });
+                    }
+                    item = self.get_item(key.clone()).await?;
+                }
+                Err(e) => return Err(ApplicationError::DynamoDb(Box::new(e.into()))),
+            }
+        }
+    }
+
+    async fn overrides(&self) -> Result<Vec<ClientRateLimit>, ApplicationError> {
+        let mut overrides: Vec<ClientRateLimit> = self
+            .scan()
+            .await?
+            .iter()
+            .filter_map(|item| {
+                let client = string(item, "client_id")?.strip_prefix(OVERRIDE_PREFIX)?;
+                Some(client_rate_limit(client.to_string(), parse_quota(item)?))
+            })
+            .collect();
+
+        overrides.sort_by(|a, b| a.client.cmp(&b.client));
+
+        Ok(overrides)
+    }
+
+    async fn set_override(&self, client: &str, quota: Quota) -> Result<(), ApplicationError> {
+        self.client
+            .put_item()
+            .table_name(&self.table_name)
+            .item(
+                "client_id",
+                AttributeValue::S(format!("{OVERRIDE_PREFIX}{client}")),
+            )
+            .item("capacity", n(quota.capacity))
+            .item("refill_per_minute", n(quota.refill_per_minute))
+            .send()
+            .await
+            .map_err(|e| ApplicationError::DynamoDb(Box::new(e.into())))?;
+
+        Ok(())
+    }
+
+    async fn delete_override(&self, client: &str) -> Result<bool, ApplicationError> {
+        let output = self
+            .client
+            .delete_item()
+            .table_name(&self.table_name)
+            .key(
+                "client_id",
+                AttributeValue::S(format!("{OVERRIDE_PREFIX}{client}")),
+            )
+            .return_values(ReturnValue::AllOld)
+            .send()
+            .await
+            .map_err(|e| ApplicationError::DynamoDb(Box::new(e.into())))?;
+
+        Ok(output.attributes.is_some())
+    }
+
+    async fn usage(&self) -> Result<Vec<ClientUsage>, ApplicationError> {
+        // Expired items are removed by DynamoDB only within a couple of days
+        let now = Utc::now().timestamp_millis() as f64 / 1000.0;
+
+        let mut usage: Vec<ClientUsage> = self
+            .scan()
+            .await?
+            .iter()
+            .filter_map(|item| {
+                let client = string(item, "client_id")?;
+                if client.starts_with(OVERRIDE_PREFIX) {
+                    return None;
+                }
+                let state = self.parse_state(item)?;
+                (now - state.last_request < STATE_TTL_SECONDS)
+                    .then(|| client_usage(client.to_string(), &state))
+            })
+            .collect();
+
+        usage.sort_by(|a, b| a.client.cmp(&b.client));
+
+        Ok(usage)
+    }
+}
@@ -0,0 +1,246 @@
+//! Degraded mode for when the shared store is unavailable. After enough failed checks in
+//! a row the circuit opens: each instance then counts requests in its own memory, without
+//! waiting for the store, until a check after the cooldown succeeds again.
+//!
+//! While the store is available its overrides are copied to the memory every minute, so
+//! that they keep applying while it's not. The copy runs in a task of its own, no request
+//! waits for it.
+
+use std::env;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use async_trait::async_trait;
+use lambda_http::Error;
+use tracing::{error, info, warn};
+use wh_core::api::{ClientRateLimit, ClientUsage};
+
+use crate::common::error::ApplicationError;
+use crate::devices::ClientId;
+
+use super::memory::InMemoryRateLimiter;
+use super::{Algorithm, Quota, RateLimitDecision, RateLimiter};
+
+const DEFAULT_FAILURE_THRESHOLD: u32 = 3;
+const DEFAULT_COOLDOWN_SECONDS: f64 = 30.0;
+const DEFAULT_TIMEOUT_MILLIS: u64 = 500;
+const OVERRIDES_SYNC_SECONDS: f64 = 60.0;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct BreakerConfig {
+    /// Failed checks in a row that open the circuit
+    pub failure_threshold: u32,
+    /// Seconds the store is left alone once the circuit is open
+    pub cooldown_seconds: f64,
+    /// Checks taking longer count as failed
+    pub timeout: Duration,
+}
+
+impl Default for BreakerConfig {
+    fn default() -> Self {
+        BreakerConfig {
+            failure_threshold: DEFAULT_FAILURE_THRESHOLD,
+            cooldown_seconds: DEFAULT_COOLDOWN_SECONDS,
+            timeout: Duration::from_millis(DEFAULT_TIMEOUT_MILLIS),
+        }
+    }
+}
+
+impl BreakerConfig {
+    /// `RATE_LIMIT_BREAKER_FAILURES`, `RATE_LIMIT_BREAKER_COOLDOWN_SECONDS` and
+    /// `RATE_LIMIT_STORE_TIMEOUT_MS`
+    pub fn from_env() -> Result<Self, Error> {
+        let default = BreakerConfig::default();
+
+        Ok(BreakerConfig {
+            failure_threshold: match env::var("RATE_LIMIT_BREAKER_FAILURES") {
+                Ok(failures) => failures.parse()?,
+                Err(_) => default.failure_threshold,
+            },
+            cooldown_seconds: match env::var("RATE_LIMIT_BREAKER_COOLDOWN_SECONDS") {
+                Ok(seconds) => seconds.parse()?,
+                Err(_) => default.cooldown_seconds,
+            },
+            timeout: match env::var("RATE_LIMIT_STORE_TIMEOUT_MS") {
+                Ok(millis) => Duration::from_millis(millis.parse()?),
+                Err(_) => default.timeout,
+            },
+        })
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Circuit {
+    /// The store is used, counting the failed checks in a row
+    Closed { failures: u32 },
+    /// The store is not used before `until`
+    Open { until: f64 },
+    /// A single check started at `since` tries the store again, the others use the
+    /// memory until it has succeeded
+    HalfOpen { since: f64 },
+}
+
+/// Checks the limits in `store`, or in the memory of the process while the store fails.
+/// The counts are not carried over, a client gets its whole quota in each instance when
+/// the circuit opens. Overrides and usage are always read from the store.
+pub struct FallbackRateLimiter {
+    store: Arc<dyn RateLimiter>,
+    memory: Arc<InMemoryRateLimiter>,
+    config: BreakerConfig,
+    circuit: Mutex<Circuit>,
+    /// When the overrides of the store were last copied to the memory
+    overrides_synced_at: Mutex<Option<f64>>,
+}
+
+impl FallbackRateLimiter {
+    pub fn new(store: Arc<dyn RateLimiter>, config: BreakerConfig) -> Self {
+        FallbackRateLimiter {
+            store,
+            memory: Arc::new(InMemoryRateLimiter::new(Algorithm::TokenBucket)),
+            config,
+            circuit: Mutex::new(Circuit::Closed { failures: 0 }),
+            overrides_synced_at: Mutex::new(None),
+        }
+    }
+
+    /// Whether the check made at `now` should go to the store
+    fn use_store(&self, now: f64) -> bool {
+        let mut circuit = self.circuit.lock().unwrap();
+
+        match *circuit {
+            Circuit::Closed { .. } => true,
+            Circuit::Open { until } if now < until => false,
+            // Another check is trying the store, unless it has not finished in a cooldown
+            Circuit::HalfOpen { since } if now < since + self.config.cooldown_seconds => false,
+            Circuit::Open { .. } | Circuit::HalfOpen { .. } => {
+                *circuit = Circuit::HalfOpen { since: now };
+                true
+            }
+        }
+    }
+
+    /// Starts copying the overrides of the store to the memory in the background, if they
+    /// were not copied within the last minute by another check
+    fn sync_overrides(&self, now: f64) {
+        {
+            let mut synced_at = self.overrides_synced_at.lock().unwrap();
+            if synced_at.is_some_and(|at| now - at < OVERRIDES_SYNC_SECONDS) {
+                return;
+            }
+            *synced_at = Some(now);
+        }
+
+        let store = self.store.clone();
+        let memory = self.memory.clone();
+        let timeout = self.config.timeout;
+
+        tokio::spawn(async move {
+            copy_overrides(store.as_ref(), &memory, timeout).await;
+        });
+    }
+
+    fn succeeded(&self) {
+        let mut circuit = self.circuit.lock().unwrap();
+
+        if !matches!(*circuit, Circuit::Closed { .. }) {
+            info!("Rate limit store recovered, closing the circuit");
+        }
+        *circuit = Circuit::Closed { failures: 0 };
+    }
+
+    fn failed(&self, now: f64) {
+        let mut circuit = self.circuit.lock().unwrap();
+
+        let failures = match *circuit {
+            Circuit::Closed { failures } => failures + 1,
+            _ => self.config.failure_threshold,
+        };
+
+        *circuit = if failures >= self.config.failure_threshold {
+            warn!(
+                "Rate limit store unavailable, limiting in memory for {} seconds",
+                self.config.cooldown_seconds
+            );
+            Circuit::Open {
+                until: now + self.config.cooldown_seconds,
+            }
+        } else {
+            Circuit::Closed { failures }
+        };
+    }
+}
+
+/// Replaces the overrides in `memory` with those of `store`
+async fn copy_overrides(store: &dyn RateLimiter, memory: &InMemoryRateLimiter, timeout: Duration) {
+    match tokio::time::timeout(timeout, store.overrides()).await {
+        Ok(Ok(overrides)) => memory.replace_overrides(
+            overrides
+                .into_iter()
+                .map(|limit| {
+                    let quota = Quota {
+                        capacity: limit.capacity,
+                        refill_per_minute: limit.refill_per_minute,
+                    };
+                    (limit.client, quota)
+                })
+                .collect(),
+        ),
+        Ok(Err(e)) => error!("Failed to copy rate limit overrides: {}", e),
+        Err(_) => error!(
+            "Copying rate limit overrides timed out after {} ms",
+            timeout.as_millis()
+        ),
+    }
+}
+
+#[async_trait]
+impl RateLimiter for FallbackRateLimiter {
+    async fn check(
+        &self,
+        client: &ClientId,
+        route: Option<&str>,
+        quota: Quota,
+        now: f64,
+    ) -> Result<RateLimitDecision, ApplicationError> {
+        if self.use_store(now) {
+            let check = self.store.check(client, route, quota, now);
+
+            match tokio::time::timeout(self.config.timeout, check).await {
+                Ok(Ok(decision)) => {
+                    self.succeeded();
+                    self.sync_overrides(now);
+                    return Ok(decision);
+                }
+                Ok(Err(e)) => error!("Rate limit check failed: {}", e),
+                Err(_) => error!(
+                    "Rate limit check timed out after {} ms",
+                    self.config.timeout.as_millis()
+                ),
+            }
+
+            self.failed(now);
+        }
+
+        self.memory.check(client, route, quota, now).await
+    }
+
+    async fn overrides(&self) -> Result<Vec<ClientRateLimit>, ApplicationError> {
+        self.store.overrides().await
+    }
+
+    async fn set_override(&self, client: &str, quota: Quota) -> Result<(), ApplicationError> {
+        self.store.set_override(client, quota).await?;
+        self.memory.set_override(client, quota).await
+    }
+
+    async fn delete_override(&self, client: &str) -> Result<bool, ApplicationError> {
+        let deleted = self.store.delete_override(client).await?;
+        self.memory.delete_override(client).await?;
+
+        Ok(deleted)
+    }
+
+    async fn usage(&self) -> Result<Vec<ClientUsage>, ApplicationError> {
+        self.store.usage().await
+    }
+}
@@ -0,0 +1,124 @@
+use std::collections::{BTreeMap, HashMap};
+use std::sync::Mutex;
+
+use async_trait::async_trait;
+use wh_core::api::{ClientRateLimit, ClientUsage};
+
+use crate::common::error::ApplicationError;
+use crate::devices::ClientId;
+
+use super::{
+    client_rate_limit, client_usage, is_overridable, state_key, Algorithm, LimitState, Quota,
+    RateLimitDecision, RateLimiter, STATE_TTL_SECONDS,
+};
+
+/// Expired states are removed at most this often instead of on every check, as that
+/// goes through every state while holding the lock
+const PRUNE_INTERVAL_SECONDS: f64 = 60.0;
+
+#[derive(Default)]
+struct ClientStates {
+    /// States keyed by the client and the route, see [`super::state_key`]
+    by_key: HashMap<String, LimitState>,
+    pruned_at: f64,
+}
+
+/// Limits kept in the memory of the process, for running without Redis
+pub struct InMemoryRateLimiter {
+    algorithm: Algorithm,
+    clients: Mutex<ClientStates>,
+    /// Overrides keyed by the client as formatted by [`ClientId`], like in the other stores
+    overrides: Mutex<BTreeMap<String, Quota>>,
+}
+
+impl InMemoryRateLimiter {
+    pub fn new(algorithm: Algorithm) -> Self {
+        InMemoryRateLimiter {
+            algorithm,
+            clients: Mutex::new(ClientStates::default()),
+            overrides: Mutex::new(BTreeMap::new()),
+        }
+    }
+
+    /// Replaces every override, keyed by the client
+    pub fn replace_overrides(&self, overrides: BTreeMap<String, Quota>) {
+        *self.overrides.lock().unwrap() = overrides;
+    }
+}
+
+#[async_trait]
+impl RateLimiter for InMemoryRateLimiter {
+    async fn check(
+        &self,
+        client: &ClientId,
+        route: Option<&str>,
+        quota: Quota,
+        now: f64,
+    ) -> Result<RateLimitDecision, ApplicationError> {
+        let quota = if is_overridable(route) {
+            self.overrides
+                .lock()
+                .unwrap()
+                .get(&client.to_string())
+                .copied()
+                .unwrap_or(quota)
+        } else {
+            quota
+        };
+
+        let mut clients = self.clients.lock().unwrap();
+
+        if now - clients.pruned_at >= PRUNE_INTERVAL_SECONDS {
+            clients
+                .by_key
+                .retain(|_, state| now - state.last_request < STATE_TTL_SECONDS);
+            clients.pruned_at = now;
+        }
+
+        // An expired state not pruned yet is ignored by the algorithm
+        let key = state_key(client, route);
+        let previous = clients.by_key.get(&key).copied();
+        let (decision, state) = self.algorithm.apply(previous, quota, now);
+        clients.by_key.insert(key, state);
+
+        Ok(decision)
+    }
+
+    async fn overrides(&self) -> Result<Vec<ClientRateLimit>, ApplicationError> {
+        Ok(self
+            .overrides
+            .lock()
+            .unwrap()
+            .iter()
+            .map(|(client, quota)| client_rate_limit(client.clone(), *quota))
+            .collect())
+    }
+
+    async fn set_override(&self, client: &str, quota: Quota) -> Result<(), ApplicationError> {
+        self.overrides
+            .lock()
+            .unwrap()
+            .insert(client.to_string(), quota);
+
+        Ok(())
+    }
+
+    async fn delete_override(&self, client: &str) -> Result<bool, ApplicationError> {
+        Ok(self.overrides.lock().unwrap().remove(client).is_some())
+    }
+
+    async fn usage(&self) -> Result<Vec<ClientUsage>, ApplicationError> {
+        let mut usage: Vec<ClientUsage> = self
+            .clients
+            .lock()
+            .unwrap()
+            .by_key
+            .iter()
+            .map(|(client, state)| client_usage(client.clone(), state))
+            .collect();
+
+        usage.sort_by(|a, b| a.client.cmp(&b.client));
+
+        Ok(usage)
+    }
+}
@@ -0,0 +1,540 @@
+//! Rate limiting of requests. The algorithm and the store the limits are kept in are
+//! chosen by configuration, behind the [`RateLimiter`] trait, and the limit of each
+//! request by the [`policy::RateLimitPolicy`].
+
+use std::{
+    env,
+    net::{IpAddr, SocketAddr},
+    str::FromStr,
+};
+
+use async_trait::async_trait;
+use axum::{
+    body::Body,
+    extract::{ConnectInfo, State},
+    http::{header::RETRY_AFTER, HeaderMap, HeaderValue, Method, Request, StatusCode},
+    middleware::Next,
+    response::{IntoResponse, Response},
+};
+use chrono::{DateTime, Utc};
+use lambda_http::Error;
+use tracing::{error, info};
+use wh_core::api::{ClientRateLimit, ClientUsage, RateLimitOverride};
+
+use crate::auth::{AnonymousAccess, Caller};
+use crate::common::error::ApplicationError;
+use crate::devices::ClientId;
+use crate::http::TooManyRequests;
+use crate::AppState;
+
+use self::fallback::BreakerConfig;
+use self::policy::RateLimitPolicy;
+
+pub mod dynamodb;
+pub mod fallback;
+pub mod memory;
+pub mod policy;
+pub mod redis;
+
+/// 20 requests at once, refilled at 20 requests per minute
+pub const DEFAULT_QUOTA: Quota = Quota {
+    capacity: 20,
+    refill_per_minute: 20.0,
+};
+
+/// 5 registrations at once, then one every 12 minutes. Every device registered gets a
+/// quota of its own, so registering is limited far below the requests of a device.
+pub const REGISTRATION_QUOTA: Quota = Quota {
+    capacity: 5,
+    refill_per_minute: 5.0 / 60.0,
+};
+
+/// Route the registrations of devices are counted for, by IP address
+const REGISTRATION_ROUTE: &str = "registration";
+/// Route the lookups of device tokens and keys not known yet are counted for, by IP
+/// address
+const CREDENTIALS_ROUTE: &str = "credentials";
+
+/// Whether an override of the client takes the place of the quota of `route`. Overrides
+/// only change the policy quota, an IP address given more requests for a household
+/// behind one address can't register more devices or guess more tokens and keys.
+fn is_overridable(route: Option<&str>) -> bool {
+    !matches!(route, Some(REGISTRATION_ROUTE | CREDENTIALS_ROUTE))
+}
+
+pub const RATE_LIMIT_LIMIT: &str = "ratelimit-limit";
+pub const RATE_LIMIT_REMAINING: &str = "ratelimit-remaining";
+pub const RATE_LIMIT_RESET: &str = "ratelimit-reset";
+
+// Clients not seen for an hour are forgotten, in every store
+const STATE_TTL_SECONDS: f64 = 3600.0;
+
+/// How many requests a client can make
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Quota {
+    /// Requests that can be made at once
+    pub capacity: u32,
+    /// Requests added back per minute. With the sliding window the window is as long as
+    /// it takes to refill `capacity` requests.
+    pub refill_per_minute: f64,
+}
+
+impl Quota {
+    fn refill_per_second(&self) -> f64 {
+        self.refill_per_minute / 60.0
+    }
+
+    fn window_seconds(&self) -> f64 {
+        self.capacity as f64 / self.refill_per_second()
+    }
+}
+
+impl From<RateLimitOverride> for Quota {
+    fn from(limit: RateLimitOverride) -> Self {
+        Quota {
+            capacity: limit.capacity,
+            refill_per_minute: limit.refill_per_minute,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Algorithm {
+    /// Requests take a token from a bucket of `capacity` tokens, which is refilled
+    /// continuously. Allows bursts of up to `capacity` requests.
+    TokenBucket,
+    /// At most `capacity` requests within a window sliding with the time, estimated
+    /// from the counts of the current and the previous fixed window.
+    SlidingWindow,
+}
+
+impl FromStr for Algorithm {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "token-bucket" => Ok(Algorithm::TokenBucket),
+            "sliding-window" => Ok(Algorithm::SlidingWindow),
+            other => Err(format!("Unsupported rate limit algorithm: {other}").into()),
+        }
+    }
+}
+
+/// What is kept of a client between its requests, by the stores that run the algorithms
+/// in Rust
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct LimitState {
+    /// Tokens left in the bucket, or requests counted in the current window
+    pub count: f64,
+    /// Start of the current window, sliding window only
+    pub window_start: f64,
+    /// Requests counted in the previous window, sliding window only
+    pub previous_count: f64,
+    /// Requests the client could still make at once after the last request
+    pub remaining: f64,
+    /// Every request within the last hour, allowed or not
+    pub requests: u64,
+    pub last_request: f64,
+}
+
+/// Outcome of a request, with what the client is told of its limit
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RateLimitDecision {
+    pub allowed: bool,
+    /// Capacity of the quota the request was counted against
+    pub limit: u32,
+    /// Requests the client can still make at once
+    pub remaining: f64,
+    /// Seconds until the client has its whole capacity again
+    pub reset: f64,
+    /// Seconds until the next request is allowed, zero while requests remain
+    pub retry_after: f64,
+}
+
+impl Algorithm {
+    /// Counts a request made at `now` and returns whether it's allowed, with the new
+    /// state of the client. Same as the Lua scripts used with Redis.
+    pub fn apply(
+        &self,
+        state: Option<LimitState>,
+        quota: Quota,
+        now: f64,
+    ) -> (RateLimitDecision, LimitState) {
+        let state = state.filter(|state| now - state.last_request < STATE_TTL_SECONDS);
+        let capacity = quota.capacity as f64;
+
+        let mut next = match self {
+            Algorithm::TokenBucket => {
+                let tokens = match state {
+                    Some(state) => {
+                        let delta = now - state.last_request;
+                        (state.count + delta * quota.refill_per_second()).min(capacity)
+                    }
+                    None => capacity,
+                };
+
+                LimitState {
+                    count: tokens,
+                    remaining: tokens,
+                    ..LimitState::default()
+                }
+            }
+            Algorithm::SlidingWindow => {
+                let window = quota.window_seconds();
+                let index = (now / window).floor();
+                let window_start = index * window;
+
+                // Windows are compared by index, the starts may differ by rounding
+                let (count, previous_count) = match state {
+                    Some(state) if (state.window_start / window).round() == index => {
+                        (state.count, state.previous_count)
+                    }
+                    Some(state) if (state.window_start / window).round() == index - 1.0 => {
+                        (0.0, state.count)
+                    }
+                    _ => (0.0, 0.0),
+                };
+
+                let elapsed = (now - window_start) / window;
+                let estimate = previous_count * (1.0 - elapsed) + count;
+
+                LimitState {
+                    count,
+                    window_start,
+                    previous_count,
+                    remaining: (capacity - estimate).max(0.0),
+                    ..LimitState::default()
+                }
+            }
+        };
+
+        next.requests = state.map(|state| state.requests).unwrap_or_default() + 1;
+        next.last_request = now;
+
+        let allowed = next.remaining >= 1.0;
+        if allowed {
+            next.remaining -= 1.0;
+            match self {
+                Algorithm::TokenBucket => next.count -= 1.0,
+                Algorithm::SlidingWindow => next.count += 1.0,
+            }
+        }
+
+        let (reset, retry_after) = self.wait_times(&next, quota, now);
+        let decision = RateLimitDecision {
+            allowed,
+            limit: quota.capacity,
+            remaining: next.remaining,
+            reset,
+            retry_after,
+        };
+
+        (decision, next)
+    }
+
+    /// Seconds from `now` until the capacity is full again, and until the next request
+    /// is allowed
+    fn wait_times(&self, state: &LimitState, quota: Quota, now: f64) -> (f64, f64) {
+        let capacity = quota.capacity as f64;
+        let rate = quota.refill_per_second();
+
+        match self {
+            Algorithm::TokenBucket => {
+                let reset = (capacity - state.count) / rate;
+                let retry_after = ((1.0 - state.count) / rate).max(0.0);
+                (reset, retry_after)
+            }
+            Algorithm::SlidingWindow => {
+                let window = quota.window_seconds();
+                let window_end = state.window_start + window;
+
+                // Requests of the current window count until the end of the next one
+                let reset = if state.count > 0.0 {
+                    window_end + window - now
+                } else if state.previous_count > 0.0 {
+                    window_end - now
+                } else {
+                    0.0
+                };
+
+                // The estimate falls as the window slides, until one request fits
+                let retry_after = if state.remaining >= 1.0 {
+                    0.0
+                } else if state.count <= capacity - 1.0 {
+                    let elapsed = 1.0 - (capacity - 1.0 - state.count) / state.previous_count;
+                    state.window_start + elapsed * window - now
+                } else {
+                    let elapsed = (1.0 - (capacity - 1.0) / state.count).max(0.0);
+                    window_end + elapsed * window - now
+                };
+
+                (reset.max(0.0), retry_after.max(0.0))
+            }
+        }
+    }
+}
+
+/// Counts the requests of each client and keeps the per-client overrides of the quota
+#[async_trait]
+pub trait RateLimiter: Send + Sync {
+    /// Counts a request of `client` made at `now`, in seconds, and returns whether it's
+    /// allowed with how much of the quota is left. Requests to a `route` with its own
+    /// limit are counted apart from the rest. An override of the client takes the place
+    /// of `quota`, except for registrations and lookups of credentials.
+    async fn check(
+        &self,
+        client: &ClientId,
+        route: Option<&str>,
+        quota: Quota,
+        now: f64,
+    ) -> Result<RateLimitDecision, ApplicationError>;
+
+    /// Clients with a quota other than the default, ordered by client
+    async fn overrides(&self) -> Result<Vec<ClientRateLimit>, ApplicationError>;
+
+    /// Sets the quota of `client`, as formatted by [`ClientId`]. Takes effect on the
+    /// next request of the client.
+    async fn set_override(&self, client: &str, quota: Quota) -> Result<(), ApplicationError>;
+
+    /// Returns false if the client has the default quota
+    async fn delete_override(&self, client: &str) -> Result<bool, ApplicationError>;
+
+    /// Clients that made rate limited requests within the last hour, ordered by client
+    async fn usage(&self) -> Result<Vec<ClientUsage>, ApplicationError>;
+}
+
+/// Where the state of the clients is kept
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RateLimitStore {
+    Redis,
+    DynamoDb,
+    /// In the memory of the process, for running without Redis. Each Lambda instance
+    /// would count separately.
+    Memory,
+}
+
+impl FromStr for RateLimitStore {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "redis" => Ok(RateLimitStore::Redis),
+            "dynamodb" => Ok(RateLimitStore::DynamoDb),
+            "memory" => Ok(RateLimitStore::Memory),
+            other => Err(format!("Unsupported rate limit store: {other}").into()),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RateLimitConfig {
+    pub store: RateLimitStore,
+    pub algorithm: Algorithm,
+    /// When to fall back to memory, with the `redis` and `dynamodb` stores
+    pub breaker: BreakerConfig,
+}
+
+impl RateLimitConfig {
+    /// `RATE_LIMIT_STORE` and `RATE_LIMIT_ALGORITHM`, the token bucket by default
+    pub fn from_env(default_store: RateLimitStore) -> Result<Self, Error> {
+        let store = match env::var("RATE_LIMIT_STORE") {
+            Ok(store) => store.parse()?,
+            Err(_) => default_store,
+        };
+        let algorithm = match env::var("RATE_LIMIT_ALGORITHM") {
+            Ok(algorithm) => algorithm.parse()?,
+            Err(_) => Algorithm::TokenBucket,
+        };
+
+        Ok(RateLimitConfig {
+            store,
+            algorithm,
+            breaker: BreakerConfig::from_env()?,
+        })
+    }
+}
+
+/// Key of the state of `client`, per route when the route has its own limit
+fn state_key(client: &ClientId, route: Option<&str>) -> String {
+    match route {
+        Some(route) => format!("{client}@{route}"),
+        None => client.to_string(),
+    }
+}
+
+fn timestamp(seconds: f64) -> DateTime<Utc> {
+    DateTime::from_timestamp_millis((seconds * 1000.0) as i64).unwrap_or_default()
+}
+
+fn client_rate_limit(client: String, quota: Quota) -> ClientRateLimit {
+    ClientRateLimit {
+        client,
+        capacity: quota.capacity,
+        refill_per_minute: quota.refill_per_minute,
+    }
+}
+
+fn client_usage(client: String, state: &LimitState) -> ClientUsage {
+    ClientUsage {
+        client,
+        requests: state.requests,
+        remaining: state.remaining,
+        last_request: timestamp(state.last_request),
+    }
+}
+
+fn is_production() -> bool {
+    env::var::<&str>("DEPLOY_ENV").unwrap_or_default() == "production"
+}
+
+fn current_time() -> f64 {
+    Utc::now().timestamp_millis() as f64 / 1000.0
+}
+
+/// Counts a lookup of credentials by `ip`, with the anonymous quota but apart from the
+/// other requests of the address: a device token that is not cached, or a key that is
+/// not valid. `None` when anonymous requests are not limited or the store failed.
+pub async fn check_credentials_lookup(
+    limiter: &dyn RateLimiter,
+    policy: &RateLimitPolicy,
+    ip: IpAddr,
+    now: f64,
+) -> Option<RateLimitDecision> {
+    let quota = policy.anonymous.quota()?;
+
+    match limiter
+        .check(&ClientId::Ip(ip), Some(CREDENTIALS_ROUTE), quota, now)
+        .await
+    {
+        Ok(decision) => Some(decision),
+        Err(e) => {
+            error!("Rate limit check failed: {}", e);
+            None
+        }
+    }
+}
+
+/// Too Many Requests in place of looking up the credentials of the request, once `ip`
+/// has looked up too many. Guessing device tokens or keys is limited like anonymous
+/// requests, before each guess costs a query or is answered with 401.
+pub async fn limit_credentials_lookup(state: &AppState, ip: IpAddr) -> Option<Response> {
+    if !is_production() {
+        return None;
+    }
+
+    let policy = state.rate_limit_policy.current().await;
+    let decision =
+        check_credentials_lookup(state.rate_limiter.as_ref(), &policy, ip, current_time()).await?;
+
+    (!decision.allowed).then(|| too_many_requests(&ClientId::Ip(ip), &decision))
+}
+
+fn is_registration(request: &Request<Body>) -> bool {
+    request.method() == Method::POST
+        && request.uri().path().trim_end_matches('/') == "/api/v3/devices"
+}
+
+pub async fn rate_limit(
+    State(state): State<AppState>,
+    ConnectInfo(addr): ConnectInfo<SocketAddr>,
+    request: Request<Body>,
+    next: Next,
+) -> Result<Response, StatusCode> {
+    // Do not rate limit on local env
+    if !is_production() {
+        return Ok(next.run(request).await);
+    }
+
+    let caller = request
+        .extensions()
+        .get::<Caller>()
+        .cloned()
+        .unwrap_or(Caller::Anonymous(AnonymousAccess::Limit));
+
+    let current_time = current_time();
+
+    // Registrations are counted by IP address even with a device token, as a new
+    // token is all that registering gives
+    if matches!(caller, Caller::Anonymous(_)) && is_registration(&request) {
+        let client_id = ClientId::Ip(addr.ip());
+        match state
+            .rate_limiter
+            .check(
+                &client_id,
+                Some(REGISTRATION_ROUTE),
+                REGISTRATION_QUOTA,
+                current_time,
+            )
+            .await
+        {
+            Ok(decision) if !decision.allowed => {
+                return Ok(too_many_requests(&client_id, &decision));
+            }
+            Ok(_) => {}
+            Err(e) => error!("Rate limit check failed: {}", e),
+        }
+    }
+
+    let policy = state.rate_limit_policy.current().await;
+    let Some((route, quota)) = policy.quota(request.uri().path(), &caller) else {
+        return Ok(next.run(request).await);
+    };
+
+    // API keys are limited by their name, devices by their token, everything else by IP
+    let client_id = match caller {
+        Caller::ApiKey(name) => ClientId::ApiKey(name),
+        _ => request
+            .extensions()
+            .get::<ClientId>()
+            .cloned()
+            .unwrap_or(ClientId::Ip(addr.ip())),
+    };
+
+    let decision = match state
+        .rate_limiter
+        .check(&client_id, route, quota, current_time)
+        .await
+    {
+        Ok(decision) => decision,
+        Err(e) => {
+            // Allow the request if the store is unavailable
+            error!("Rate limit check failed: {}", e);
+            return Ok(next.run(request).await);
+        }
+    };
+
+    if decision.allowed {
+        let mut response = next.run(request).await;
+        insert_rate_limit_headers(response.headers_mut(), &decision);
+        Ok(response)
+    } else {
+        Ok(too_many_requests(&client_id, &decision))
+    }
+}
+
+fn too_many_requests(client_id: &ClientId, decision: &RateLimitDecision) -> Response {
+    info!("Rate limit exceeded for client: {}", client_id);
+
+    let mut response = TooManyRequests("Too Many Requests".to_string()).into_response();
+    insert_rate_limit_headers(response.headers_mut(), decision);
+    // Whole seconds, never zero so that the client waits
+    let retry_after = decision.retry_after.ceil().max(1.0) as u64;
+    response
+        .headers_mut()
+        .insert(RETRY_AFTER, HeaderValue::from(retry_after));
+    response
+}
+
+/// `RateLimit-Limit`, `RateLimit-Remaining` and `RateLimit-Reset` of the IETF draft on
+/// rate limit headers, in whole requests and seconds
+fn insert_rate_limit_headers(headers: &mut HeaderMap, decision: &RateLimitDecision) {
+    headers.insert(RATE_LIMIT_LIMIT, HeaderValue::from(decision.limit));
+    headers.insert(
+        RATE_LIMIT_REMAINING,
+        HeaderValue::from(decision.remaining.floor() as u64),
+    );
+    headers.insert(
+        RATE_LIMIT_RESET,
+        HeaderValue::from(decision.reset.ceil() as u64),
+    );
+}
@@ -0,0 +1,359 @@
+//! Which [`Quota`] applies to a request, by the route and the caller. Policies are JSON
+//! kept in a file or in SSM Parameter Store, and fetched again periodically so that
+//! limits can be changed without a deploy:
+//!
+//! ```json
+//! {
+//!   "anonymous": { "requests_per_minute": 20 },
+//!   "tiers": {
+//!     "free": { "requests_per_minute": 60, "burst": 120 },
+//!     "partner": "unlimited"
+//!   },
+//!   "api_keys": { "sauna": "partner" },
+//!   "default_tier": "free",
+//!   "routes": [
+//!     { "path": "/api-docs", "anonymous": { "requests_per_minute": 5 } }
+//!   ]
+//! }
+//! ```
+
+use std::collections::BTreeMap;
+use std::env;
+use std::sync::Arc;
+use std::time::Duration;
+
+use async_trait::async_trait;
+use lambda_http::Error;
+use serde::Deserialize;
+use tracing::info;
+
+use crate::auth::{matches_route, AnonymousAccess, Caller};
+use crate::common::cache::RefreshingCache;
+use crate::common::error::ApplicationError;
+
+use super::{Quota, DEFAULT_QUOTA};
+
+pub const RATE_LIMIT_POLICY_PARAMETER: &str = "/waterheater_calc/rate_limit_policy";
+
+const DEFAULT_REFRESH_SECONDS: u64 = 60;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Unlimited {
+    Unlimited,
+}
+
+/// `"unlimited"`, or a rate with an optional burst
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(untagged)]
+pub enum Limit {
+    Unlimited(Unlimited),
+    Rate {
+        requests_per_minute: u32,
+        /// Requests that can be made at once, `requests_per_minute` if not set
+        #[serde(default)]
+        burst: Option<u32>,
+    },
+}
+
+impl Limit {
+    /// `None` when unlimited
+    pub fn quota(&self) -> Option<Quota> {
+        match *self {
+            Limit::Unlimited(_) => None,
+            Limit::Rate {
+                requests_per_minute,
+                burst,
+            } => Some(Quota {
+                capacity: burst.unwrap_or(requests_per_minute),
+                refill_per_minute: requests_per_minute as f64,
+            }),
+        }
+    }
+}
+
+/// Limits of requests to paths starting with `path`, in place of the global ones. Each
+/// route is counted separately from the rest of the API.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct RoutePolicy {
+    pub path: String,
+    #[serde(default)]
+    pub anonymous: Option<Limit>,
+    #[serde(default)]
+    pub tiers: BTreeMap<String, Limit>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct RateLimitPolicy {
+    /// Requests without an API key, per device or IP address
+    #[serde(default = "default_anonymous")]
+    pub anonymous: Limit,
+    /// Limits of API keys by tier, per key
+    #[serde(default)]
+    pub tiers: BTreeMap<String, Limit>,
+    /// Tier of each API key by the name of the key
+    #[serde(default)]
+    pub api_keys: BTreeMap<String, String>,
+    /// Tier of the keys missing from `api_keys`. Without one they are not limited.
+    #[serde(default)]
+    pub default_tier: Option<String>,
+    #[serde(default)]
+    pub routes: Vec<RoutePolicy>,
+}
+
+fn default_anonymous() -> Limit {
+    Limit::Rate {
+        requests_per_minute: DEFAULT_QUOTA.refill_per_minute as u32,
+        burst: Some(DEFAULT_QUOTA.capacity),
+    }
+}
+
+impl Default for RateLimitPolicy {
+    /// Anonymous requests limited to 20 per minute, API keys not limited
+    fn default() -> Self {
+        RateLimitPolicy {
+            anonymous: default_anonymous(),
+            tiers: BTreeMap::new(),
+            api_keys: BTreeMap::new(),
+            default_tier: None,
+            routes: Vec::new(),
+        }
+    }
+}
+
+impl RateLimitPolicy {
+    pub fn parse(json: &str) -> Result<Self, ApplicationError> {
+        let policy: RateLimitPolicy = serde_json::from_str(json)
+            .map_err(|e| ApplicationError::Service(format!("Invalid rate limit policy: {e}")))?;
+        policy.validate()?;
+
+        Ok(policy)
+    }
+
+    /// Every tier referred to must be defined, so that a typo doesn't lift the limits, and
+    /// every rate must allow at least one request, as a client could never be allowed
+    /// again otherwise
+    fn validate(&self) -> Result<(), ApplicationError> {
+        let referred = self
+            .api_keys
+            .values()
+            .chain(&self.default_tier)
+            .chain(self.routes.iter().flat_map(|route| route.tiers.keys()));
+
+        for tier in referred {
+            if !self.tiers.contains_key(tier) {
+                return Err(ApplicationError::Service(format!(
+                    "Invalid rate limit policy: unknown tier {tier}"
+                )));
+            }
+        }
+
+        let limits = std::iter::once(&self.anonymous)
+            .chain(self.tiers.values())
+            .chain(
+                self.routes
+                    .iter()
+                    .flat_map(|route| route.anonymous.iter().chain(route.tiers.values())),
+            );
+
+        for limit in limits {
+            if let Limit::Rate {
+                requests_per_minute,
+                burst,
+            } = limit
+            {
+                if *requests_per_minute == 0 || *burst == Some(0) {
+                    return Err(ApplicationError::Service(
+                        "Invalid rate limit policy: requests_per_minute and burst must be at least 1"
+                            .to_string(),
+                    ));
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Quota of a request to `path`, with the route it's counted for when the route has
+    /// its own limit. `None` when the request is not limited.
+    pub fn quota(&self, path: &str, caller: &Caller) -> Option<(Option<&str>, Quota)> {
+        let tier = match caller {
+            Caller::Admin | Caller::Anonymous(AnonymousAccess::Allow) => return None,
+            Caller::ApiKey(name) => Some(self.api_keys.get(name).or(self.default_tier.as_ref())?),
+            Caller::Anonymous(_) => None,
+        };
+
+        let route = self
+            .routes
+            .iter()
+            .filter(|route| matches_route(path, &route.path))
+            .max_by_key(|route| route.path.len())
+            .and_then(|route| {
+                let limit = match tier {
+                    Some(tier) => route.tiers.get(tier),
+                    None => route.anonymous.as_ref(),
+                }?;
+                Some((route.path.as_str(), limit))
+            });
+
+        match route {
+            Some((path, limit)) => Some((Some(path), limit.quota()?)),
+            None => {
+                let limit = match tier {
+                    Some(tier) => self.tiers.get(tier)?,
+                    None => &self.anonymous,
+                };
+                Some((None, limit.quota()?))
+            }
+        }
+    }
+}
+
+/// Where the policy is kept
+#[async_trait]
+pub trait PolicySource: Send + Sync {
+    async fn fetch_policy(&self) -> Result<RateLimitPolicy, ApplicationError>;
+}
+
+/// JSON file, re-read on every refresh
+pub struct FilePolicySource {
+    path: String,
+}
+
+impl FilePolicySource {
+    pub fn new(path: impl Into<String>) -> Self {
+        FilePolicySource { path: path.into() }
+    }
+}
+
+#[async_trait]
+impl PolicySource for FilePolicySource {
+    async fn fetch_policy(&self) -> Result<RateLimitPolicy, ApplicationError> {
+        let json = tokio::fs::read_to_string(&self.path)
+            .await
+            .map_err(|e| ApplicationError::Service(format!("Failed to read {}: {e}", self.path)))?;
+
+        RateLimitPolicy::parse(&json)
+    }
+}
+
+/// JSON in a String parameter of SSM Parameter Store
+pub struct SsmPolicySource {
+    client: aws_sdk_ssm::Client,
+    name: String,
+}
+
+impl SsmPolicySource {
+    pub fn new(client: aws_sdk_ssm::Client, name: impl Into<String>) -> Self {
+        SsmPolicySource {
+            client,
+            name: name.into(),
+        }
+    }
+}
+
+#[async_trait]
+impl PolicySource for SsmPolicySource {
+    async fn fetch_policy(&self) -> Result<RateLimitPolicy, ApplicationError> {
+        let output = self
+            .client
+            .get_parameter()
+            .name(&self.name)
+            .send()
+            .await
+            .map_err(|e| ApplicationError::SsmOperation(Box::new(e.into())))?;
+
+        let json = output
+            .parameter()
+            .and_then(|parameter| parameter.value())
+            .ok_or(ApplicationError::Service(format!(
+                "{} has no value",
+                self.name
+            )))?;
+
+        RateLimitPolicy::parse(json)
+    }
+}
+
+/// The same policy every time, when none is configured
+pub struct StaticPolicySource(pub RateLimitPolicy);
+
+#[async_trait]
+impl PolicySource for StaticPolicySource {
+    async fn fetch_policy(&self) -> Result<RateLimitPolicy, ApplicationError> {
+        Ok(self.0.clone())
+    }
+}
+
+/// The policy fetched from the source on first use and again once `refresh_interval`
+/// has passed. An invalid policy is logged and the previous one kept.
+pub struct PolicyCache {
+    source: Box<dyn PolicySource>,
+    policy: RefreshingCache<RateLimitPolicy>,
+}
+
+impl PolicyCache {
+    pub fn new(source: impl PolicySource + 'static, refresh_interval: Duration) -> Self {
+        PolicyCache {
+            source: Box::new(source),
+            policy: RefreshingCache::new(
+                "rate limit policy",
+                RateLimitPolicy::default(),
+                refresh_interval,
+            ),
+        }
+    }
+
+    /// `RATE_LIMIT_POLICY_FILE`, or the SSM parameter `RATE_LIMIT_POLICY_SSM_PARAMETER`
+    /// when `ssm` is given, refreshed every `RATE_LIMIT_POLICY_REFRESH_SECONDS`. The
+    /// default policy when neither is available.
+    pub fn from_env(ssm: Option<aws_sdk_ssm::Client>) -> Result<Self, Error> {
+        let refresh_interval = match env::var("RATE_LIMIT_POLICY_REFRESH_SECONDS") {
+            Ok(seconds) => Duration::from_secs(seconds.parse()?),
+            Err(_) => Duration::from_secs(DEFAULT_REFRESH_SECONDS),
+        };
+
+        if let Ok(path) = env::var("RATE_LIMIT_POLICY_FILE") {
+            return Ok(PolicyCache::new(
+                FilePolicySource::new(path),
+                refresh_interval,
+            ));
+        }
+
+        match ssm {
+            Some(client) => Ok(PolicyCache::new(
+                SsmPolicySource::new(
+                    client,
+                    env::var("RATE_LIMIT_POLICY_SSM_PARAMETER")
+                        .unwrap_or(RATE_LIMIT_POLICY_PARAMETER.into()),
+                ),
+                refresh_interval,
+            )),
+            None => Ok(PolicyCache::default()),
+        }
+    }
+
+    pub async fn current(&self) -> Arc<RateLimitPolicy> {
+        self.policy
+            .get(|| async {
+                let policy = self.source.fetch_policy().await?;
+                if *self.policy.cached() != policy {
+                    info!("Loaded rate limit policy");
+                }
+
+                Ok::<_, ApplicationError>(policy)
+            })
+            .await
+    }
+}
+
+impl Default for PolicyCache {
+    fn default() -> Self {
+        PolicyCache::new(
+            StaticPolicySource(RateLimitPolicy::default()),
+            Duration::MAX,
+        )
+    }
+}
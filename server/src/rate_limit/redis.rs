@@ -0,0 +1,284 @@
+use std::sync::Arc;
+
+use async_trait::async_trait;
+use deadpool_redis::{Connection, Pool};
+use redis::{AsyncCommands, RedisError};
+use wh_core::api::{ClientRateLimit, ClientUsage};
+
+use crate::common::error::ApplicationError;
+use crate::devices::ClientId;
+
+use super::{
+    client_rate_limit, is_overridable, state_key, timestamp, Algorithm, Quota, RateLimitDecision,
+    RateLimiter, STATE_TTL_SECONDS,
+};
+
+const TOKEN_BUCKET_KEY_PREFIX: &str = "rate_limit:";
+// The fields differ from the token bucket, so the windows don't share its keys
+const SLIDING_WINDOW_KEY_PREFIX: &str = "rate_limit_window:";
+const OVERRIDE_KEY_PREFIX: &str = "rate_limit_override:";
+
+const ARGS_SCRIPT: &str = r#"
+            local capacity = tonumber(ARGV[1])
+            local refill_rate = tonumber(ARGV[2])
+            local current_time = tonumber(ARGV[3])
+            local ttl = tonumber(ARGV[4])
+"#;
+
+// Reads the override of the client from KEYS[2] in place of the capacity and refill rate
+// of the arguments
+const OVERRIDE_SCRIPT: &str = r#"
+            local limit = redis.call("HMGET", KEYS[2], "capacity", "refill_per_minute")
+            if limit[1] and limit[2] then
+                capacity = tonumber(limit[1])
+                refill_rate = tonumber(limit[2]) / 60
+            end
+"#;
+
+const TOKEN_BUCKET_SCRIPT: &str = r#"
+            local key = KEYS[1]
+
+            local data = redis.call("HMGET", key, "tokens", "last_refill")
+            local tokens = tonumber(data[1])
+            local last_refill = tonumber(data[2])
+
+            if tokens == nil then
+                tokens = capacity
+                last_refill = current_time
+            end
+
+            local delta = current_time - last_refill
+            local tokens_to_add = delta * refill_rate
+            tokens = math.min(tokens + tokens_to_add, capacity)
+            last_refill = current_time
+
+            local allowed = 0
+            if tokens >= 1 then
+                allowed = 1
+                tokens = tokens - 1
+            end
+
+            redis.call("HMSET", key, "tokens", tokens, "last_refill", last_refill)
+            redis.call("HINCRBY", key, "requests", 1)
+            redis.call("EXPIRE", key, ttl)
+
+            local reset = (capacity - tokens) / refill_rate
+            local retry_after = math.max((1 - tokens) / refill_rate, 0)
+
+            return {allowed, tostring(tokens), tostring(reset), tostring(retry_after), capacity}
+"#;
+
+const SLIDING_WINDOW_SCRIPT: &str = r#"
+            local key = KEYS[1]
+            local window = capacity / refill_rate
+            local index = math.floor(current_time / window)
+
+            local data = redis.call("HMGET", key, "window", "count", "previous_count")
+            local stored_index = tonumber(data[1])
+            local count = 0
+            local previous_count = 0
+
+            if stored_index == index then
+                count = tonumber(data[2])
+                previous_count = tonumber(data[3])
+            elseif stored_index == index - 1 then
+                previous_count = tonumber(data[2])
+            end
+
+            local elapsed = (current_time - index * window) / window
+            local remaining = math.max(capacity - (previous_count * (1 - elapsed) + count), 0)
+
+            local allowed = 0
+            if remaining >= 1 then
+                allowed = 1
+                count = count + 1
+                remaining = remaining - 1
+            end
+
+            redis.call(
+                "HMSET", key,
+                "window", index, "count", count, "previous_count", previous_count,
+                "remaining", remaining, "last_request", current_time
+            )
+            redis.call("HINCRBY", key, "requests", 1)
+            redis.call("EXPIRE", key, ttl)
+
+            local window_start = index * window
+            local window_end = window_start + window
+
+            local reset = 0
+            if count > 0 then
+                reset = window_end + window - current_time
+            elseif previous_count > 0 then
+                reset = window_end - current_time
+            end
+
+            local retry_after = 0
+            if remaining < 1 then
+                if count <= capacity - 1 then
+                    local elapsed_until = 1 - (capacity - 1 - count) / previous_count
+                    retry_after = window_start + elapsed_until * window - current_time
+                else
+                    local elapsed_until = math.max(1 - (capacity - 1) / count, 0)
+                    retry_after = window_end + elapsed_until * window - current_time
+                end
+            end
+
+            return {
+                allowed, tostring(remaining), tostring(math.max(reset, 0)),
+                tostring(math.max(retry_after, 0)), capacity
+            }
+"#;
+
+/// Limits shared by every Lambda instance, counted by Lua scripts so that concurrent
+/// requests of a client can't both take the last request
+pub struct RedisRateLimiter {
+    pool: Arc<Pool>,
+    algorithm: Algorithm,
+}
+
+impl RedisRateLimiter {
+    pub fn new(pool: Arc<Pool>, algorithm: Algorithm) -> Self {
+        RedisRateLimiter { pool, algorithm }
+    }
+
+    fn key_prefix(&self) -> &'static str {
+        match self.algorithm {
+            Algorithm::TokenBucket => TOKEN_BUCKET_KEY_PREFIX,
+            Algorithm::SlidingWindow => SLIDING_WINDOW_KEY_PREFIX,
+        }
+    }
+}
+
+async fn scan_keys(conn: &mut Connection, prefix: &str) -> Result<Vec<String>, RedisError> {
+    let mut keys = Vec::new();
+    let mut iter: redis::AsyncIter<String> = conn.scan_match(format!("{prefix}*")).await?;
+
+    while let Some(key) = iter.next_item().await {
+        keys.push(key);
+    }
+
+    Ok(keys)
+}
+
+#[async_trait]
+impl RateLimiter for RedisRateLimiter {
+    async fn check(
+        &self,
+        client: &ClientId,
+        route: Option<&str>,
+        quota: Quota,
+        now: f64,
+    ) -> Result<RateLimitDecision, ApplicationError> {
+        let mut conn = self.pool.get().await?;
+
+        let script = match self.algorithm {
+            Algorithm::TokenBucket => TOKEN_BUCKET_SCRIPT,
+            Algorithm::SlidingWindow => SLIDING_WINDOW_SCRIPT,
+        };
+
+        let state_key = format!("{}{}", self.key_prefix(), state_key(client, route));
+        let mut cmd = redis::cmd("EVAL");
+        if is_overridable(route) {
+            cmd.arg(format!("{ARGS_SCRIPT}{OVERRIDE_SCRIPT}{script}"))
+                .arg(2)
+                .arg(state_key)
+                .arg(format!("{OVERRIDE_KEY_PREFIX}{client}"));
+        } else {
+            cmd.arg(format!("{ARGS_SCRIPT}{script}"))
+                .arg(1)
+                .arg(state_key);
+        }
+
+        // Numbers are returned as strings, Redis would truncate them to integers
+        let (allowed, remaining, reset, retry_after, limit): (i32, f64, f64, f64, u32) = cmd
+            .arg(quota.capacity)
+            .arg(quota.refill_per_second())
+            .arg(now)
+            .arg(STATE_TTL_SECONDS as u64)
+            .query_async(&mut conn)
+            .await?;
+
+        Ok(RateLimitDecision {
+            allowed: allowed == 1,
+            limit,
+            remaining,
+            reset,
+            retry_after,
+        })
+    }
+
+    async fn overrides(&self) -> Result<Vec<ClientRateLimit>, ApplicationError> {
+        let mut conn = self.pool.get().await?;
+        let mut overrides = Vec::new();
+
+        for key in scan_keys(&mut conn, OVERRIDE_KEY_PREFIX).await? {
+            let (capacity, refill_per_minute): (Option<u32>, Option<f64>) =
+                conn.hget(&key, &["capacity", "refill_per_minute"]).await?;
+
+            if let (Some(capacity), Some(refill_per_minute)) = (capacity, refill_per_minute) {
+                overrides.push(client_rate_limit(
+                    key[OVERRIDE_KEY_PREFIX.len()..].to_string(),
+                    Quota {
+                        capacity,
+                        refill_per_minute,
+                    },
+                ));
+            }
+        }
+
+        overrides.sort_by(|a, b| a.client.cmp(&b.client));
+
+        Ok(overrides)
+    }
+
+    async fn set_override(&self, client: &str, quota: Quota) -> Result<(), ApplicationError> {
+        let mut conn = self.pool.get().await?;
+        let _: () = conn
+            .hset_multiple(
+                format!("{OVERRIDE_KEY_PREFIX}{client}"),
+                &[
+                    ("capacity", quota.capacity.to_string()),
+                    ("refill_per_minute", quota.refill_per_minute.to_string()),
+                ],
+            )
+            .await?;
+
+        Ok(())
+    }
+
+    async fn delete_override(&self, client: &str) -> Result<bool, ApplicationError> {
+        let mut conn = self.pool.get().await?;
+        let deleted: u32 = conn.del(format!("{OVERRIDE_KEY_PREFIX}{client}")).await?;
+
+        Ok(deleted > 0)
+    }
+
+    async fn usage(&self) -> Result<Vec<ClientUsage>, ApplicationError> {
+        let mut conn = self.pool.get().await?;
+        let prefix = self.key_prefix();
+        let fields = match self.algorithm {
+            Algorithm::TokenBucket => ["tokens", "last_refill", "requests"],
+            Algorithm::SlidingWindow => ["remaining", "last_request", "requests"],
+        };
+        let mut usage = Vec::new();
+
+        for key in scan_keys(&mut conn, prefix).await? {
+            let (remaining, last_request, requests): (Option<f64>, Option<f64>, Option<u64>) =
+                conn.hget(&key, &fields).await?;
+
+            if let (Some(remaining), Some(last_request)) = (remaining, last_request) {
+                usage.push(ClientUsage {
+                    client: key[prefix.len()..].to_string(),
+                    requests: requests.unwrap_or_default(),
+                    remaining,
+                    last_request: timestamp(last_request),
+                });
+            }
+        }
+
+        usage.sort_by(|a, b| a.client.cmp(&b.client));
+
+        Ok(usage)
+    }
+}
@@ -0,0 +1,104 @@
+use std::sync::Arc;
+
+use chrono::{DateTime, Duration, NaiveTime, Utc};
+use strum::IntoEnumIterator;
+use tracing::{error, info};
+
+use wh_core::repository::PricingRepository;
+use wh_core::retry::retry_delays;
+use wh_core::types::BiddingZone;
+use wh_core::util::get_storage_date;
+
+/// Same as the `cron(0 13 * * ? *)` EventBridge schedule used in AWS
+pub const DEFAULT_FETCH_TIME: NaiveTime = match NaiveTime::from_hms_opt(13, 0, 0) {
+    Some(time) => time,
+    None => panic!("Invalid default fetch time"),
+};
+
+/// Next time the pricing should be fetched at, `fetch_time` being in UTC
+pub fn next_fetch_time(now: DateTime<Utc>, fetch_time: NaiveTime) -> DateTime<Utc> {
+    let today = now.date_naive().and_time(fetch_time).and_utc();
+
+    if today > now {
+        return today;
+    }
+
+    today + Duration::days(1)
+}
+
+/// Runs the worker pipeline once a day in-process, retrying with the same backoff as
+/// the message handler does in AWS. Pricing is also fetched on start up if the
+/// current pricing of any zone is missing from the storage.
+pub async fn run_price_fetch_scheduler(
+    pricing_repository: Arc<dyn PricingRepository>,
+    fetch_time: NaiveTime,
+) {
+    let reqwest_client = Arc::new(reqwest::Client::new());
+
+    if !has_current_pricing(pricing_repository.as_ref()).await {
+        info!("Current pricing missing, fetching pricing on start up");
+        fetch_with_retries(&pricing_repository, &reqwest_client).await;
+    }
+
+    loop {
+        let next_fetch = next_fetch_time(Utc::now(), fetch_time);
+        info!("Next pricing fetch scheduled at {}", next_fetch);
+
+        sleep(next_fetch - Utc::now()).await;
+
+        fetch_with_retries(&pricing_repository, &reqwest_client).await;
+    }
+}
+
+/// Whether the current pricing of every zone the worker fetches is in the storage
+pub async fn has_current_pricing(pricing_repository: &dyn PricingRepository) -> bool {
+    let date = get_storage_date();
+
+    for zone in BiddingZone::iter() {
+        if !matches!(
+            pricing_repository.get_pricing(&zone, date).await,
+            Ok(Some(_))
+        ) {
+            return false;
+        }
+    }
+
+    true
+}
+
+async fn fetch_with_retries(
+    pricing_repository: &Arc<dyn PricingRepository>,
+    reqwest_client: &Arc<reqwest::Client>,
+) {
+    let mut delays = retry_delays();
+    let mut retry_attempt: u16 = 0;
+
+    loop {
+        match worker::fetch_and_store_pricing(pricing_repository.clone(), reqwest_client.clone())
+            .await
+        {
+            Ok(()) => {
+                info!("Pricing fetched and stored");
+                return;
+            }
+            Err(e) => error!(retry_attempt, "Fetching pricing failed: {}", e),
+        }
+
+        let Some(delay) = delays.next() else {
+            error!("MaxRetryAttemptsExceeded");
+            return;
+        };
+        retry_attempt += 1;
+
+        info!(
+            retry_attempt,
+            "Retrying pricing fetch in {} minutes",
+            delay.num_minutes()
+        );
+        sleep(delay).await;
+    }
+}
+
+async fn sleep(duration: Duration) {
+    tokio::time::sleep(duration.to_std().unwrap_or_default()).await;
+}
@@ -1,55 +1,255 @@
-use std::sync::Mutex;
+use std::collections::{BTreeMap, HashMap};
+use std::sync::RwLock;
+use std::time::Duration;
 
-use lazy_static::lazy_static;
+use async_trait::async_trait;
+use aws_sdk_ssm::types::ParameterType;
+use tracing::info;
 
-use crate::error::ApplicationError;
+use crate::common::cache::RefreshingCache;
+use crate::common::error::ApplicationError;
 
-lazy_static! {
-    static ref API_KEYS: Mutex<Vec<String>> = Mutex::new(Vec::new());
+pub const API_KEYS_PATH: &str = "/waterheater_calc/api_keys/";
+pub const ADMIN_KEYS_PATH: &str = "/waterheater_calc/admin_keys/";
+
+/// Where API keys are kept. Sources that can't be changed at runtime only implement
+/// [`ApiKeySource::fetch_api_keys`].
+#[async_trait]
+pub trait ApiKeySource: Send + Sync {
+    /// Every key by its name
+    async fn fetch_api_keys(&self) -> Result<BTreeMap<String, String>, ApplicationError>;
+
+    /// Names of the keys, without the keys themselves
+    async fn list_api_keys(&self) -> Result<Vec<String>, ApplicationError> {
+        Err(read_only())
+    }
+
+    async fn put_api_key(&self, _name: &str, _api_key: &str) -> Result<(), ApplicationError> {
+        Err(read_only())
+    }
+
+    /// Returns false if there is no key with the name
+    async fn delete_api_key(&self, _name: &str) -> Result<bool, ApplicationError> {
+        Err(read_only())
+    }
 }
 
-async fn update_api_keys_cache(api_keys: Vec<String>) {
-    let mut api_keys_cache = API_KEYS.lock().unwrap();
-    *api_keys_cache = api_keys;
+fn read_only() -> ApplicationError {
+    ApplicationError::Service("The API keys can't be changed".to_string())
 }
 
-async fn cache_api_keys() -> Result<(), ApplicationError> {
-    match fetch_api_keys().await {
-        Ok(api_keys) => {
-            update_api_keys_cache(api_keys).await;
-            Ok(())
+/// Keys stored as SecureString parameters under a path in SSM Parameter Store
+pub struct SsmApiKeySource {
+    client: aws_sdk_ssm::Client,
+    path: String,
+}
+
+impl SsmApiKeySource {
+    pub fn new(client: aws_sdk_ssm::Client, path: impl Into<String>) -> Self {
+        SsmApiKeySource {
+            client,
+            path: path.into(),
         }
-        Err(e) => Err(e),
+    }
+
+    fn parameter_name(&self, name: &str) -> String {
+        format!("{}/{}", self.path.trim_end_matches('/'), name)
     }
 }
 
-async fn fetch_api_keys() -> Result<Vec<String>, ApplicationError> {
-    let config = aws_config::load_from_env().await;
-    let client = aws_sdk_ssm::Client::new(&config);
+#[async_trait]
+impl ApiKeySource for SsmApiKeySource {
+    async fn fetch_api_keys(&self) -> Result<BTreeMap<String, String>, ApplicationError> {
+        let mut next_token: Option<String> = None;
+        let mut api_keys = BTreeMap::new();
+
+        loop {
+            let resp = self
+                .client
+                .get_parameters_by_path()
+                .path(&self.path)
+                .recursive(true)
+                .with_decryption(true)
+                .set_next_token(next_token)
+                .send()
+                .await
+                .map_err(Box::new)?;
+
+            for param in resp.parameters() {
+                if let (Some(name), Some(value)) = (param.name(), param.value()) {
+                    let name = name.rsplit('/').next().unwrap_or(name);
+                    api_keys.insert(name.to_string(), value.to_string());
+                }
+            }
+
+            if resp.next_token().is_none() {
+                break;
+            }
+
+            next_token = resp.next_token().map(|t| t.to_string());
+        }
+
+        Ok(api_keys)
+    }
+
+    async fn list_api_keys(&self) -> Result<Vec<String>, ApplicationError> {
+        let mut next_token: Option<String> = None;
+        let mut names: Vec<String> = Vec::new();
+
+        loop {
+            let resp = self
+                .client
+                .get_parameters_by_path()
+                .path(&self.path)
+                .recursive(true)
+                .set_next_token(next_token)
+                .send()
+                .await
+                .map_err(Box::new)?;
+
+            resp.parameters()
+                .iter()
+                .filter_map(|param| param.name())
+                .filter_map(|name| name.rsplit('/').next())
+                .for_each(|name| names.push(name.to_string()));
+
+            if resp.next_token().is_none() {
+                break;
+            }
+
+            next_token = resp.next_token().map(|t| t.to_string());
+        }
+
+        names.sort();
+
+        Ok(names)
+    }
+
+    async fn put_api_key(&self, name: &str, api_key: &str) -> Result<(), ApplicationError> {
+        self.client
+            .put_parameter()
+            .name(self.parameter_name(name))
+            .value(api_key)
+            .r#type(ParameterType::SecureString)
+            .overwrite(true)
+            .send()
+            .await
+            .map_err(|e| ApplicationError::SsmOperation(Box::new(e.into())))?;
 
-    let mut next_token: Option<String> = None;
-    let mut api_keys: Vec<String> = Vec::new();
+        Ok(())
+    }
 
-    loop {
-        let resp = client
-            .get_parameters_by_path()
-            .path("/waterheater_calc/api_keys/")
-            .recursive(true)
-            .with_decryption(true)
-            .set_next_token(next_token)
+    async fn delete_api_key(&self, name: &str) -> Result<bool, ApplicationError> {
+        match self
+            .client
+            .delete_parameter()
+            .name(self.parameter_name(name))
             .send()
-            .await?;
+            .await
+        {
+            Ok(_) => Ok(true),
+            Err(e)
+                if e.as_service_error()
+                    .is_some_and(|e| e.is_parameter_not_found()) =>
+            {
+                Ok(false)
+            }
+            Err(e) => Err(ApplicationError::SsmOperation(Box::new(e.into()))),
+        }
+    }
+}
 
-        resp.parameters()
-            .iter()
-            .for_each(|param| api_keys.push(param.value().unwrap().to_string()));
+/// Keys given in the configuration, for running without AWS. Keys added at runtime are
+/// only kept in memory.
+pub struct StaticApiKeySource {
+    keys: RwLock<BTreeMap<String, String>>,
+}
 
-        if resp.next_token().is_none() {
-            break;
+impl StaticApiKeySource {
+    /// The keys are named by their position in `keys`, e.g. `key-1`
+    pub fn new(keys: Vec<String>) -> Self {
+        StaticApiKeySource {
+            keys: RwLock::new(
+                keys.into_iter()
+                    .enumerate()
+                    .map(|(index, key)| (format!("key-{}", index + 1), key))
+                    .collect(),
+            ),
         }
+    }
+}
 
-        next_token = resp.next_token().map(|t| t.to_string());
+#[async_trait]
+impl ApiKeySource for StaticApiKeySource {
+    async fn fetch_api_keys(&self) -> Result<BTreeMap<String, String>, ApplicationError> {
+        Ok(self.keys.read().unwrap().clone())
     }
 
-    Ok(api_keys)
+    async fn list_api_keys(&self) -> Result<Vec<String>, ApplicationError> {
+        Ok(self.keys.read().unwrap().keys().cloned().collect())
+    }
+
+    async fn put_api_key(&self, name: &str, api_key: &str) -> Result<(), ApplicationError> {
+        self.keys
+            .write()
+            .unwrap()
+            .insert(name.to_string(), api_key.to_string());
+
+        Ok(())
+    }
+
+    async fn delete_api_key(&self, name: &str) -> Result<bool, ApplicationError> {
+        Ok(self.keys.write().unwrap().remove(name).is_some())
+    }
+}
+
+/// API keys fetched from the source on first use and again once `refresh_interval` has
+/// passed. If a refresh fails the previous keys are kept, and the fetch is retried after
+/// a short backoff.
+pub struct ApiKeyCache {
+    source: Box<dyn ApiKeySource>,
+    /// Names of the keys by key
+    keys: RefreshingCache<HashMap<String, String>>,
+}
+
+impl ApiKeyCache {
+    pub fn new(source: impl ApiKeySource + 'static, refresh_interval: Duration) -> Self {
+        ApiKeyCache {
+            source: Box::new(source),
+            keys: RefreshingCache::new("API keys", HashMap::new(), refresh_interval),
+        }
+    }
+
+    pub fn source(&self) -> &dyn ApiKeySource {
+        self.source.as_ref()
+    }
+
+    /// Makes the next lookup fetch the keys again, after they have been changed
+    pub fn invalidate(&self) {
+        self.keys.invalidate();
+    }
+
+    pub async fn contains(&self, api_key: &str) -> bool {
+        self.name_of(api_key).await.is_some()
+    }
+
+    /// Name of `api_key`, `None` if it's not a valid key
+    pub async fn name_of(&self, api_key: &str) -> Option<String> {
+        let keys = self
+            .keys
+            .get(|| async {
+                let api_keys = self.source.fetch_api_keys().await?;
+                info!(count = api_keys.len(), "Refreshed API keys");
+
+                Ok::<_, ApplicationError>(
+                    api_keys
+                        .into_iter()
+                        .map(|(name, key)| (key, name))
+                        .collect(),
+                )
+            })
+            .await;
+
+        keys.get(api_key).cloned()
+    }
 }
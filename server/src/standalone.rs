@@ -0,0 +1,121 @@
+use std::env;
+use std::net::{Ipv4Addr, SocketAddr};
+use std::sync::Arc;
+
+use axum::Router;
+use chrono::NaiveTime;
+use lambda_http::Error;
+use tokio::net::TcpListener;
+use tracing::info;
+
+use wh_core::device::file::FileDeviceRepository;
+#[cfg(feature = "sqlite")]
+use wh_core::device::sqlite::SqliteDeviceRepository;
+use wh_core::device::DeviceRepository;
+use wh_core::repository::file::FilePricingRepository;
+#[cfg(feature = "sqlite")]
+use wh_core::repository::sqlite::SqlitePricingRepository;
+use wh_core::repository::PricingRepository;
+
+use crate::scheduler::DEFAULT_FETCH_TIME;
+
+const DEFAULT_PORT: u16 = 3000;
+
+/// Where the standalone server reads the pricing from, set with `PRICING_STORAGE`
+/// (`file` or `sqlite`) and `PRICING_STORAGE_PATH`.
+pub enum PricingStorage {
+    File(String),
+    #[cfg(feature = "sqlite")]
+    Sqlite(String),
+}
+
+pub struct StandaloneConfig {
+    pub port: u16,
+    pub storage: PricingStorage,
+    /// Time of day in UTC to fetch the pricing at in all-in-one mode, `PRICE_FETCH_TIME`
+    pub fetch_time: NaiveTime,
+    /// Accepted API keys, comma separated in `API_KEYS`
+    pub api_keys: Vec<String>,
+    /// Keys of the admin API, comma separated in `ADMIN_API_KEYS`. The admin API is
+    /// closed when there are none.
+    pub admin_api_keys: Vec<String>,
+}
+
+impl StandaloneConfig {
+    pub fn from_env() -> Result<Self, Error> {
+        let port = match env::var("PORT") {
+            Ok(port) => port.parse()?,
+            Err(_) => DEFAULT_PORT,
+        };
+
+        let storage = env::var("PRICING_STORAGE").unwrap_or("file".into());
+
+        let storage = match storage.as_str() {
+            "file" => {
+                PricingStorage::File(env::var("PRICING_STORAGE_PATH").unwrap_or("./pricing".into()))
+            }
+            #[cfg(feature = "sqlite")]
+            "sqlite" => PricingStorage::Sqlite(
+                env::var("PRICING_STORAGE_PATH").unwrap_or("./pricing.db".into()),
+            ),
+            other => return Err(format!("Unsupported PRICING_STORAGE: {other}").into()),
+        };
+
+        let fetch_time = match env::var("PRICE_FETCH_TIME") {
+            Ok(time) => NaiveTime::parse_from_str(&time, "%H:%M")?,
+            Err(_) => DEFAULT_FETCH_TIME,
+        };
+
+        let api_keys = keys_from_env("API_KEYS");
+        let admin_api_keys = keys_from_env("ADMIN_API_KEYS");
+
+        Ok(StandaloneConfig {
+            port,
+            storage,
+            fetch_time,
+            api_keys,
+            admin_api_keys,
+        })
+    }
+
+    pub fn pricing_repository(&self) -> Result<Arc<dyn PricingRepository>, Error> {
+        match &self.storage {
+            PricingStorage::File(path) => Ok(Arc::new(FilePricingRepository::new(path))),
+            #[cfg(feature = "sqlite")]
+            PricingStorage::Sqlite(path) => Ok(Arc::new(SqlitePricingRepository::open(path)?)),
+        }
+    }
+
+    /// Registered devices are kept in the same storage as the pricing
+    pub fn device_repository(&self) -> Result<Arc<dyn DeviceRepository>, Error> {
+        match &self.storage {
+            PricingStorage::File(path) => Ok(Arc::new(FileDeviceRepository::new(path))),
+            #[cfg(feature = "sqlite")]
+            PricingStorage::Sqlite(path) => Ok(Arc::new(SqliteDeviceRepository::open(path)?)),
+        }
+    }
+}
+
+pub async fn serve(app: Router, port: u16) -> Result<(), Error> {
+    let listener = TcpListener::bind((Ipv4Addr::UNSPECIFIED, port)).await?;
+
+    info!("Listening on {}", listener.local_addr()?);
+
+    axum::serve(
+        listener,
+        app.into_make_service_with_connect_info::<SocketAddr>(),
+    )
+    .await?;
+
+    Ok(())
+}
+
+fn keys_from_env(name: &str) -> Vec<String> {
+    env::var(name)
+        .unwrap_or_default()
+        .split(',')
+        .map(str::trim)
+        .filter(|key| !key.is_empty())
+        .map(str::to_string)
+        .collect()
+}
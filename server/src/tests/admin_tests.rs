@@ -0,0 +1,282 @@
+#![cfg(test)]
+
+use std::net::IpAddr;
+use std::sync::Arc;
+
+use axum::body::{to_bytes, Body};
+use axum::http::{Request, StatusCode};
+use lambda_http::tower::ServiceExt;
+use wh_core::api::{
+    ApiKeyInfo, ClientRateLimit, ClientUsage, CreatedApiKey, DeviceInfo, DeviceRegistration,
+};
+
+use crate::auth::{ADMIN_KEY_HEADER, API_KEY_HEADER};
+use crate::devices::ClientId;
+use crate::rate_limit::memory::InMemoryRateLimiter;
+use crate::rate_limit::{Algorithm, Quota, RateLimiter};
+use crate::tests::TestState;
+use crate::{create_app, AppState};
+
+const ADMIN_KEY: &str = "admin-secret";
+
+// Twenty requests at once, one more every second
+const QUOTA: Quota = Quota {
+    capacity: 20,
+    refill_per_minute: 60.0,
+};
+
+fn create_state(limiter: Arc<InMemoryRateLimiter>) -> AppState {
+    TestState {
+        rate_limiter: limiter,
+        api_keys: vec!["secret"],
+        admin_keys: vec![ADMIN_KEY],
+        ..Default::default()
+    }
+    .build()
+}
+
+async fn admin_request(
+    state: &AppState,
+    method: &str,
+    uri: &str,
+    body: Option<&str>,
+) -> (StatusCode, Vec<u8>) {
+    let request = Request::builder()
+        .method(method)
+        .uri(uri)
+        .header(ADMIN_KEY_HEADER, ADMIN_KEY)
+        .header("content-type", "application/json")
+        .body(
+            body.map(|body| Body::from(body.to_string()))
+                .unwrap_or_default(),
+        )
+        .unwrap();
+    let response = create_app(state.clone()).oneshot(request).await.unwrap();
+
+    let status = response.status();
+    let body = to_bytes(response.into_body(), usize::MAX).await.unwrap();
+
+    (status, body.to_vec())
+}
+
+#[tokio::test]
+async fn test_admin_api_requires_admin_key() {
+    let state = create_state(Arc::new(InMemoryRateLimiter::new(Algorithm::TokenBucket)));
+
+    let status = |request: Request<Body>| {
+        let app = create_app(state.clone());
+        async move { app.oneshot(request).await.unwrap().status() }
+    };
+
+    assert_eq!(
+        status(
+            Request::get("/api/admin/devices")
+                .body(Body::empty())
+                .unwrap()
+        )
+        .await,
+        StatusCode::UNAUTHORIZED
+    );
+    // API keys don't give access to the admin API
+    assert_eq!(
+        status(
+            Request::get("/api/admin/devices")
+                .header(API_KEY_HEADER, "secret")
+                .body(Body::empty())
+                .unwrap()
+        )
+        .await,
+        StatusCode::UNAUTHORIZED
+    );
+    assert_eq!(
+        status(
+            Request::get("/api/admin/devices")
+                .header(ADMIN_KEY_HEADER, "wrong")
+                .body(Body::empty())
+                .unwrap()
+        )
+        .await,
+        StatusCode::UNAUTHORIZED
+    );
+    assert_eq!(
+        status(
+            Request::get("/api/admin/devices")
+                .header(ADMIN_KEY_HEADER, ADMIN_KEY)
+                .body(Body::empty())
+                .unwrap()
+        )
+        .await,
+        StatusCode::OK
+    );
+}
+
+#[tokio::test]
+async fn test_created_api_key_is_accepted_until_deleted() {
+    let state = create_state(Arc::new(InMemoryRateLimiter::new(Algorithm::TokenBucket)));
+
+    let (status, body) = admin_request(
+        &state,
+        "POST",
+        "/api/admin/api-keys",
+        Some(r#"{"name":"sauna"}"#),
+    )
+    .await;
+    assert_eq!(status, StatusCode::CREATED);
+    let created: CreatedApiKey = serde_json::from_slice(&body).unwrap();
+    assert!(state.auth.api_keys.contains(&created.key).await);
+
+    let (status, _) = admin_request(
+        &state,
+        "POST",
+        "/api/admin/api-keys",
+        Some(r#"{"name":"sauna"}"#),
+    )
+    .await;
+    assert_eq!(status, StatusCode::CONFLICT);
+
+    let (_, body) = admin_request(&state, "GET", "/api/admin/api-keys", None).await;
+    let keys: Vec<ApiKeyInfo> = serde_json::from_slice(&body).unwrap();
+    let names: Vec<&str> = keys.iter().map(|key| key.name.as_str()).collect();
+    assert_eq!(names, ["key-1", "sauna"]);
+
+    let (status, _) = admin_request(&state, "DELETE", "/api/admin/api-keys/sauna", None).await;
+    assert_eq!(status, StatusCode::NO_CONTENT);
+    assert!(!state.auth.api_keys.contains(&created.key).await);
+
+    let (status, _) = admin_request(&state, "DELETE", "/api/admin/api-keys/sauna", None).await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+}
+
+#[tokio::test]
+async fn test_invalid_api_key_name_is_rejected() {
+    let state = create_state(Arc::new(InMemoryRateLimiter::new(Algorithm::TokenBucket)));
+
+    let (status, _) = admin_request(
+        &state,
+        "POST",
+        "/api/admin/api-keys",
+        Some(r#"{"name":"../other"}"#),
+    )
+    .await;
+
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+}
+
+#[tokio::test]
+async fn test_admin_manages_devices() {
+    let state = create_state(Arc::new(InMemoryRateLimiter::new(Algorithm::TokenBucket)));
+    let (device, token) = state
+        .devices
+        .register(DeviceRegistration::default())
+        .await
+        .unwrap();
+    assert!(state.devices.find_by_token(&token).await.unwrap().is_some());
+
+    let uri = format!("/api/admin/devices/{}", device.id);
+    let (status, body) = admin_request(
+        &state,
+        "PUT",
+        &uri,
+        Some(r#"{"name":"Boiler","zone":"fi","profile":{"mode":"always_on"}}"#),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK);
+    let updated: DeviceInfo = serde_json::from_slice(&body).unwrap();
+    assert_eq!(updated.name.as_deref(), Some("Boiler"));
+
+    let (_, body) = admin_request(&state, "GET", "/api/admin/devices", None).await;
+    let devices: Vec<DeviceInfo> = serde_json::from_slice(&body).unwrap();
+    assert_eq!(devices, vec![updated]);
+
+    let (status, _) = admin_request(&state, "DELETE", &uri, None).await;
+    assert_eq!(status, StatusCode::NO_CONTENT);
+    let (status, _) = admin_request(&state, "GET", &uri, None).await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+
+    // The token stops working even though its lookup was cached
+    assert!(state.devices.find_by_token(&token).await.unwrap().is_none());
+}
+
+#[tokio::test]
+async fn test_rate_limit_override_and_usage() {
+    let limiter = Arc::new(InMemoryRateLimiter::new(Algorithm::TokenBucket));
+    let state = create_state(limiter.clone());
+    let client = ClientId::Ip(IpAddr::from([192, 168, 1, 10]));
+
+    let (status, body) = admin_request(
+        &state,
+        "PUT",
+        "/api/admin/rate-limits/192.168.1.10",
+        Some(r#"{"capacity":1,"refill_per_minute":1.0}"#),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK);
+    let limit: ClientRateLimit = serde_json::from_slice(&body).unwrap();
+    assert_eq!(limit.capacity, 1);
+
+    // The override takes the place of the default capacity of 20
+    assert!(
+        limiter
+            .check(&client, None, QUOTA, 0.0)
+            .await
+            .unwrap()
+            .allowed
+    );
+    assert!(
+        !limiter
+            .check(&client, None, QUOTA, 0.0)
+            .await
+            .unwrap()
+            .allowed
+    );
+
+    let (_, body) = admin_request(&state, "GET", "/api/admin/usage", None).await;
+    let usage: Vec<ClientUsage> = serde_json::from_slice(&body).unwrap();
+    assert_eq!(usage.len(), 1);
+    assert_eq!(usage[0].client, "192.168.1.10");
+    assert_eq!(usage[0].requests, 2);
+
+    let (_, body) = admin_request(&state, "GET", "/api/admin/rate-limits", None).await;
+    let overrides: Vec<ClientRateLimit> = serde_json::from_slice(&body).unwrap();
+    assert_eq!(overrides, vec![limit]);
+
+    let (status, _) = admin_request(
+        &state,
+        "DELETE",
+        "/api/admin/rate-limits/192.168.1.10",
+        None,
+    )
+    .await;
+    assert_eq!(status, StatusCode::NO_CONTENT);
+
+    // Back to the default refill rate of one token per second
+    assert!(
+        limiter
+            .check(&client, None, QUOTA, 1.0)
+            .await
+            .unwrap()
+            .allowed
+    );
+    assert!(
+        !limiter
+            .check(&client, None, QUOTA, 1.0)
+            .await
+            .unwrap()
+            .allowed
+    );
+}
+
+#[tokio::test]
+async fn test_invalid_rate_limit_is_rejected() {
+    let state = create_state(Arc::new(InMemoryRateLimiter::new(Algorithm::TokenBucket)));
+
+    let (status, _) = admin_request(
+        &state,
+        "PUT",
+        "/api/admin/rate-limits/device:boiler",
+        Some(r#"{"capacity":0,"refill_per_minute":-1.0}"#),
+    )
+    .await;
+
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+}
@@ -0,0 +1,195 @@
+#![cfg(test)]
+
+use std::collections::BTreeMap;
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use async_trait::async_trait;
+use axum::body::Body;
+use axum::http::{Request, StatusCode};
+use lambda_http::tower::ServiceExt;
+
+use crate::auth::{matches_route, parse_routes, AnonymousAccess, AuthPolicy};
+use crate::common::error::ApplicationError;
+use crate::secrets::{ApiKeyCache, ApiKeySource};
+use crate::tests::TestState;
+use crate::{create_app, AppState};
+
+fn create_state(policy: AuthPolicy) -> AppState {
+    TestState {
+        api_keys: vec!["secret"],
+        admin_keys: vec!["admin-secret"],
+        auth_policy: policy,
+        ..Default::default()
+    }
+    .build()
+}
+
+async fn status(policy: AuthPolicy, request: Request<Body>) -> StatusCode {
+    create_app(create_state(policy))
+        .oneshot(request)
+        .await
+        .unwrap()
+        .status()
+}
+
+#[test]
+fn test_longest_route_prefix_wins() {
+    let policy = AuthPolicy {
+        default: AnonymousAccess::Limit,
+        routes: parse_routes("/api/v3=deny, /api/v3/zones/fi=allow").unwrap(),
+    };
+
+    assert_eq!(
+        policy.anonymous_access("/api/v2/prices/fi"),
+        AnonymousAccess::Limit
+    );
+    assert_eq!(
+        policy.anonymous_access("/api/v3/zones/se3/schedule"),
+        AnonymousAccess::Deny
+    );
+    assert_eq!(
+        policy.anonymous_access("/api/v3/zones/fi/schedule"),
+        AnonymousAccess::Allow
+    );
+    assert!(parse_routes("/api/v3").is_err());
+    assert!(parse_routes("/api/v3=maybe").is_err());
+}
+
+#[test]
+fn test_routes_match_whole_segments() {
+    let policy = AuthPolicy {
+        default: AnonymousAccess::Limit,
+        routes: parse_routes("/api/v2/prices=allow, /api/v3/dev=deny").unwrap(),
+    };
+
+    assert_eq!(
+        policy.anonymous_access("/api/v2/prices"),
+        AnonymousAccess::Allow
+    );
+    assert_eq!(
+        policy.anonymous_access("/api/v2/prices/fi"),
+        AnonymousAccess::Allow
+    );
+    assert_eq!(
+        policy.anonymous_access("/api/v2/pricesx"),
+        AnonymousAccess::Limit
+    );
+    assert_eq!(
+        policy.anonymous_access("/api/v3/devices"),
+        AnonymousAccess::Limit
+    );
+    assert!(matches_route("/api/v3/devices", "/"));
+    assert!(matches_route("/api/v3/devices", "/api/v3/"));
+}
+
+#[tokio::test]
+async fn test_denied_route_requires_api_key() {
+    let policy = AuthPolicy {
+        default: AnonymousAccess::Deny,
+        routes: Vec::new(),
+    };
+    let uri = "/api/v2/unknown";
+
+    let anonymous = Request::get(uri).body(Body::empty()).unwrap();
+    assert_eq!(
+        status(policy.clone(), anonymous).await,
+        StatusCode::UNAUTHORIZED
+    );
+
+    // Passing authentication ends in the 404 of the unknown path
+    let header = Request::get(uri)
+        .header("x-api-key", "secret")
+        .body(Body::empty())
+        .unwrap();
+    assert_eq!(status(policy.clone(), header).await, StatusCode::NOT_FOUND);
+
+    let query = Request::get(format!("{uri}?api_key=secret"))
+        .body(Body::empty())
+        .unwrap();
+    assert_eq!(status(policy, query).await, StatusCode::NOT_FOUND);
+}
+
+#[tokio::test]
+async fn test_invalid_api_key_is_rejected_on_open_routes() {
+    let request = Request::get("/api/v2/unknown?api_key=wrong")
+        .body(Body::empty())
+        .unwrap();
+
+    assert_eq!(
+        status(AuthPolicy::default(), request).await,
+        StatusCode::UNAUTHORIZED
+    );
+}
+
+fn keys(keys: &[&str]) -> BTreeMap<String, String> {
+    keys.iter()
+        .map(|key| (format!("{key}-name"), key.to_string()))
+        .collect()
+}
+
+struct CountingSource {
+    fetches: AtomicU32,
+    keys: Mutex<Result<BTreeMap<String, String>, String>>,
+}
+
+#[async_trait]
+impl ApiKeySource for Arc<CountingSource> {
+    async fn fetch_api_keys(&self) -> Result<BTreeMap<String, String>, ApplicationError> {
+        self.fetches.fetch_add(1, Ordering::SeqCst);
+        self.keys
+            .lock()
+            .unwrap()
+            .clone()
+            .map_err(ApplicationError::Service)
+    }
+}
+
+#[tokio::test]
+async fn test_api_key_cache_refreshes_and_keeps_keys_on_failure() {
+    let source = Arc::new(CountingSource {
+        fetches: AtomicU32::new(0),
+        keys: Mutex::new(Ok(keys(&["first"]))),
+    });
+
+    let cache = ApiKeyCache::new(source.clone(), Duration::from_secs(300));
+    assert!(cache.contains("first").await);
+    assert!(!cache.contains("second").await);
+    assert_eq!(source.fetches.load(Ordering::SeqCst), 1);
+
+    let cache = ApiKeyCache::new(source.clone(), Duration::ZERO);
+    assert!(cache.contains("first").await);
+
+    *source.keys.lock().unwrap() = Ok(keys(&["second"]));
+    assert!(cache.contains("second").await);
+    assert!(!cache.contains("first").await);
+
+    *source.keys.lock().unwrap() = Err("SSM unavailable".to_string());
+    assert!(cache.contains("second").await);
+}
+
+#[tokio::test(start_paused = true)]
+async fn test_api_key_cache_retries_failed_refresh_after_backoff() {
+    let source = Arc::new(CountingSource {
+        fetches: AtomicU32::new(0),
+        keys: Mutex::new(Err("SSM unavailable".to_string())),
+    });
+
+    let cache = ApiKeyCache::new(source.clone(), Duration::from_secs(300));
+    assert!(!cache.contains("first").await);
+
+    *source.keys.lock().unwrap() = Ok(keys(&["first"]));
+    assert!(!cache.contains("first").await);
+    assert_eq!(source.fetches.load(Ordering::SeqCst), 1);
+
+    // Retried long before the refresh interval has passed
+    tokio::time::advance(Duration::from_secs(10)).await;
+    assert!(cache.contains("first").await);
+    assert_eq!(source.fetches.load(Ordering::SeqCst), 2);
+
+    // Fresh for the whole refresh interval once fetched
+    tokio::time::advance(Duration::from_secs(60)).await;
+    assert!(cache.contains("first").await);
+    assert_eq!(source.fetches.load(Ordering::SeqCst), 2);
+}
@@ -0,0 +1,125 @@
+#![cfg(test)]
+
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+
+use async_trait::async_trait;
+use chrono::{Duration, FixedOffset, NaiveDate, TimeZone, Utc};
+use rust_decimal_macros::dec;
+use wh_core::{
+    price_series::PriceSeries,
+    repository::{
+        memory::InMemoryPricingRepository, DeliveryDay, PricingRepository, RepositoryError,
+    },
+    test_utils::MockTimeProvider,
+    types::BiddingZone,
+    units::CentsPerKwh,
+};
+
+use crate::common::cache::CachedPricingRepository;
+
+#[derive(Default)]
+struct CountingRepository {
+    inner: InMemoryPricingRepository,
+    fetches: AtomicUsize,
+}
+
+#[async_trait]
+impl PricingRepository for CountingRepository {
+    async fn get_pricing(
+        &self,
+        zone: &BiddingZone,
+        date: NaiveDate,
+    ) -> Result<Option<PriceSeries<CentsPerKwh>>, RepositoryError> {
+        self.fetches.fetch_add(1, Ordering::SeqCst);
+        tokio::time::sleep(std::time::Duration::from_millis(10)).await;
+        self.inner.get_pricing(zone, date).await
+    }
+
+    async fn store_pricing(
+        &self,
+        zone: &BiddingZone,
+        date: NaiveDate,
+        pricing: &PriceSeries<CentsPerKwh>,
+    ) -> Result<(), RepositoryError> {
+        self.inner.store_pricing(zone, date, pricing).await
+    }
+
+    async fn get_delivery_days(
+        &self,
+        zone: &BiddingZone,
+        from: NaiveDate,
+        to: NaiveDate,
+    ) -> Result<Vec<DeliveryDay>, RepositoryError> {
+        self.inner.get_delivery_days(zone, from, to).await
+    }
+
+    async fn store_delivery_day(
+        &self,
+        zone: &BiddingZone,
+        day: &DeliveryDay,
+    ) -> Result<(), RepositoryError> {
+        self.inner.store_delivery_day(zone, day).await
+    }
+}
+
+fn create_pricing() -> PriceSeries<CentsPerKwh> {
+    let start = FixedOffset::east_opt(3 * 3600)
+        .unwrap()
+        .with_ymd_and_hms(2024, 9, 17, 0, 0, 0)
+        .unwrap();
+
+    PriceSeries::from_slots(Duration::hours(1), [(start, CentsPerKwh::eur(dec!(1.5)))])
+}
+
+fn date() -> NaiveDate {
+    NaiveDate::from_ymd_opt(2024, 9, 17).unwrap()
+}
+
+#[tokio::test]
+async fn test_concurrent_requests_share_one_fetch() {
+    let inner = Arc::new(CountingRepository::default());
+    inner
+        .store_pricing(&BiddingZone::FI, date(), &create_pricing())
+        .await
+        .unwrap();
+
+    let now = Utc.with_ymd_and_hms(2024, 9, 17, 12, 0, 0).unwrap();
+    let cache = CachedPricingRepository::new(inner.clone(), MockTimeProvider::new(now));
+
+    let (first, second, third) = tokio::join!(
+        cache.get_pricing(&BiddingZone::FI, date()),
+        cache.get_pricing(&BiddingZone::FI, date()),
+        cache.get_pricing(&BiddingZone::FI, date()),
+    );
+
+    assert_eq!(first.unwrap(), Some(create_pricing()));
+    assert_eq!(second.unwrap(), Some(create_pricing()));
+    assert_eq!(third.unwrap(), Some(create_pricing()));
+
+    cache.get_pricing(&BiddingZone::FI, date()).await.unwrap();
+    assert_eq!(inner.fetches.load(Ordering::SeqCst), 1);
+}
+
+#[tokio::test]
+async fn test_missing_pricing_is_not_cached() {
+    let inner = Arc::new(CountingRepository::default());
+    let now = Utc.with_ymd_and_hms(2024, 9, 17, 12, 0, 0).unwrap();
+    let cache = CachedPricingRepository::new(inner.clone(), MockTimeProvider::new(now));
+
+    assert_eq!(
+        cache.get_pricing(&BiddingZone::FI, date()).await.unwrap(),
+        None
+    );
+
+    cache
+        .store_pricing(&BiddingZone::FI, date(), &create_pricing())
+        .await
+        .unwrap();
+
+    assert_eq!(
+        cache.get_pricing(&BiddingZone::FI, date()).await.unwrap(),
+        Some(create_pricing())
+    );
+    assert_eq!(inner.fetches.load(Ordering::SeqCst), 2);
+}
@@ -0,0 +1,137 @@
+#![cfg(test)]
+
+use std::net::IpAddr;
+
+use axum::http::{HeaderMap, HeaderValue};
+
+use crate::client_ip::{ForwardedHeader, TrustedProxies};
+
+const PEER: [u8; 4] = [10, 0, 0, 2];
+
+fn headers(name: &'static str, values: &[&str]) -> HeaderMap {
+    let mut headers = HeaderMap::new();
+    for value in values {
+        headers.append(name, HeaderValue::from_str(value).unwrap());
+    }
+    headers
+}
+
+fn ip(address: &str) -> IpAddr {
+    address.parse().unwrap()
+}
+
+fn proxies(networks: &[&str], hops: usize) -> TrustedProxies {
+    TrustedProxies {
+        networks: networks
+            .iter()
+            .map(|network| network.parse().unwrap())
+            .collect(),
+        hops,
+        header: ForwardedHeader::XForwardedFor,
+    }
+}
+
+#[test]
+fn test_forwarded_for_is_ignored_without_trusted_proxies() {
+    let spoofed = headers("x-forwarded-for", &["198.51.100.1"]);
+
+    assert_eq!(
+        TrustedProxies::default().client_ip(&spoofed, IpAddr::from(PEER)),
+        IpAddr::from(PEER)
+    );
+}
+
+#[test]
+fn test_client_is_first_untrusted_address_from_the_right() {
+    // The client prepended a spoofed address, the load balancer appended the real one
+    let chain = headers(
+        "x-forwarded-for",
+        &["198.51.100.1, 203.0.113.7", "10.0.0.1"],
+    );
+
+    assert_eq!(
+        proxies(&["10.0.0.0/8"], 0).client_ip(&chain, IpAddr::from(PEER)),
+        ip("203.0.113.7")
+    );
+    // A peer outside the trusted networks is the client itself
+    assert_eq!(
+        proxies(&["10.0.0.0/8"], 0).client_ip(&chain, ip("192.0.2.1")),
+        ip("192.0.2.1")
+    );
+}
+
+#[test]
+fn test_hops_trust_proxies_in_front_whatever_their_address() {
+    let chain = headers("x-forwarded-for", &["198.51.100.1, 203.0.113.7, 192.0.2.9"]);
+
+    assert_eq!(
+        proxies(&[], 1).client_ip(&chain, IpAddr::from(PEER)),
+        ip("192.0.2.9")
+    );
+    assert_eq!(
+        proxies(&[], 2).client_ip(&chain, IpAddr::from(PEER)),
+        ip("203.0.113.7")
+    );
+}
+
+#[test]
+fn test_source_ip_added_by_api_gateway_is_not_counted_twice() {
+    // Behind CloudFront, API Gateway appends the address of the edge it saw
+    let chain = headers("x-forwarded-for", &["203.0.113.7, 192.0.2.9"]);
+
+    assert_eq!(
+        proxies(&[], 1).client_ip(&chain, ip("192.0.2.9")),
+        ip("203.0.113.7")
+    );
+    assert_eq!(
+        TrustedProxies::default().client_ip(&chain, ip("192.0.2.9")),
+        ip("192.0.2.9")
+    );
+}
+
+#[test]
+fn test_forwarded_header_is_parsed() {
+    let chain = headers(
+        "forwarded",
+        &[r#"for=198.51.100.1, For="[2001:db8:cafe::17]:4711";proto=https, for=10.0.0.1:80"#],
+    );
+    let trusted = TrustedProxies {
+        header: ForwardedHeader::Forwarded,
+        ..proxies(&["10.0.0.0/8"], 0)
+    };
+
+    assert_eq!(
+        trusted.client_ip(&chain, IpAddr::from(PEER)),
+        ip("2001:db8:cafe::17")
+    );
+
+    // X-Forwarded-For is not read when the proxies use Forwarded
+    let spoofed = headers("x-forwarded-for", &["198.51.100.1"]);
+    assert_eq!(
+        trusted.client_ip(&spoofed, IpAddr::from(PEER)),
+        IpAddr::from(PEER)
+    );
+}
+
+#[test]
+fn test_walk_stops_at_hidden_or_malformed_address() {
+    let chain = headers(
+        "forwarded",
+        &["for=198.51.100.1, for=_hidden, for=10.0.0.1"],
+    );
+    let trusted = TrustedProxies {
+        header: ForwardedHeader::Forwarded,
+        ..proxies(&["10.0.0.0/8"], 0)
+    };
+
+    assert_eq!(
+        trusted.client_ip(&chain, IpAddr::from(PEER)),
+        ip("10.0.0.1")
+    );
+
+    let chain = headers("x-forwarded-for", &["198.51.100.1, not-an-ip, 10.0.0.1"]);
+    assert_eq!(
+        proxies(&["10.0.0.0/8"], 0).client_ip(&chain, IpAddr::from(PEER)),
+        ip("10.0.0.1")
+    );
+}
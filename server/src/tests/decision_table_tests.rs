@@ -0,0 +1,87 @@
+#![cfg(test)]
+
+use chrono::{DateTime, Duration, FixedOffset, TimeZone, Utc};
+use rust_decimal::Decimal;
+use wh_core::{
+    price_series::PriceSeries, test_utils::MockTimeProvider, types::BiddingZone, units::CentsPerKwh,
+};
+
+use crate::v2::decision_table::DecisionTable;
+use wh_core::schedule::{calculate_cheapest_start_time, get_filtered_pricing};
+
+fn create_two_day_series() -> PriceSeries<CentsPerKwh> {
+    let offset = FixedOffset::east_opt(3 * 3600).unwrap();
+    let first_hour = offset.with_ymd_and_hms(2024, 4, 8, 0, 0, 0).unwrap();
+
+    PriceSeries::from_slots(
+        Duration::hours(1),
+        (0..48).map(|i| {
+            // Deterministic but uneven prices, so that every window has a distinct cost
+            let price = Decimal::from((i * 37 + 11) % 29);
+            (first_hour + Duration::hours(i), CentsPerKwh::eur(price))
+        }),
+    )
+}
+
+fn compute_directly(
+    pricing: &PriceSeries<CentsPerKwh>,
+    now: DateTime<Utc>,
+    hours: u32,
+    starting_hour: u32,
+    ending_hour: u32,
+) -> Option<DateTime<FixedOffset>> {
+    let time_provider = MockTimeProvider::new(now);
+    let filtered_pricing = get_filtered_pricing(
+        &time_provider,
+        &BiddingZone::FI,
+        pricing,
+        starting_hour,
+        ending_hour,
+    );
+
+    if filtered_pricing.is_empty() || filtered_pricing.len() < hours as usize {
+        return None;
+    }
+
+    calculate_cheapest_start_time(&filtered_pricing, hours)
+}
+
+#[test]
+fn test_decision_table_matches_direct_computation() {
+    let pricing = create_two_day_series();
+    let tz = BiddingZone::FI.to_tz();
+    let table = DecisionTable::new(pricing.clone());
+
+    // Every hour of both days in UTC, including the evening before the first day
+    let first = Utc.with_ymd_and_hms(2024, 4, 7, 21, 0, 0).unwrap();
+    for offset in 0..48 {
+        let now = first + Duration::hours(offset) + Duration::minutes(30);
+        let current_time = now.with_timezone(&tz);
+
+        for (starting_hour, ending_hour) in [(0, 23), (6, 18), (22, 7), (17, 3), (5, 5)] {
+            for hours in [1, 3, 6] {
+                assert_eq!(
+                    table.cheapest_start_time(&current_time, hours, starting_hour, ending_hour),
+                    compute_directly(&pricing, now, hours, starting_hour, ending_hour),
+                    "now {now}, {hours} hours between {starting_hour} and {ending_hour}"
+                );
+            }
+        }
+    }
+}
+
+#[test]
+fn test_decision_table_has_no_entry_without_enough_prices() {
+    let pricing = create_two_day_series();
+    let tz = BiddingZone::FI.to_tz();
+    let table = DecisionTable::new(pricing);
+
+    let current_time = tz.with_ymd_and_hms(2024, 4, 8, 10, 0, 0).unwrap();
+
+    assert!(table
+        .cheapest_start_time(&current_time, 5, 10, 14)
+        .is_none());
+    assert!(table
+        .cheapest_start_time(&current_time, 4, 10, 14)
+        .is_some());
+}
@@ -0,0 +1,289 @@
+#![cfg(test)]
+
+use std::sync::Arc;
+
+use axum::body::{to_bytes, Body};
+use axum::http::{Request, StatusCode};
+use chrono::{Duration, TimeZone};
+use chrono_tz::Europe::Helsinki;
+use lambda_http::tower::ServiceExt;
+use rust_decimal_macros::dec;
+use wh_core::api::RegisteredDevice;
+use wh_core::device::memory::InMemoryDeviceRepository;
+use wh_core::device::{DeviceMode, DeviceProfile, DeviceRepository};
+use wh_core::price_series::PriceSeries;
+use wh_core::repository::PricingRepository;
+use wh_core::tariff::Tariff;
+use wh_core::types::BiddingZone;
+use wh_core::units::CentsPerKwh;
+use wh_core::util::storage_date_at;
+use wh_core::{repository::memory::InMemoryPricingRepository, time_provider::SystemTimeProvider};
+
+use crate::devices::hash_token;
+use crate::tests::TestState;
+use crate::v2::decision_table::DecisionTables;
+use crate::v2::fallback::{FallbackPolicy, PricingFallback};
+use crate::v2::service::plan_device;
+use crate::{create_app, AppState};
+
+fn create_state(devices: Arc<InMemoryDeviceRepository>) -> AppState {
+    TestState {
+        devices,
+        ..Default::default()
+    }
+    .build()
+}
+
+async fn register(state: AppState, body: &str) -> (StatusCode, Vec<u8>) {
+    let response = create_app(state)
+        .oneshot(
+            Request::post("/api/v3/devices")
+                .header("content-type", "application/json")
+                .body(Body::from(body.to_string()))
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+
+    let status = response.status();
+    let body = to_bytes(response.into_body(), usize::MAX).await.unwrap();
+
+    (status, body.to_vec())
+}
+
+#[tokio::test]
+async fn test_registration_stores_only_token_hash() {
+    let repository = Arc::new(InMemoryDeviceRepository::new());
+
+    let (status, body) = register(
+        create_state(repository.clone()),
+        r#"{"name":"Boiler","zone":"fi"}"#,
+    )
+    .await;
+    assert_eq!(status, StatusCode::CREATED);
+
+    let registered: RegisteredDevice = serde_json::from_slice(&body).unwrap();
+    assert_eq!(registered.name.as_deref(), Some("Boiler"));
+    assert_eq!(registered.zone, Some(BiddingZone::FI));
+
+    assert_eq!(
+        repository
+            .get_device_by_token(&registered.token)
+            .await
+            .unwrap(),
+        None
+    );
+    let stored = repository
+        .get_device_by_token(&hash_token(&registered.token))
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(stored.id, registered.id);
+}
+
+#[tokio::test]
+async fn test_invalid_registration_is_rejected() {
+    let (status, _) = register(
+        create_state(Arc::new(InMemoryDeviceRepository::new())),
+        r#"{"zone":"xx"}"#,
+    )
+    .await;
+
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+}
+
+#[tokio::test]
+async fn test_requests_with_device_token() {
+    let repository = Arc::new(InMemoryDeviceRepository::new());
+    let state = create_state(repository);
+
+    let (_, body) = register(state.clone(), "{}").await;
+    let registered: RegisteredDevice = serde_json::from_slice(&body).unwrap();
+
+    let status = |uri: String| {
+        let app = create_app(state.clone());
+        async move {
+            app.oneshot(Request::get(uri).body(Body::empty()).unwrap())
+                .await
+                .unwrap()
+                .status()
+        }
+    };
+    let uri = "/api/v3/zones/fi/schedule?hours=3&start=22&end=6";
+
+    // No pricing in the repository, so a known device gets as far as the handler
+    assert_eq!(
+        status(format!("{uri}&device_token={}", registered.token)).await,
+        StatusCode::SERVICE_UNAVAILABLE
+    );
+    assert_eq!(
+        status(format!("{uri}&device_token=whd_unknown")).await,
+        StatusCode::UNAUTHORIZED
+    );
+}
+
+fn profile(mode: DeviceMode, tariffs: Vec<Tariff>) -> DeviceProfile {
+    DeviceProfile { mode, tariffs }
+}
+
+async fn send(state: AppState, request: Request<Body>) -> (StatusCode, serde_json::Value) {
+    let response = create_app(state).oneshot(request).await.unwrap();
+
+    let status = response.status();
+    let body = to_bytes(response.into_body(), usize::MAX).await.unwrap();
+
+    (status, serde_json::from_slice(&body).unwrap_or_default())
+}
+
+#[tokio::test]
+async fn test_profile_is_validated() {
+    let (status, body) = register(
+        create_state(Arc::new(InMemoryDeviceRepository::new())),
+        r#"{"profile":{"mode":"cheapest_period","hours":10,"start":22,"end":7,"tariffs":[{"start":22,"end":25,"price":1.5}]}}"#,
+    )
+    .await;
+    let body: serde_json::Value = serde_json::from_slice(&body).unwrap();
+
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    let fields: Vec<&str> = body["errors"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|error| error["field"].as_str().unwrap())
+        .collect();
+    assert_eq!(fields, ["zone", "profile.hours", "profile.tariffs[0].end"]);
+}
+
+#[tokio::test]
+async fn test_short_url_evaluates_stored_profile() {
+    let state = create_state(Arc::new(InMemoryDeviceRepository::new()));
+
+    let (_, body) = register(
+        state.clone(),
+        r#"{"zone":"fi","profile":{"mode":"always_on"}}"#,
+    )
+    .await;
+    let device: RegisteredDevice = serde_json::from_slice(&body).unwrap();
+    let uri = format!("/d/{}", device.id);
+
+    let (status, body) = send(
+        state.clone(),
+        Request::get(&uri).body(Body::empty()).unwrap(),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(body["enabled"], true);
+
+    // Only the device itself can change its profile
+    let update = r#"{"zone":"fi","profile":{"mode":"always_off"}}"#;
+    let put = |token: Option<&str>| {
+        let mut request = Request::put(format!("/api/v3/devices/{}", device.id))
+            .header("content-type", "application/json");
+        if let Some(token) = token {
+            request = request.header("x-device-token", token);
+        }
+        request.body(Body::from(update)).unwrap()
+    };
+
+    let (status, _) = send(state.clone(), put(None)).await;
+    assert_eq!(status, StatusCode::UNAUTHORIZED);
+
+    let (status, body) = send(state.clone(), put(Some(&device.token))).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(body["profile"]["mode"], "always_off");
+
+    let (status, body) = send(
+        state.clone(),
+        Request::get(&uri).body(Body::empty()).unwrap(),
+    )
+    .await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    assert_eq!(body["enabled"], false);
+
+    let (status, body) = send(
+        state,
+        Request::get("/d/unknown").body(Body::empty()).unwrap(),
+    )
+    .await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+    assert_eq!(body["message"], "Unknown device");
+}
+
+#[tokio::test]
+async fn test_tariffs_move_the_cheapest_hours() {
+    let now = Helsinki.with_ymd_and_hms(2024, 4, 8, 21, 30, 0).unwrap();
+    let midnight = Helsinki.with_ymd_and_hms(2024, 4, 9, 0, 0, 0).unwrap();
+    let first = Helsinki.with_ymd_and_hms(2024, 4, 8, 21, 0, 0).unwrap();
+
+    let pricing = PriceSeries::from_slots(
+        Duration::hours(1),
+        (0..10).map(|hour| {
+            let price = match hour {
+                0 => dec!(2),
+                1 | 2 => dec!(5),
+                _ => dec!(3),
+            };
+            (
+                (first + Duration::hours(hour)).fixed_offset(),
+                CentsPerKwh::eur(price),
+            )
+        }),
+    );
+    let repository = InMemoryPricingRepository::new();
+    repository
+        .store_pricing(&BiddingZone::FI, storage_date_at(now.to_utc()), &pricing)
+        .await
+        .unwrap();
+
+    let tariffs = vec![
+        Tariff {
+            start: 7,
+            end: 22,
+            price: dec!(10),
+        },
+        Tariff {
+            start: 22,
+            end: 7,
+            price: dec!(0),
+        },
+    ];
+    let plan = |profile: DeviceProfile| {
+        let repository = &repository;
+        async move {
+            plan_device(
+                repository,
+                &DecisionTables::new(SystemTimeProvider),
+                &PricingFallback::new(FallbackPolicy::Off),
+                BiddingZone::FI,
+                &profile,
+                now.to_utc(),
+            )
+            .await
+            .unwrap()
+        }
+    };
+    let cheapest_hour = DeviceMode::CheapestPeriod {
+        hours: 1,
+        start: 21,
+        end: 7,
+    };
+
+    let without_tariffs = plan(profile(cheapest_hour.clone(), Vec::new())).await;
+    assert!(without_tariffs.enabled);
+
+    let with_tariffs = plan(profile(cheapest_hour, tariffs.clone())).await;
+    assert!(!with_tariffs.enabled);
+    assert_eq!(
+        with_tariffs.period.map(|(start, _)| start),
+        Some(midnight.fixed_offset())
+    );
+    assert_eq!(with_tariffs.next_change, Some(midnight.fixed_offset()));
+
+    let below_price = plan(profile(
+        DeviceMode::BelowPrice { max_price: dec!(4) },
+        tariffs,
+    ))
+    .await;
+    assert!(!below_price.enabled);
+    assert_eq!(below_price.next_change, Some(midnight.fixed_offset()));
+}
@@ -0,0 +1,211 @@
+#![cfg(test)]
+
+use async_trait::async_trait;
+use chrono::{Duration, NaiveDate, TimeZone};
+use rust_decimal_macros::dec;
+use wh_core::{
+    price_series::PriceSeries,
+    repository::{
+        memory::InMemoryPricingRepository, DeliveryDay, PricingRepository, RepositoryError,
+    },
+    types::BiddingZone,
+    units::CentsPerKwh,
+};
+
+use crate::v2::fallback::{
+    last_known_pricing, parse_window, typical_day_pricing, FallbackPolicy, PricingFallback,
+};
+
+/// Fails every operation, like a database that can not be reached
+struct FailingRepository;
+
+fn unavailable() -> RepositoryError {
+    RepositoryError::Data("unavailable".to_string())
+}
+
+#[async_trait]
+impl PricingRepository for FailingRepository {
+    async fn get_pricing(
+        &self,
+        _zone: &BiddingZone,
+        _date: NaiveDate,
+    ) -> Result<Option<PriceSeries<CentsPerKwh>>, RepositoryError> {
+        Err(unavailable())
+    }
+
+    async fn store_pricing(
+        &self,
+        _zone: &BiddingZone,
+        _date: NaiveDate,
+        _pricing: &PriceSeries<CentsPerKwh>,
+    ) -> Result<(), RepositoryError> {
+        Err(unavailable())
+    }
+
+    async fn get_delivery_days(
+        &self,
+        _zone: &BiddingZone,
+        _from: NaiveDate,
+        _to: NaiveDate,
+    ) -> Result<Vec<DeliveryDay>, RepositoryError> {
+        Err(unavailable())
+    }
+
+    async fn store_delivery_day(
+        &self,
+        _zone: &BiddingZone,
+        _day: &DeliveryDay,
+    ) -> Result<(), RepositoryError> {
+        Err(unavailable())
+    }
+}
+
+fn date(day: u32) -> NaiveDate {
+    NaiveDate::from_ymd_opt(2024, 4, day).unwrap()
+}
+
+/// Hourly pricing for the whole storage date in Finnish time, with the given price at
+/// hour 3 and 1 c/kWh otherwise
+fn day_pricing(day: u32, price_at_three: i64) -> PriceSeries<CentsPerKwh> {
+    let tz = BiddingZone::FI.to_tz();
+    let midnight = tz.with_ymd_and_hms(2024, 4, day, 0, 0, 0).unwrap();
+
+    PriceSeries::from_slots(
+        Duration::hours(1),
+        (0..24).map(|hour| {
+            let price = if hour == 3 { price_at_three } else { 1 };
+            (
+                (midnight + Duration::hours(hour)).fixed_offset(),
+                CentsPerKwh::eur(price.into()),
+            )
+        }),
+    )
+}
+
+async fn store_day(repository: &InMemoryPricingRepository, day: u32, price_at_three: i64) {
+    let pricing = day_pricing(day, price_at_three);
+
+    repository
+        .store_pricing(&BiddingZone::FI, date(day), &pricing)
+        .await
+        .unwrap();
+    repository
+        .archive_pricing(&BiddingZone::FI, &pricing)
+        .await
+        .unwrap();
+}
+
+#[tokio::test]
+async fn test_last_known_pricing_moves_latest_day_forward() {
+    let repository = InMemoryPricingRepository::new();
+    store_day(&repository, 5, 9).await;
+    store_day(&repository, 6, 7).await;
+
+    let pricing = last_known_pricing(&repository, &BiddingZone::FI, date(8), 7)
+        .await
+        .unwrap()
+        .unwrap();
+
+    let three = BiddingZone::FI
+        .to_tz()
+        .with_ymd_and_hms(2024, 4, 8, 3, 0, 0)
+        .unwrap()
+        .fixed_offset();
+
+    assert_eq!(pricing.len(), 24);
+    assert_eq!(pricing.get(&three), Some(&CentsPerKwh::eur(dec!(7))));
+}
+
+#[tokio::test]
+async fn test_last_known_pricing_respects_max_age() {
+    let repository = InMemoryPricingRepository::new();
+    store_day(&repository, 1, 9).await;
+
+    let pricing = last_known_pricing(&repository, &BiddingZone::FI, date(8), 3)
+        .await
+        .unwrap();
+
+    assert!(pricing.is_none());
+}
+
+#[tokio::test]
+async fn test_typical_day_pricing_follows_weekly_profile() {
+    let repository = InMemoryPricingRepository::new();
+    // 2024-04-01 is a Monday, like the date the fallback is needed for
+    store_day(&repository, 1, 20).await;
+    store_day(&repository, 5, 4).await;
+    store_day(&repository, 6, 9).await;
+
+    let pricing = typical_day_pricing(&repository, &BiddingZone::FI, date(8), 2)
+        .await
+        .unwrap()
+        .unwrap();
+
+    let tz = BiddingZone::FI.to_tz();
+    let at = |day, hour| {
+        tz.with_ymd_and_hms(2024, 4, day, hour, 0, 0)
+            .unwrap()
+            .fixed_offset()
+    };
+
+    // Today and tomorrow are covered
+    assert_eq!(pricing.len(), 48);
+    assert_eq!(pricing.get(&at(8, 3)), Some(&CentsPerKwh::eur(dec!(20))));
+    // No history for Tuesdays, the hour is averaged over all days
+    assert_eq!(pricing.get(&at(9, 3)), Some(&CentsPerKwh::eur(dec!(11))));
+    assert_eq!(pricing.get(&at(9, 4)), Some(&CentsPerKwh::eur(dec!(1))));
+}
+
+#[tokio::test]
+async fn test_typical_day_pricing_without_history() {
+    let repository = InMemoryPricingRepository::new();
+
+    let pricing = typical_day_pricing(&repository, &BiddingZone::FI, date(8), 2)
+        .await
+        .unwrap();
+
+    assert!(pricing.is_none());
+}
+
+#[tokio::test]
+async fn test_fallback_uses_last_loaded_pricing_when_repository_fails() {
+    let tz = BiddingZone::FI.to_tz();
+    let now = tz.with_ymd_and_hms(2024, 4, 8, 0, 30, 0).unwrap();
+    let three = tz
+        .with_ymd_and_hms(2024, 4, 8, 3, 0, 0)
+        .unwrap()
+        .fixed_offset();
+
+    for policy in [
+        FallbackPolicy::LastKnownDay { max_age_days: 7 },
+        FallbackPolicy::TypicalDay { weeks: 4 },
+    ] {
+        let fallback = PricingFallback::new(policy);
+
+        assert!(fallback
+            .decision_table(&FailingRepository, &BiddingZone::FI, date(8))
+            .await
+            .is_err());
+
+        fallback.remember_loaded(&BiddingZone::FI, date(7), &day_pricing(7, 0));
+
+        let table = fallback
+            .decision_table(&FailingRepository, &BiddingZone::FI, date(8))
+            .await
+            .unwrap();
+
+        assert_eq!(
+            table.cheapest_start_time(&now, 1, 0, 23),
+            Some(three),
+            "{policy:?}"
+        );
+    }
+}
+
+#[test]
+fn test_parse_window() {
+    assert_eq!(parse_window("22-06"), Ok((22, 6)));
+    assert_eq!(parse_window("0 - 5"), Ok((0, 5)));
+    assert!(parse_window("22").is_err());
+    assert!(parse_window("22-24").is_err());
+}
@@ -1 +1,90 @@
+#![cfg(test)]
+
+use std::sync::Arc;
+use std::time::Duration;
+
+use wh_core::device::memory::InMemoryDeviceRepository;
+use wh_core::device::DeviceRepository;
+use wh_core::repository::{memory::InMemoryPricingRepository, PricingRepository};
+use wh_core::time_provider::SystemTimeProvider;
+
+use crate::auth::{Auth, AuthPolicy};
+use crate::client_ip::TrustedProxies;
+use crate::devices::DeviceRegistry;
+use crate::rate_limit::memory::InMemoryRateLimiter;
+use crate::rate_limit::policy::PolicyCache;
+use crate::rate_limit::{Algorithm, RateLimiter};
+use crate::secrets::{ApiKeyCache, StaticApiKeySource};
+use crate::v2::decision_table::DecisionTables;
+use crate::v2::fallback::{FallbackPolicy, PricingFallback};
+use crate::AppState;
+
+mod admin_tests;
+mod auth_tests;
+mod cache_tests;
+mod client_ip_tests;
+mod decision_table_tests;
+mod device_tests;
+mod fallback_tests;
+mod openapi_tests;
+mod prices_tests;
+mod rate_limit_tests;
+mod scheduler_tests;
 mod service_tests;
+mod v3_tests;
+
+/// Builds the [`AppState`] of a test app, everything kept in memory and without
+/// any keys by default. Tests override the fields they need:
+///
+/// ```ignore
+/// TestState { api_keys: vec!["secret"], ..Default::default() }.build()
+/// ```
+pub(crate) struct TestState {
+    pub rate_limiter: Arc<dyn RateLimiter>,
+    pub pricing_repository: Arc<dyn PricingRepository>,
+    pub fallback_policy: FallbackPolicy,
+    pub api_keys: Vec<&'static str>,
+    pub admin_keys: Vec<&'static str>,
+    pub auth_policy: AuthPolicy,
+    pub devices: Arc<dyn DeviceRepository>,
+}
+
+impl Default for TestState {
+    fn default() -> Self {
+        TestState {
+            rate_limiter: Arc::new(InMemoryRateLimiter::new(Algorithm::TokenBucket)),
+            pricing_repository: Arc::new(InMemoryPricingRepository::new()),
+            fallback_policy: FallbackPolicy::Off,
+            api_keys: Vec::new(),
+            admin_keys: Vec::new(),
+            auth_policy: AuthPolicy::default(),
+            devices: Arc::new(InMemoryDeviceRepository::new()),
+        }
+    }
+}
+
+impl TestState {
+    pub fn build(self) -> AppState {
+        AppState {
+            rate_limiter: self.rate_limiter,
+            rate_limit_policy: Arc::new(PolicyCache::default()),
+            trusted_proxies: Arc::new(TrustedProxies::default()),
+            pricing_repository: self.pricing_repository,
+            decision_tables: Arc::new(DecisionTables::new(SystemTimeProvider)),
+            pricing_fallback: Arc::new(PricingFallback::new(self.fallback_policy)),
+            auth: Arc::new(Auth {
+                api_keys: key_cache(self.api_keys),
+                admin_keys: key_cache(self.admin_keys),
+                policy: self.auth_policy,
+            }),
+            devices: Arc::new(DeviceRegistry::new(self.devices)),
+        }
+    }
+}
+
+fn key_cache(keys: Vec<&'static str>) -> ApiKeyCache {
+    ApiKeyCache::new(
+        StaticApiKeySource::new(keys.into_iter().map(String::from).collect()),
+        Duration::from_secs(300),
+    )
+}
@@ -0,0 +1,123 @@
+#![cfg(test)]
+
+use std::collections::BTreeSet;
+
+use axum::body::{to_bytes, Body};
+use axum::http::{Request, StatusCode};
+use lambda_http::tower::ServiceExt;
+use utoipa::OpenApi;
+
+use crate::admin::router::routes as admin_routes;
+use crate::auth::ADMIN_KEY_HEADER;
+use crate::device_url::router::routes as device_url_routes;
+use crate::tests::TestState;
+use crate::v2::router::routes as v2_routes;
+use crate::v3::router::routes as v3_routes;
+use crate::{create_app, ApiDoc, AppState};
+
+const NOT_FOUND_BODY: &[u8] = br#"{"status":"Not Found"}"#;
+
+fn create_state() -> AppState {
+    TestState {
+        admin_keys: vec!["admin-secret"],
+        ..Default::default()
+    }
+    .build()
+}
+
+fn documented_paths() -> BTreeSet<String> {
+    ApiDoc::openapi().paths.paths.into_keys().collect()
+}
+
+/// `/prices/:zone` of the router as `/api/v2/prices/{zone}` of the document
+fn openapi_path(prefix: &str, route: &str) -> String {
+    let segments: Vec<String> = route
+        .split('/')
+        .map(|segment| match segment.strip_prefix(':') {
+            Some(param) => format!("{{{param}}}"),
+            None => segment.to_string(),
+        })
+        .collect();
+
+    format!("{prefix}{}", segments.join("/"))
+}
+
+#[test]
+fn test_every_route_is_documented() {
+    let documented = documented_paths();
+
+    let routes = v2_routes()
+        .into_iter()
+        .map(|(route, _)| openapi_path("/api/v2", route))
+        .chain(
+            v3_routes()
+                .into_iter()
+                .map(|(route, _)| openapi_path("/api/v3", route)),
+        )
+        .chain(
+            device_url_routes()
+                .into_iter()
+                .map(|(route, _)| openapi_path("/d", route)),
+        )
+        .chain(
+            admin_routes()
+                .into_iter()
+                .map(|(route, _)| openapi_path("/api/admin", route)),
+        );
+
+    for path in routes {
+        assert!(
+            documented.contains(&path),
+            "{path} is routed but missing from the OpenAPI document"
+        );
+    }
+}
+
+#[tokio::test]
+async fn test_every_documented_path_is_routed() {
+    for path in documented_paths() {
+        let uri = path
+            .replace("{country_code}", "fi")
+            .replace("{zone}", "fi")
+            .replace("{device_id}", "unknown")
+            .replace("{name}", "unknown")
+            .replace("{client}", "unknown");
+        // Without the key the admin API answers 401 whether the path is routed or not
+        let request = Request::get(&uri)
+            .header(ADMIN_KEY_HEADER, "admin-secret")
+            .body(Body::empty())
+            .unwrap();
+        let response = create_app(create_state()).oneshot(request).await.unwrap();
+
+        // Routes of missing resources answer 404 too, but not with the fallback body
+        let status = response.status();
+        let body = to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        assert!(
+            status != StatusCode::NOT_FOUND || &body[..] != NOT_FOUND_BODY,
+            "{path} is documented but not routed"
+        );
+    }
+}
+
+#[tokio::test]
+async fn test_unknown_path_returns_documented_error() {
+    let response = create_app(create_state())
+        .oneshot(Request::get("/api/v2/unknown").body(Body::empty()).unwrap())
+        .await
+        .unwrap();
+
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+    let body = to_bytes(response.into_body(), usize::MAX).await.unwrap();
+    assert_eq!(&body[..], NOT_FOUND_BODY);
+}
+
+#[test]
+fn test_client_covers_every_documented_route() {
+    let client: BTreeSet<String> = wh_client::ROUTES.iter().map(|r| r.to_string()).collect();
+
+    assert_eq!(
+        documented_paths(),
+        client,
+        "wh-client routes are out of sync with the OpenAPI document"
+    );
+}
@@ -0,0 +1,84 @@
+#![cfg(test)]
+
+use std::sync::Arc;
+
+use axum::body::{to_bytes, Body};
+use axum::http::{Request, StatusCode};
+use chrono::{Duration, TimeZone};
+use lambda_http::tower::ServiceExt;
+use rust_decimal_macros::dec;
+use wh_core::{
+    price_series::PriceSeries,
+    repository::{memory::InMemoryPricingRepository, PricingRepository},
+    types::BiddingZone,
+    units::CentsPerKwh,
+};
+
+use crate::tests::TestState;
+use crate::{create_app, AppState};
+
+async fn create_state() -> AppState {
+    let repository = InMemoryPricingRepository::new();
+    let tz = BiddingZone::FI.to_tz();
+    let first = tz.with_ymd_and_hms(2024, 4, 8, 0, 0, 0).unwrap();
+
+    // Three full delivery days from 2024-04-08
+    let pricing = PriceSeries::from_slots(
+        Duration::hours(1),
+        (0..72).map(|hour| {
+            (
+                (first + Duration::hours(hour)).fixed_offset(),
+                CentsPerKwh::eur(dec!(1.5)),
+            )
+        }),
+    );
+    repository
+        .archive_pricing(&BiddingZone::FI, &pricing)
+        .await
+        .unwrap();
+
+    TestState {
+        pricing_repository: Arc::new(repository),
+        ..Default::default()
+    }
+    .build()
+}
+
+async fn get(uri: &str) -> (StatusCode, serde_json::Value) {
+    let response = create_app(create_state().await)
+        .oneshot(Request::get(uri).body(Body::empty()).unwrap())
+        .await
+        .unwrap();
+
+    let status = response.status();
+    let body = to_bytes(response.into_body(), usize::MAX).await.unwrap();
+
+    (status, serde_json::from_slice(&body).unwrap())
+}
+
+#[tokio::test]
+async fn test_get_prices_is_paginated() {
+    let (status, body) = get("/api/v2/prices/fi?from=2024-04-07&to=2024-04-10&limit=2").await;
+
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(body["unit"], "c/kWh");
+    assert_eq!(body["days"].as_array().unwrap().len(), 1);
+    assert_eq!(body["days"][0]["date"], "2024-04-08");
+    assert_eq!(body["days"][0]["prices"].as_array().unwrap().len(), 24);
+    assert_eq!(body["days"][0]["prices"][0]["price"], 1.5);
+    assert_eq!(body["next"], "2024-04-09");
+
+    let (_, body) = get("/api/v2/prices/fi?from=2024-04-09&to=2024-04-10&limit=2").await;
+
+    assert_eq!(body["days"].as_array().unwrap().len(), 2);
+    assert!(body["next"].is_null());
+}
+
+#[tokio::test]
+async fn test_get_prices_rejects_invalid_range() {
+    let (status, _) = get("/api/v2/prices/fi?from=2024-04-10&to=2024-04-08").await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+
+    let (status, _) = get("/api/v2/prices/fi?from=2024-04-08&to=2024-04-10&limit=32").await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+}
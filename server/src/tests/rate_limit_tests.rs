@@ -0,0 +1,486 @@
+#![cfg(test)]
+
+use std::net::IpAddr;
+use std::sync::atomic::{AtomicBool, AtomicU32, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+
+use async_trait::async_trait;
+use wh_core::api::{ClientRateLimit, ClientUsage};
+
+use crate::auth::{AnonymousAccess, Caller};
+use crate::common::error::ApplicationError;
+use crate::devices::ClientId;
+use crate::rate_limit::fallback::{BreakerConfig, FallbackRateLimiter};
+use crate::rate_limit::memory::InMemoryRateLimiter;
+use crate::rate_limit::policy::{FilePolicySource, PolicyCache, RateLimitPolicy};
+use crate::rate_limit::{
+    check_credentials_lookup, Algorithm, Quota, RateLimitDecision, RateLimiter, DEFAULT_QUOTA,
+};
+
+// Two requests at once, one more every second
+const QUOTA: Quota = Quota {
+    capacity: 2,
+    refill_per_minute: 60.0,
+};
+
+async fn check(limiter: &InMemoryRateLimiter, client: &ClientId, now: f64) -> bool {
+    limiter
+        .check(client, None, QUOTA, now)
+        .await
+        .unwrap()
+        .allowed
+}
+
+#[tokio::test]
+async fn test_token_bucket_refills_tokens() {
+    let limiter = InMemoryRateLimiter::new(Algorithm::TokenBucket);
+    let client_ip = ClientId::Ip(IpAddr::from([192, 168, 1, 10]));
+    let other_ip = ClientId::Ip(IpAddr::from([192, 168, 1, 11]));
+
+    assert!(check(&limiter, &client_ip, 0.0).await);
+    assert!(check(&limiter, &client_ip, 0.0).await);
+    assert!(!check(&limiter, &client_ip, 0.5).await);
+    assert!(check(&limiter, &other_ip, 0.5).await);
+
+    // One token is refilled every second
+    assert!(check(&limiter, &client_ip, 1.5).await);
+    assert!(!check(&limiter, &client_ip, 1.5).await);
+}
+
+#[tokio::test]
+async fn test_sliding_window_counts_part_of_previous_window() {
+    let limiter = InMemoryRateLimiter::new(Algorithm::SlidingWindow);
+    let client_ip = ClientId::Ip(IpAddr::from([192, 168, 1, 10]));
+
+    // The window of two requests at one per second is two seconds long
+    assert!(check(&limiter, &client_ip, 0.0).await);
+    assert!(check(&limiter, &client_ip, 1.0).await);
+    assert!(!check(&limiter, &client_ip, 1.9).await);
+
+    // Half way into the next window half of the previous requests still count
+    assert!(check(&limiter, &client_ip, 3.0).await);
+    assert!(!check(&limiter, &client_ip, 3.0).await);
+
+    // Two windows later nothing is left of them
+    assert!(check(&limiter, &client_ip, 6.0).await);
+    assert!(check(&limiter, &client_ip, 6.0).await);
+}
+
+#[tokio::test]
+async fn test_devices_behind_the_same_ip_have_their_own_buckets() {
+    let limiter = InMemoryRateLimiter::new(Algorithm::TokenBucket);
+    let boiler = ClientId::Device("boiler".to_string());
+    let sauna = ClientId::Device("sauna".to_string());
+
+    assert!(check(&limiter, &boiler, 0.0).await);
+    assert!(check(&limiter, &boiler, 0.0).await);
+    assert!(!check(&limiter, &boiler, 0.0).await);
+    assert!(check(&limiter, &sauna, 0.0).await);
+}
+
+#[tokio::test]
+async fn test_token_bucket_decision_tells_remaining_and_wait_times() {
+    let limiter = InMemoryRateLimiter::new(Algorithm::TokenBucket);
+    let client = ClientId::Device("boiler".to_string());
+
+    let decision = limiter.check(&client, None, QUOTA, 0.0).await.unwrap();
+    assert_eq!(
+        decision,
+        RateLimitDecision {
+            allowed: true,
+            limit: 2,
+            remaining: 1.0,
+            reset: 1.0,
+            retry_after: 0.0,
+        }
+    );
+
+    limiter.check(&client, None, QUOTA, 0.0).await.unwrap();
+    let decision = limiter.check(&client, None, QUOTA, 0.5).await.unwrap();
+    assert!(!decision.allowed);
+    assert_eq!(decision.remaining, 0.5);
+    assert_eq!(decision.reset, 1.5);
+    assert_eq!(decision.retry_after, 0.5);
+}
+
+#[tokio::test]
+async fn test_sliding_window_decision_waits_for_window_to_slide() {
+    let limiter = InMemoryRateLimiter::new(Algorithm::SlidingWindow);
+    let client = ClientId::Device("boiler".to_string());
+
+    assert!(check(&limiter, &client, 0.0).await);
+    assert!(check(&limiter, &client, 0.0).await);
+
+    // Both requests count until the end of the next window, and one of them is left
+    // out once the next window is half way
+    let decision = limiter.check(&client, None, QUOTA, 1.0).await.unwrap();
+    assert!(!decision.allowed);
+    assert_eq!(decision.limit, 2);
+    assert_eq!(decision.remaining, 0.0);
+    assert_eq!(decision.reset, 3.0);
+    assert_eq!(decision.retry_after, 2.0);
+
+    assert!(check(&limiter, &client, 3.0).await);
+}
+
+#[tokio::test]
+async fn test_decision_uses_override_capacity() {
+    let limiter = InMemoryRateLimiter::new(Algorithm::TokenBucket);
+    let client = ClientId::Device("boiler".to_string());
+    limiter
+        .set_override(&client.to_string(), quota(60, 10))
+        .await
+        .unwrap();
+
+    let decision = limiter.check(&client, None, QUOTA, 0.0).await.unwrap();
+    assert_eq!(decision.limit, 10);
+    assert_eq!(decision.remaining, 9.0);
+}
+
+const POLICY: &str = r#"{
+    "anonymous": { "requests_per_minute": 10 },
+    "tiers": {
+        "free": { "requests_per_minute": 60, "burst": 120 },
+        "partner": "unlimited"
+    },
+    "api_keys": { "sauna": "partner" },
+    "default_tier": "free",
+    "routes": [
+        { "path": "/api/v3", "tiers": { "partner": { "requests_per_minute": 600 } } },
+        { "path": "/api/v3/schedule", "anonymous": { "requests_per_minute": 5 } }
+    ]
+}"#;
+
+fn quota(requests_per_minute: u32, burst: u32) -> Quota {
+    Quota {
+        capacity: burst,
+        refill_per_minute: requests_per_minute as f64,
+    }
+}
+
+#[test]
+fn test_policy_resolves_quota_by_tier_and_route() {
+    let policy = RateLimitPolicy::parse(POLICY).unwrap();
+    let anonymous = Caller::Anonymous(AnonymousAccess::Limit);
+    let sauna = Caller::ApiKey("sauna".to_string());
+    let boiler = Caller::ApiKey("boiler".to_string());
+
+    assert_eq!(
+        policy.quota("/api/v2/waterheater", &anonymous),
+        Some((None, quota(10, 10)))
+    );
+    assert_eq!(
+        policy.quota("/api/v3/schedule", &anonymous),
+        Some((Some("/api/v3/schedule"), quota(5, 5)))
+    );
+    // Routes match whole path segments
+    assert_eq!(
+        policy.quota("/api/v3/schedules", &anonymous),
+        Some((None, quota(10, 10)))
+    );
+    // Keys without a tier of their own get the default tier
+    assert_eq!(
+        policy.quota("/api/v3/schedule", &boiler),
+        Some((None, quota(60, 120)))
+    );
+    assert_eq!(policy.quota("/api/v2/waterheater", &sauna), None);
+    // The longest matching route without a limit of the tier falls back to the global one
+    assert_eq!(policy.quota("/api/v3/schedule", &sauna), None);
+    assert_eq!(
+        policy.quota("/api/v3/devices", &sauna),
+        Some((Some("/api/v3"), quota(600, 600)))
+    );
+
+    assert_eq!(policy.quota("/api/v2/waterheater", &Caller::Admin), None);
+    assert_eq!(
+        policy.quota(
+            "/api/v2/waterheater",
+            &Caller::Anonymous(AnonymousAccess::Allow)
+        ),
+        None
+    );
+}
+
+#[test]
+fn test_default_policy_limits_only_anonymous_callers() {
+    let policy = RateLimitPolicy::default();
+
+    assert_eq!(
+        policy.quota(
+            "/api/v3/schedule",
+            &Caller::Anonymous(AnonymousAccess::Limit)
+        ),
+        Some((None, DEFAULT_QUOTA))
+    );
+    assert_eq!(
+        policy.quota("/api/v3/schedule", &Caller::ApiKey("sauna".to_string())),
+        None
+    );
+}
+
+#[test]
+fn test_policy_with_unknown_tier_is_rejected() {
+    assert!(RateLimitPolicy::parse(r#"{ "default_tier": "gold" }"#).is_err());
+    assert!(RateLimitPolicy::parse(r#"{ "api_keys": { "sauna": "gold" } }"#).is_err());
+    assert!(RateLimitPolicy::parse(
+        r#"{ "routes": [{ "path": "/d", "tiers": { "gold": "unlimited" } }] }"#
+    )
+    .is_err());
+    assert!(RateLimitPolicy::parse(r#"{ "anonymous": "sometimes" }"#).is_err());
+}
+
+#[tokio::test]
+async fn test_credentials_lookups_are_counted_apart_by_ip() {
+    let limiter = InMemoryRateLimiter::new(Algorithm::TokenBucket);
+    let policy =
+        RateLimitPolicy::parse(r#"{ "anonymous": { "requests_per_minute": 2 } }"#).unwrap();
+    let ip: IpAddr = "203.0.113.7".parse().unwrap();
+
+    let lookup = |policy| check_credentials_lookup(&limiter, policy, ip, 0.0);
+    assert!(lookup(&policy).await.unwrap().allowed);
+    assert!(lookup(&policy).await.unwrap().allowed);
+    assert!(!lookup(&policy).await.unwrap().allowed);
+
+    // The other requests of the address have their own quota
+    assert!(check(&limiter, &ClientId::Ip(ip), 0.0).await);
+
+    let unlimited = RateLimitPolicy::parse(r#"{ "anonymous": "unlimited" }"#).unwrap();
+    assert!(lookup(&unlimited).await.is_none());
+}
+
+#[tokio::test]
+async fn test_override_does_not_lift_credentials_lookup_limit() {
+    let limiter = InMemoryRateLimiter::new(Algorithm::TokenBucket);
+    let policy =
+        RateLimitPolicy::parse(r#"{ "anonymous": { "requests_per_minute": 2 } }"#).unwrap();
+    let ip: IpAddr = "203.0.113.7".parse().unwrap();
+    let raised = Quota {
+        capacity: 1000,
+        refill_per_minute: 1000.0,
+    };
+    limiter.set_override(&ip.to_string(), raised).await.unwrap();
+
+    // Invalid keys from the address are still answered with 429 after two lookups
+    let lookup = || check_credentials_lookup(&limiter, &policy, ip, 0.0);
+    assert!(lookup().await.unwrap().allowed);
+    assert!(lookup().await.unwrap().allowed);
+    assert!(!lookup().await.unwrap().allowed);
+
+    // While the other requests of the address get the override
+    let decision = limiter
+        .check(&ClientId::Ip(ip), None, QUOTA, 0.0)
+        .await
+        .unwrap();
+    assert_eq!(decision.limit, 1000);
+}
+
+#[test]
+fn test_policy_with_zero_rate_is_rejected() {
+    assert!(RateLimitPolicy::parse(r#"{ "anonymous": { "requests_per_minute": 0 } }"#).is_err());
+    assert!(RateLimitPolicy::parse(
+        r#"{ "tiers": { "free": { "requests_per_minute": 60, "burst": 0 } } }"#
+    )
+    .is_err());
+    assert!(RateLimitPolicy::parse(
+        r#"{ "routes": [{ "path": "/api-docs", "anonymous": { "requests_per_minute": 0 } }] }"#
+    )
+    .is_err());
+    assert!(RateLimitPolicy::parse(r#"{ "anonymous": { "requests_per_minute": 1 } }"#).is_ok());
+}
+
+#[tokio::test]
+async fn test_routes_are_counted_apart() {
+    let limiter = InMemoryRateLimiter::new(Algorithm::TokenBucket);
+    let client = ClientId::ApiKey("sauna".to_string());
+
+    assert!(check(&limiter, &client, 0.0).await);
+    assert!(check(&limiter, &client, 0.0).await);
+    assert!(!check(&limiter, &client, 0.0).await);
+    assert!(
+        limiter
+            .check(&client, Some("/api/v3/schedule"), QUOTA, 0.0)
+            .await
+            .unwrap()
+            .allowed
+    );
+}
+
+#[tokio::test]
+async fn test_policy_cache_reloads_file_and_keeps_policy_on_failure() {
+    let path = std::env::temp_dir().join(format!("rate_limit_policy_{}.json", std::process::id()));
+    let anonymous = Caller::Anonymous(AnonymousAccess::Limit);
+
+    std::fs::write(&path, POLICY).unwrap();
+    let cache = PolicyCache::new(
+        FilePolicySource::new(path.to_str().unwrap()),
+        Duration::ZERO,
+    );
+    assert_eq!(
+        cache.current().await.quota("/d/boiler", &anonymous),
+        Some((None, quota(10, 10)))
+    );
+
+    std::fs::write(&path, r#"{ "anonymous": "unlimited" }"#).unwrap();
+    assert_eq!(cache.current().await.quota("/d/boiler", &anonymous), None);
+
+    std::fs::write(&path, r#"{ "default_tier": "gold" }"#).unwrap();
+    assert_eq!(cache.current().await.quota("/d/boiler", &anonymous), None);
+
+    std::fs::remove_file(&path).unwrap();
+}
+
+/// Store that fails, or hangs, while it's unavailable
+struct FlakyStore {
+    limiter: InMemoryRateLimiter,
+    available: AtomicBool,
+    hangs: bool,
+    checks: AtomicU32,
+}
+
+impl FlakyStore {
+    fn new(hangs: bool) -> Arc<Self> {
+        Arc::new(FlakyStore {
+            limiter: InMemoryRateLimiter::new(Algorithm::TokenBucket),
+            available: AtomicBool::new(true),
+            hangs,
+            checks: AtomicU32::new(0),
+        })
+    }
+
+    fn checks(&self) -> u32 {
+        self.checks.load(Ordering::SeqCst)
+    }
+}
+
+#[async_trait]
+impl RateLimiter for FlakyStore {
+    async fn check(
+        &self,
+        client: &ClientId,
+        route: Option<&str>,
+        quota: Quota,
+        now: f64,
+    ) -> Result<RateLimitDecision, ApplicationError> {
+        self.checks.fetch_add(1, Ordering::SeqCst);
+
+        if !self.available.load(Ordering::SeqCst) {
+            if self.hangs {
+                tokio::time::sleep(Duration::from_secs(5)).await;
+            }
+            return Err(ApplicationError::Service("Connection refused".to_string()));
+        }
+
+        self.limiter.check(client, route, quota, now).await
+    }
+
+    async fn overrides(&self) -> Result<Vec<ClientRateLimit>, ApplicationError> {
+        self.limiter.overrides().await
+    }
+
+    async fn set_override(&self, client: &str, quota: Quota) -> Result<(), ApplicationError> {
+        self.limiter.set_override(client, quota).await
+    }
+
+    async fn delete_override(&self, client: &str) -> Result<bool, ApplicationError> {
+        self.limiter.delete_override(client).await
+    }
+
+    async fn usage(&self) -> Result<Vec<ClientUsage>, ApplicationError> {
+        self.limiter.usage().await
+    }
+}
+
+const BREAKER: BreakerConfig = BreakerConfig {
+    failure_threshold: 2,
+    cooldown_seconds: 10.0,
+    timeout: Duration::from_millis(50),
+};
+
+async fn check_fallback(limiter: &FallbackRateLimiter, client: &ClientId, now: f64) -> bool {
+    limiter
+        .check(client, None, QUOTA, now)
+        .await
+        .unwrap()
+        .allowed
+}
+
+#[tokio::test]
+async fn test_circuit_opens_after_failures_and_limits_in_memory() {
+    let store = FlakyStore::new(false);
+    let limiter = FallbackRateLimiter::new(store.clone(), BREAKER);
+    let client = ClientId::Device("boiler".to_string());
+
+    assert!(check_fallback(&limiter, &client, 0.0).await);
+    assert_eq!(store.checks(), 1);
+
+    // The failed checks are counted in memory, with the whole quota
+    store.available.store(false, Ordering::SeqCst);
+    assert!(check_fallback(&limiter, &client, 1.0).await);
+    assert!(check_fallback(&limiter, &client, 1.0).await);
+    assert_eq!(store.checks(), 3);
+
+    // The circuit is open, the store is left alone and the memory limits the client
+    assert!(!check_fallback(&limiter, &client, 1.0).await);
+    assert_eq!(store.checks(), 3);
+
+    // After the cooldown a single check tries the store again
+    store.available.store(true, Ordering::SeqCst);
+    assert!(check_fallback(&limiter, &client, 12.0).await);
+    assert_eq!(store.checks(), 4);
+    assert!(check_fallback(&limiter, &client, 12.0).await);
+    assert_eq!(store.checks(), 5);
+}
+
+#[tokio::test]
+async fn test_failed_trial_opens_circuit_again() {
+    let store = FlakyStore::new(false);
+    let limiter = FallbackRateLimiter::new(store.clone(), BREAKER);
+    let client = ClientId::Device("boiler".to_string());
+
+    store.available.store(false, Ordering::SeqCst);
+    check_fallback(&limiter, &client, 0.0).await;
+    check_fallback(&limiter, &client, 0.0).await;
+
+    check_fallback(&limiter, &client, 11.0).await;
+    assert_eq!(store.checks(), 3);
+
+    check_fallback(&limiter, &client, 15.0).await;
+    assert_eq!(store.checks(), 3);
+}
+
+#[tokio::test]
+async fn test_slow_store_counts_as_failed() {
+    let store = FlakyStore::new(true);
+    let limiter = FallbackRateLimiter::new(store.clone(), BREAKER);
+    let client = ClientId::Device("boiler".to_string());
+
+    store.available.store(false, Ordering::SeqCst);
+    assert!(check_fallback(&limiter, &client, 0.0).await);
+    assert!(check_fallback(&limiter, &client, 0.0).await);
+    assert!(!check_fallback(&limiter, &client, 0.0).await);
+    assert_eq!(store.checks(), 2);
+}
+
+#[tokio::test]
+async fn test_memory_applies_overrides_of_the_store() {
+    let store = FlakyStore::new(false);
+    let limiter = FallbackRateLimiter::new(store.clone(), BREAKER);
+    let client = ClientId::Device("boiler".to_string());
+
+    // Set through another instance
+    let quota = Quota {
+        capacity: 5,
+        refill_per_minute: 60.0,
+    };
+    store.set_override("device:boiler", quota).await.unwrap();
+    assert!(check_fallback(&limiter, &client, 0.0).await);
+    // Let the copy of the overrides started by the check run
+    tokio::task::yield_now().await;
+
+    store.available.store(false, Ordering::SeqCst);
+    for _ in 0..5 {
+        assert!(check_fallback(&limiter, &client, 1.0).await);
+    }
+    assert!(!check_fallback(&limiter, &client, 1.0).await);
+}
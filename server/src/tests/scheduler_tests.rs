@@ -0,0 +1,64 @@
+#![cfg(test)]
+
+use chrono::{Duration, NaiveTime, TimeZone, Utc};
+use rust_decimal::Decimal;
+use strum::IntoEnumIterator;
+use wh_core::price_series::PriceSeries;
+use wh_core::repository::{memory::InMemoryPricingRepository, PricingRepository};
+use wh_core::types::BiddingZone;
+use wh_core::units::CentsPerKwh;
+use wh_core::util::get_storage_date;
+
+use crate::scheduler::{has_current_pricing, next_fetch_time, DEFAULT_FETCH_TIME};
+
+#[test]
+fn test_next_fetch_time_later_today() {
+    let now = Utc.with_ymd_and_hms(2024, 9, 17, 8, 30, 0).unwrap();
+
+    assert_eq!(
+        next_fetch_time(now, DEFAULT_FETCH_TIME),
+        Utc.with_ymd_and_hms(2024, 9, 17, 13, 0, 0).unwrap()
+    );
+}
+
+#[test]
+fn test_next_fetch_time_tomorrow() {
+    let now = Utc.with_ymd_and_hms(2024, 9, 17, 13, 0, 0).unwrap();
+    let fetch_time = NaiveTime::from_hms_opt(12, 15, 0).unwrap();
+
+    assert_eq!(
+        next_fetch_time(
+            Utc.with_ymd_and_hms(2024, 9, 17, 12, 15, 0).unwrap(),
+            fetch_time
+        ),
+        Utc.with_ymd_and_hms(2024, 9, 18, 12, 15, 0).unwrap()
+    );
+    assert_eq!(
+        next_fetch_time(now, DEFAULT_FETCH_TIME),
+        Utc.with_ymd_and_hms(2024, 9, 18, 13, 0, 0).unwrap()
+    );
+}
+
+#[tokio::test]
+async fn test_current_pricing_of_every_zone_is_required() {
+    let repository = InMemoryPricingRepository::new();
+    let date = get_storage_date();
+    let pricing = PriceSeries::from_slots(
+        Duration::hours(1),
+        [(Utc::now().fixed_offset(), CentsPerKwh::eur(Decimal::ONE))],
+    );
+
+    repository
+        .store_pricing(&BiddingZone::FI, date, &pricing)
+        .await
+        .unwrap();
+    assert!(!has_current_pricing(&repository).await);
+
+    for zone in BiddingZone::iter() {
+        repository
+            .store_pricing(&zone, date, &pricing)
+            .await
+            .unwrap();
+    }
+    assert!(has_current_pricing(&repository).await);
+}
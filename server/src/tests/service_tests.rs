@@ -1,50 +1,44 @@
 #![cfg(test)]
 
-use chrono::{FixedOffset, TimeZone, Timelike, Utc};
-use std::sync::Arc;
-use wh_core::{test_utils::MockTimeProvider, types::BiddingZone};
-
-use crate::{
-    common::db::Pricing,
-    v2::service::{calculate_cheapest_start_time, get_filtered_pricing},
+use chrono::{DateTime, Duration, FixedOffset, TimeZone, Timelike, Utc};
+use rust_decimal::Decimal;
+use rust_decimal_macros::dec;
+use wh_core::{
+    price_series::PriceSeries, test_utils::MockTimeProvider, types::BiddingZone, units::CentsPerKwh,
 };
 
+use wh_core::schedule::{calculate_cheapest_start_time, get_filtered_pricing};
+
+type Pricing = (DateTime<FixedOffset>, CentsPerKwh);
+
+fn create_series(pricing_data: Vec<Pricing>) -> PriceSeries<CentsPerKwh> {
+    PriceSeries::from_slots(Duration::hours(1), pricing_data)
+}
+
 fn create_static_pricing_with_hour(hour: u32) -> Pricing {
     let offset = FixedOffset::east_opt(0).unwrap();
     let date_time = offset.with_ymd_and_hms(2024, 4, 8, hour, 0, 0).unwrap();
 
-    Pricing {
-        date_time,
-        price_no_tax: 0.5,
-    }
+    (date_time, CentsPerKwh::eur(dec!(0.5)))
 }
 
 fn create_static_pricing_with_hour_and_day(hour: u32, day: u32) -> Pricing {
     let offset = FixedOffset::east_opt(0).unwrap();
     let date_time = offset.with_ymd_and_hms(2024, 4, day, hour, 0, 0).unwrap();
 
-    Pricing {
-        date_time,
-        price_no_tax: 0.5,
-    }
+    (date_time, CentsPerKwh::eur(dec!(0.5)))
 }
 
-fn create_pricing_with_hour(hour: u32, price: f32) -> Pricing {
+fn create_pricing_with_hour(hour: u32, price: Decimal) -> Pricing {
     let offset = FixedOffset::east_opt(0).unwrap();
     let date_time = offset.with_ymd_and_hms(2024, 4, 8, hour, 0, 0).unwrap();
-    Pricing {
-        date_time,
-        price_no_tax: price,
-    }
+    (date_time, CentsPerKwh::eur(price))
 }
 
-fn create_pricing_with_hour_and_day(hour: u32, day: u32, price: f32) -> Pricing {
+fn create_pricing_with_hour_and_day(hour: u32, day: u32, price: Decimal) -> Pricing {
     let offset = FixedOffset::east_opt(0).unwrap();
     let date_time = offset.with_ymd_and_hms(2024, 4, day, hour, 0, 0).unwrap();
-    Pricing {
-        date_time,
-        price_no_tax: price,
-    }
+    (date_time, CentsPerKwh::eur(price))
 }
 
 #[test]
@@ -54,7 +48,7 @@ fn test_get_filtered_pricing_normal_period() {
         create_static_pricing_with_hour(15),
         create_static_pricing_with_hour(20),
     ];
-    let pricing = Arc::from(pricing_data.into_boxed_slice());
+    let pricing = create_series(pricing_data);
     let starting_hour = 12;
     let ending_hour = 18;
 
@@ -69,7 +63,7 @@ fn test_get_filtered_pricing_normal_period() {
         ending_hour,
     );
     assert_eq!(filtered_pricing.len(), 1);
-    assert_eq!(filtered_pricing[0].date_time.hour(), 15);
+    assert_eq!(filtered_pricing.slots()[0].start.hour(), 15);
 }
 
 #[test]
@@ -88,7 +82,7 @@ fn test_get_filtered_pricing_cross_midnight() {
         create_static_pricing_with_hour_and_day(8, 9),
         create_static_pricing_with_hour_and_day(9, 9),
     ];
-    let pricing = Arc::from(pricing_data.into_boxed_slice());
+    let pricing = create_series(pricing_data);
     let starting_hour = 22;
     let ending_hour = 7;
 
@@ -103,8 +97,8 @@ fn test_get_filtered_pricing_cross_midnight() {
         ending_hour,
     );
     assert_eq!(filtered_pricing.len(), 9);
-    assert_eq!(filtered_pricing[0].date_time.hour(), 22);
-    assert_eq!(filtered_pricing.last().unwrap().date_time.hour(), 6);
+    assert_eq!(filtered_pricing.slots()[0].start.hour(), 22);
+    assert_eq!(filtered_pricing.slots().last().unwrap().start.hour(), 6);
 }
 
 #[test]
@@ -125,7 +119,7 @@ fn test_get_filtered_pricing_excludes_hours_from_next_day_evening() {
         create_static_pricing_with_hour_and_day(4, 10),
         create_static_pricing_with_hour_and_day(5, 10),
     ];
-    let pricing = Arc::from(pricing_data.into_boxed_slice());
+    let pricing = create_series(pricing_data);
     let starting_hour = 22;
     let ending_hour = 7;
 
@@ -141,30 +135,30 @@ fn test_get_filtered_pricing_excludes_hours_from_next_day_evening() {
     );
 
     assert_eq!(filtered_pricing.len(), 3);
-    assert_eq!(filtered_pricing[0].date_time.hour(), 22);
+    assert_eq!(filtered_pricing.slots()[0].start.hour(), 22);
     assert_eq!(
-        filtered_pricing[0].date_time.date_naive().to_string(),
+        filtered_pricing.slots()[0].start.date_naive().to_string(),
         "2024-04-08"
     );
-    assert_eq!(filtered_pricing.last().unwrap().date_time.hour(), 0);
+    assert_eq!(filtered_pricing.slots().last().unwrap().start.hour(), 0);
 }
 
 #[test]
 fn test_calculate_cheapest_start_time() {
     let pricing_data = [
-        create_pricing_with_hour(22, 0.1),
-        create_pricing_with_hour(23, 0.2),
-        create_pricing_with_hour(0, 0.3),
-        create_pricing_with_hour(1, 0.5),
-        create_pricing_with_hour(2, 0.1),
-        create_pricing_with_hour(3, 0.1),
-        create_pricing_with_hour(4, 0.1),
-        create_pricing_with_hour(5, 0.1),
+        create_pricing_with_hour(22, dec!(0.1)),
+        create_pricing_with_hour(23, dec!(0.2)),
+        create_pricing_with_hour(0, dec!(0.3)),
+        create_pricing_with_hour(1, dec!(0.5)),
+        create_pricing_with_hour(2, dec!(0.1)),
+        create_pricing_with_hour(3, dec!(0.1)),
+        create_pricing_with_hour(4, dec!(0.1)),
+        create_pricing_with_hour(5, dec!(0.1)),
     ];
 
-    let pricing_refs: Vec<&Pricing> = pricing_data.iter().collect();
+    let pricing = create_series(pricing_data.to_vec());
 
-    let start_time = calculate_cheapest_start_time(pricing_refs, 6);
+    let start_time = calculate_cheapest_start_time(&pricing, 6);
 
     assert_eq!(
         start_time,
@@ -180,19 +174,19 @@ fn test_calculate_cheapest_start_time() {
 #[test]
 fn test_calculate_cheapest_start_time_before_midnight() {
     let pricing_data = [
-        create_pricing_with_hour_and_day(22, 9, 4.722),
-        create_pricing_with_hour_and_day(23, 9, 4.078),
-        create_pricing_with_hour_and_day(0, 10, 0.619),
-        create_pricing_with_hour_and_day(1, 10, 0.869),
-        create_pricing_with_hour_and_day(2, 10, 0.508),
-        create_pricing_with_hour_and_day(3, 10, 0.107),
-        create_pricing_with_hour_and_day(4, 10, 0.000),
-        create_pricing_with_hour_and_day(5, 10, -0.001),
-        create_pricing_with_hour_and_day(6, 10, 0.000),
+        create_pricing_with_hour_and_day(22, 9, dec!(4.722)),
+        create_pricing_with_hour_and_day(23, 9, dec!(4.078)),
+        create_pricing_with_hour_and_day(0, 10, dec!(0.619)),
+        create_pricing_with_hour_and_day(1, 10, dec!(0.869)),
+        create_pricing_with_hour_and_day(2, 10, dec!(0.508)),
+        create_pricing_with_hour_and_day(3, 10, dec!(0.107)),
+        create_pricing_with_hour_and_day(4, 10, dec!(0.000)),
+        create_pricing_with_hour_and_day(5, 10, dec!(-0.001)),
+        create_pricing_with_hour_and_day(6, 10, dec!(0.000)),
     ];
-    let pricing_refs: Vec<&Pricing> = pricing_data.iter().collect();
+    let pricing = create_series(pricing_data.to_vec());
 
-    let start_time = calculate_cheapest_start_time(pricing_refs, 6);
+    let start_time = calculate_cheapest_start_time(&pricing, 6);
 
     assert_eq!(
         start_time,
@@ -204,3 +198,28 @@ fn test_calculate_cheapest_start_time_before_midnight() {
         )
     );
 }
+
+#[test]
+fn test_calculate_cheapest_start_time_skips_missing_hours() {
+    let pricing_data = vec![
+        create_pricing_with_hour(0, dec!(0.4)),
+        create_pricing_with_hour(1, dec!(0.4)),
+        create_pricing_with_hour(2, dec!(0.1)),
+        // 03:00 is missing, 02:00 and 04:00 are not a contiguous period
+        create_pricing_with_hour(4, dec!(0.1)),
+        create_pricing_with_hour(5, dec!(0.4)),
+    ];
+    let pricing = create_series(pricing_data);
+
+    let start_time = calculate_cheapest_start_time(&pricing, 2);
+
+    assert_eq!(
+        start_time,
+        Some(
+            FixedOffset::east_opt(0)
+                .unwrap()
+                .with_ymd_and_hms(2024, 4, 8, 1, 0, 0)
+                .unwrap()
+        )
+    );
+}
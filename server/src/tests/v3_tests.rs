@@ -0,0 +1,186 @@
+#![cfg(test)]
+
+use axum::body::{to_bytes, Body};
+use axum::http::{Request, StatusCode};
+use chrono::{DateTime, Duration, DurationRound, TimeZone, Timelike, Utc};
+use chrono_tz::{Europe::Helsinki, Tz};
+use lambda_http::tower::ServiceExt;
+
+use crate::tests::TestState;
+use crate::v2::fallback::FallbackPolicy;
+use crate::v2::service::next_change;
+use crate::{create_app, AppState};
+
+fn create_state(policy: FallbackPolicy) -> AppState {
+    TestState {
+        fallback_policy: policy,
+        ..Default::default()
+    }
+    .build()
+}
+
+async fn get(state: AppState, uri: &str) -> (StatusCode, serde_json::Value) {
+    let response = create_app(state)
+        .oneshot(Request::get(uri).body(Body::empty()).unwrap())
+        .await
+        .unwrap();
+
+    let status = response.status();
+    let body = to_bytes(response.into_body(), usize::MAX).await.unwrap();
+
+    (status, serde_json::from_slice(&body).unwrap())
+}
+
+#[tokio::test]
+async fn test_schedule_without_pricing_is_unavailable() {
+    let (status, body) = get(
+        create_state(FallbackPolicy::Off),
+        "/api/v3/zones/fi/schedule?hours=3&start=22&end=6",
+    )
+    .await;
+
+    assert_eq!(status, StatusCode::SERVICE_UNAVAILABLE);
+    assert_eq!(body["status"], "Service Unavailable");
+}
+
+#[tokio::test]
+async fn test_schedule_rejects_invalid_input_as_json() {
+    let (status, body) = get(
+        create_state(FallbackPolicy::Off),
+        "/api/v3/zones/fi/schedule?hours=3&start=22",
+    )
+    .await;
+
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    assert_eq!(body["errors"][0]["field"], "end");
+    assert_eq!(body["errors"][0]["code"], "missing");
+
+    let (status, body) = get(
+        create_state(FallbackPolicy::Off),
+        "/api/v3/zones/fi/schedule?hours=x&start=25&end=25",
+    )
+    .await;
+
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    assert_eq!(body["errors"].as_array().unwrap().len(), 1);
+    assert_eq!(body["errors"][0]["field"], "hours");
+    assert_eq!(body["errors"][0]["code"], "invalid");
+
+    let (status, body) = get(
+        create_state(FallbackPolicy::Off),
+        "/api/v3/zones/fi/schedule?hours=3&start=25&end=25",
+    )
+    .await;
+
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    let fields: Vec<&str> = body["errors"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|error| error["field"].as_str().unwrap())
+        .collect();
+    assert_eq!(fields, ["start", "end"]);
+
+    let (status, body) = get(
+        create_state(FallbackPolicy::Off),
+        "/api/v3/zones/fi/schedule?hours=10&start=22&end=7",
+    )
+    .await;
+
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    assert_eq!(body["errors"][0]["field"], "hours");
+    assert_eq!(body["errors"][0]["code"], "out_of_range");
+
+    let (status, _) = get(
+        create_state(FallbackPolicy::Off),
+        "/api/v3/zones/xx/schedule?hours=3&start=22&end=6",
+    )
+    .await;
+
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+}
+
+#[tokio::test]
+async fn test_schedule_with_fixed_window_fallback() {
+    let now = Utc::now().with_timezone(&Helsinki);
+    let start = (now.hour() + 1) % 24;
+    let end = (now.hour() + 3) % 24;
+
+    let (status, body) = get(
+        create_state(FallbackPolicy::FixedWindow { start, end }),
+        &format!("/api/v3/zones/fi/schedule?hours=1&start={start}&end={end}"),
+    )
+    .await;
+
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(body["zone"], "fi");
+    assert_eq!(body["enabled"], false);
+    assert_eq!(body["fallback"], "fixed-window");
+    assert!(body["period"].is_null());
+
+    let next_change: DateTime<Tz> = body["next_change"]
+        .as_str()
+        .unwrap()
+        .parse::<DateTime<Utc>>()
+        .unwrap()
+        .with_timezone(&Helsinki);
+    assert_eq!(next_change.hour(), start);
+    assert_eq!(next_change.minute(), 0);
+}
+
+#[test]
+fn test_next_change_is_the_first_differing_slot() {
+    let now = Helsinki.with_ymd_and_hms(2024, 4, 8, 21, 40, 0).unwrap();
+    let on_from = Helsinki.with_ymd_and_hms(2024, 4, 8, 23, 0, 0).unwrap();
+
+    let change = next_change(now, (now + Duration::days(1)).to_utc(), |time| {
+        *time >= on_from
+    });
+    assert_eq!(change, Some(on_from));
+
+    // Nothing changes before the horizon
+    let change = next_change(now, on_from.to_utc(), |time| *time >= on_from);
+    assert_eq!(change, None);
+
+    let start = now.duration_trunc(Duration::minutes(15)).unwrap();
+    let change = next_change(now, (now + Duration::hours(1)).to_utc(), |time| {
+        *time < start + Duration::minutes(15)
+    });
+    assert_eq!(change, Some(start + Duration::minutes(15)));
+}
+
+#[tokio::test]
+async fn test_v2_still_answers_with_status_only() {
+    for query in [
+        "hours=3&start=22&end=6",
+        "hours=0&start=22&end=6",
+        "hours=3&start=25&end=6",
+    ] {
+        let response = create_app(create_state(FallbackPolicy::Off))
+            .oneshot(
+                Request::get(format!(
+                    "/api/v2/waterheater/country/fi/cheapest-period?{query}"
+                ))
+                .body(Body::empty())
+                .unwrap(),
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+        let body = to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        assert!(body.is_empty());
+    }
+}
+
+#[tokio::test]
+async fn test_schedule_accepts_midnight_as_24() {
+    let (status, body) = get(
+        create_state(FallbackPolicy::FixedWindow { start: 0, end: 0 }),
+        "/api/v3/zones/fi/schedule?hours=24&start=0&end=24",
+    )
+    .await;
+
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(body["fallback"], "fixed-window");
+}
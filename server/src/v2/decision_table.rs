@@ -0,0 +1,114 @@
+use std::collections::HashMap;
+use std::future::Future;
+use std::sync::{Arc, RwLock};
+
+use chrono::{DateTime, FixedOffset, NaiveDate, Timelike};
+use chrono_tz::Tz;
+
+use wh_core::price_series::PriceSeries;
+use wh_core::schedule::find_cheapest_start_time;
+use wh_core::time_provider::TimeProvider;
+use wh_core::types::BiddingZone;
+use wh_core::units::CentsPerKwh;
+
+use crate::common::cache::ExpiringCache;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+struct DecisionKey {
+    current_day: NaiveDate,
+    hours: u32,
+    starting_hour: u32,
+    ending_hour: u32,
+    /// Periods crossing midnight include the early hours of the current day only
+    /// before the period has started
+    before_start: bool,
+}
+
+/// Cheapest start times of the `hours/start/end` combinations requested on the pricing,
+/// each computed on its first request and remembered, so that answering the same request
+/// again is a single lookup.
+#[derive(Debug)]
+pub struct DecisionTable {
+    pricing: PriceSeries<CentsPerKwh>,
+    cheapest_starts: RwLock<HashMap<DecisionKey, Option<DateTime<FixedOffset>>>>,
+}
+
+impl DecisionTable {
+    pub fn new(pricing: PriceSeries<CentsPerKwh>) -> Self {
+        DecisionTable {
+            pricing,
+            cheapest_starts: RwLock::new(HashMap::new()),
+        }
+    }
+
+    pub fn cheapest_start_time(
+        &self,
+        current_time: &DateTime<Tz>,
+        hours: u32,
+        starting_hour: u32,
+        ending_hour: u32,
+    ) -> Option<DateTime<FixedOffset>> {
+        let before_start = starting_hour >= ending_hour && current_time.hour() < starting_hour;
+        let key = DecisionKey {
+            current_day: current_time.date_naive(),
+            hours,
+            starting_hour,
+            ending_hour,
+            before_start,
+        };
+
+        if let Some(cheapest_start) = self.cheapest_starts.read().unwrap().get(&key) {
+            return *cheapest_start;
+        }
+
+        // Before the start of the period the hour only matters for periods crossing
+        // midnight, so every hour until the start shares the entry of midnight
+        let current_hour = if before_start { 0 } else { starting_hour };
+        let cheapest_start = find_cheapest_start_time(
+            &self.pricing,
+            key.current_day,
+            current_hour,
+            hours,
+            starting_hour,
+            ending_hour,
+        );
+
+        self.cheapest_starts
+            .write()
+            .unwrap()
+            .insert(key, cheapest_start);
+
+        cheapest_start
+    }
+}
+
+/// Decision tables of each zone and storage date, created on the first request and kept
+/// until the pricing changes.
+pub struct DecisionTables<T: TimeProvider = wh_core::time_provider::SystemTimeProvider> {
+    cache: ExpiringCache<(BiddingZone, NaiveDate), Arc<DecisionTable>, T>,
+}
+
+impl<T: TimeProvider> DecisionTables<T> {
+    pub fn new(time_provider: T) -> Self {
+        DecisionTables {
+            cache: ExpiringCache::new(time_provider),
+        }
+    }
+
+    pub async fn get_or_build<E, F, Fut>(
+        &self,
+        zone: &BiddingZone,
+        date: NaiveDate,
+        load_pricing: F,
+    ) -> Result<Arc<DecisionTable>, E>
+    where
+        F: FnOnce() -> Fut,
+        Fut: Future<Output = Result<PriceSeries<CentsPerKwh>, E>>,
+    {
+        self.cache
+            .get_or_try_load((*zone, date), || async {
+                Ok(Arc::new(DecisionTable::new(load_pricing().await?)))
+            })
+            .await
+    }
+}
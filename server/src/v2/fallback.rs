@@ -0,0 +1,280 @@
+use std::collections::HashMap;
+use std::env;
+use std::fmt;
+use std::sync::{Arc, RwLock};
+
+use chrono::{Duration, NaiveDate};
+use lambda_http::Error;
+use tracing::error;
+
+use wh_core::price_series::PriceSeries;
+use wh_core::profile::PriceProfile;
+use wh_core::repository::PricingRepository;
+use wh_core::time_provider::SystemTimeProvider;
+use wh_core::types::BiddingZone;
+use wh_core::units::CentsPerKwh;
+
+use crate::common::error::ApplicationError;
+
+use super::decision_table::{DecisionTable, DecisionTables};
+
+/// Response header telling which fallback the decision was made with, only present
+/// when the published pricing was not available.
+pub const FALLBACK_HEADER: &str = "x-pricing-fallback";
+
+const DEFAULT_MAX_AGE_DAYS: u32 = 7;
+const DEFAULT_HISTORY_WEEKS: u32 = 4;
+const DEFAULT_FALLBACK_WINDOW: (u32, u32) = (22, 6);
+
+/// What to do when the pricing for the current storage date can not be loaded, set
+/// with `PRICING_FALLBACK`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FallbackPolicy {
+    /// Keep the heater off
+    Off,
+    /// Reuse the most recent pricing stored at most `max_age_days` before
+    LastKnownDay { max_age_days: u32 },
+    /// Use the average price of each hour of the week over the last `weeks` of archived
+    /// pricing
+    TypicalDay { weeks: u32 },
+    /// Keep the heater on between `start` and `end` regardless of the request
+    FixedWindow { start: u32, end: u32 },
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Fallback {
+    LastKnownDay,
+    TypicalDay,
+    FixedWindow,
+}
+
+impl Fallback {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Fallback::LastKnownDay => "last-known-day",
+            Fallback::TypicalDay => "typical-day",
+            Fallback::FixedWindow => "fixed-window",
+        }
+    }
+}
+
+impl fmt::Display for Fallback {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.as_str())
+    }
+}
+
+impl FallbackPolicy {
+    pub fn from_env() -> Result<Self, Error> {
+        let policy = env::var("PRICING_FALLBACK").unwrap_or("off".into());
+
+        let number = |name: &str, default: u32| -> Result<u32, Error> {
+            match env::var(name) {
+                Ok(number) => Ok(number.parse()?),
+                Err(_) => Ok(default),
+            }
+        };
+
+        match policy.as_str() {
+            "off" => Ok(FallbackPolicy::Off),
+            "last-known-day" => Ok(FallbackPolicy::LastKnownDay {
+                max_age_days: number("PRICING_FALLBACK_MAX_AGE_DAYS", DEFAULT_MAX_AGE_DAYS)?,
+            }),
+            "typical-day" => Ok(FallbackPolicy::TypicalDay {
+                weeks: number("PRICING_FALLBACK_HISTORY_WEEKS", DEFAULT_HISTORY_WEEKS)?,
+            }),
+            "fixed-window" => {
+                let (start, end) = match env::var("PRICING_FALLBACK_WINDOW") {
+                    Ok(window) => parse_window(&window)?,
+                    Err(_) => DEFAULT_FALLBACK_WINDOW,
+                };
+                Ok(FallbackPolicy::FixedWindow { start, end })
+            }
+            other => Err(format!("Unsupported PRICING_FALLBACK: {other}").into()),
+        }
+    }
+
+    pub fn fallback(&self) -> Option<Fallback> {
+        match self {
+            FallbackPolicy::Off => None,
+            FallbackPolicy::LastKnownDay { .. } => Some(Fallback::LastKnownDay),
+            FallbackPolicy::TypicalDay { .. } => Some(Fallback::TypicalDay),
+            FallbackPolicy::FixedWindow { .. } => Some(Fallback::FixedWindow),
+        }
+    }
+}
+
+/// Parses a window of hours such as `22-06`, which may cross midnight.
+pub fn parse_window(window: &str) -> Result<(u32, u32), String> {
+    let invalid = || format!("Invalid window, expected e.g. 22-06: {window}");
+
+    let (start, end) = window.split_once('-').ok_or_else(invalid)?;
+    let start: u32 = start.trim().parse().map_err(|_| invalid())?;
+    let end: u32 = end.trim().parse().map_err(|_| invalid())?;
+
+    if start > 23 || end > 23 {
+        return Err(invalid());
+    }
+
+    Ok((start, end))
+}
+
+/// Pricing of the most recent stored date before `date`, moved forward to `date`.
+pub async fn last_known_pricing(
+    pricing_repository: &dyn PricingRepository,
+    zone: &BiddingZone,
+    date: NaiveDate,
+    max_age_days: u32,
+) -> Result<Option<PriceSeries<CentsPerKwh>>, ApplicationError> {
+    for age in 1..=i64::from(max_age_days) {
+        let Some(pricing) = pricing_repository
+            .get_pricing(zone, date - Duration::days(age))
+            .await?
+        else {
+            continue;
+        };
+
+        return Ok(Some(shift_days(&pricing, age)));
+    }
+
+    Ok(None)
+}
+
+fn shift_days(pricing: &PriceSeries<CentsPerKwh>, days: i64) -> PriceSeries<CentsPerKwh> {
+    let shift = Duration::days(days);
+
+    PriceSeries::from_slots(
+        pricing.resolution(),
+        pricing.iter().map(|slot| (slot.start + shift, slot.price)),
+    )
+}
+
+/// Hourly pricing for `date` and the day after it following the typical price of each
+/// hour of the week over the delivery days archived in the last `weeks`.
+pub async fn typical_day_pricing(
+    pricing_repository: &dyn PricingRepository,
+    zone: &BiddingZone,
+    date: NaiveDate,
+    weeks: u32,
+) -> Result<Option<PriceSeries<CentsPerKwh>>, ApplicationError> {
+    let history = pricing_repository
+        .get_delivery_days(
+            zone,
+            date - Duration::weeks(i64::from(weeks)),
+            date - Duration::days(1),
+        )
+        .await?;
+
+    let tz = zone.to_tz();
+    let profile = PriceProfile::from_history(&tz, history.iter().map(|day| &day.pricing));
+
+    if profile.is_empty() {
+        return Ok(None);
+    }
+
+    Ok(Some(
+        profile.to_series(&tz, [date, date + Duration::days(1)]),
+    ))
+}
+
+/// Decision tables built from fallback pricing, kept separately from the tables of
+/// the published pricing so that the published pricing is used as soon as it's stored.
+///
+/// The pricing last loaded for each zone is kept as well and used before the repository,
+/// which is likely failing too when the pricing can not be loaded.
+pub struct PricingFallback {
+    pub policy: FallbackPolicy,
+    tables: DecisionTables,
+    last_loaded: RwLock<HashMap<BiddingZone, (NaiveDate, PriceSeries<CentsPerKwh>)>>,
+}
+
+impl PricingFallback {
+    pub fn new(policy: FallbackPolicy) -> Self {
+        PricingFallback {
+            policy,
+            tables: DecisionTables::new(SystemTimeProvider),
+            last_loaded: RwLock::new(HashMap::new()),
+        }
+    }
+
+    /// Remembers the published pricing loaded for the storage `date`, unless pricing of a
+    /// later date is already known
+    pub fn remember_loaded(
+        &self,
+        zone: &BiddingZone,
+        date: NaiveDate,
+        pricing: &PriceSeries<CentsPerKwh>,
+    ) {
+        let mut last_loaded = self.last_loaded.write().unwrap();
+
+        if last_loaded.get(zone).is_none_or(|(last, _)| *last <= date) {
+            last_loaded.insert(*zone, (date, pricing.clone()));
+        }
+    }
+
+    /// Pricing last loaded for the zone at most `max_age_days` before `date`, moved
+    /// forward to `date`
+    fn last_loaded(
+        &self,
+        zone: &BiddingZone,
+        date: NaiveDate,
+        max_age_days: u32,
+    ) -> Option<PriceSeries<CentsPerKwh>> {
+        let last_loaded = self.last_loaded.read().unwrap();
+        let (loaded_date, pricing) = last_loaded.get(zone)?;
+
+        let age = (date - *loaded_date).num_days();
+        (0..=i64::from(max_age_days))
+            .contains(&age)
+            .then(|| shift_days(pricing, age))
+    }
+
+    pub async fn decision_table(
+        &self,
+        pricing_repository: &dyn PricingRepository,
+        zone: &BiddingZone,
+        date: NaiveDate,
+    ) -> Result<Arc<DecisionTable>, ApplicationError> {
+        self.tables
+            .get_or_build(zone, date, || async {
+                let pricing = match self.policy {
+                    FallbackPolicy::LastKnownDay { max_age_days } => {
+                        match self.last_loaded(zone, date, max_age_days) {
+                            Some(pricing) => Some(pricing),
+                            None => {
+                                last_known_pricing(pricing_repository, zone, date, max_age_days)
+                                    .await?
+                            }
+                        }
+                    }
+                    FallbackPolicy::TypicalDay { weeks } => {
+                        let typical_day =
+                            typical_day_pricing(pricing_repository, zone, date, weeks)
+                                .await
+                                .inspect_err(|e| {
+                                    error!("Error retrieving pricing history: {:?}", e)
+                                })
+                                .ok()
+                                .flatten();
+
+                        // Without the history, a profile of the last loaded pricing alone
+                        // is still closer to the typical day than nothing
+                        typical_day.or_else(|| {
+                            let pricing = self.last_loaded(zone, date, weeks * 7)?;
+                            let tz = zone.to_tz();
+                            Some(
+                                PriceProfile::from_history(&tz, [&pricing])
+                                    .to_series(&tz, [date, date + Duration::days(1)]),
+                            )
+                        })
+                    }
+                    FallbackPolicy::Off | FallbackPolicy::FixedWindow { .. } => None,
+                };
+
+                pricing.ok_or(ApplicationError::Service(
+                    "No pricing to fall back to".to_string(),
+                ))
+            })
+            .await
+    }
+}
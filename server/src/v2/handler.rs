@@ -2,16 +2,19 @@ use std::net::SocketAddr;
 
 use axum::{
     extract::{ConnectInfo, Path, Query, State},
-    http::StatusCode,
-    response::IntoResponse,
+    http::{HeaderValue, StatusCode},
+    response::{IntoResponse, Response},
 };
 use serde::Deserialize;
 
 use tracing::info;
+use wh_core::schedule::HeatingPeriod;
 use wh_core::types::BiddingZone;
 
+use crate::http::{TooManyRequests, Unauthorized};
 use crate::AppState;
 
+use super::fallback::FALLBACK_HEADER;
 use super::service::is_water_heater_enabled_for_current_hour;
 
 #[derive(Deserialize)]
@@ -30,14 +33,21 @@ pub struct QueryParams {
     get,
     path = "/api/v2/waterheater/country/{country_code}/cheapest-period",
     responses(
-        (status = 200, description = "Current hour is withing the cheapest period of electricity price"),
-        (status = 400, description = "Current hour is not in the cheapest period of electricity price"),
+        (status = 200, description = "Current hour is withing the cheapest period of electricity price",
+            headers(("x-pricing-fallback" = String, description = "Fallback used when the published pricing was not available"))),
+        (status = 400, description = "Current hour is not in the cheapest period of electricity price. \
+            Invalid query parameters also return 400, the reason as text/plain if they could not be parsed. \
+            Use the v3 API for descriptive errors.",
+            headers(("x-pricing-fallback" = String, description = "Fallback used when the published pricing was not available"))),
+        (status = 401, response = Unauthorized),
+        (status = 429, response = TooManyRequests),
     ),
     params(
         ("country_code" = BiddingZone, Path, description = "Country code"),
         ("hours" = u32, Query, description = "Number of hours in the period"),
         ("start" = u32, Query, description = "First hour of the period in 24h format"),
-        ("end" = u32, Query, description = "The hour when the period ends in 24h format")
+        ("end" = u32, Query, description = "The hour when the period ends in 24h format, 24 is midnight. \
+            The period is the whole day when the same as start.")
     ),
 )]
 pub async fn handle_enable_water_heater(
@@ -45,26 +55,46 @@ pub async fn handle_enable_water_heater(
     ConnectInfo(addr): ConnectInfo<SocketAddr>,
     Path(country_code): Path<BiddingZone>,
     Query(params): Query<QueryParams>,
-) -> impl IntoResponse {
-    let is_enabled = is_water_heater_enabled_for_current_hour(
-        app_state.dynamo_client,
+) -> Response {
+    // Invalid periods are "off" like before, the Shelly scripts only look at the status
+    let period = match HeatingPeriod::new(params.hours, params.start, params.end) {
+        Ok(period) => period,
+        Err(errors) => {
+            info!(?errors, "Invalid period requested");
+            return StatusCode::BAD_REQUEST.into_response();
+        }
+    };
+
+    let decision = is_water_heater_enabled_for_current_hour(
+        app_state.pricing_repository.as_ref(),
+        &app_state.decision_tables,
+        &app_state.pricing_fallback,
         country_code,
-        params.hours,
-        params.start,
-        params.end,
+        period.hours,
+        period.starting_hour,
+        period.ending_hour,
     )
     .await;
 
-    if is_enabled {
+    let status = if decision.enabled {
         info!(
             "Waterheater enabled at {} ({} hours starting at {})",
             addr.ip(),
             params.hours,
             params.start
         );
-        return StatusCode::OK;
+        StatusCode::OK
+    } else {
+        info!("Waterheater should not be enabled");
+        StatusCode::BAD_REQUEST
+    };
+
+    let mut response = status.into_response();
+    if let Some(fallback) = decision.fallback {
+        response
+            .headers_mut()
+            .insert(FALLBACK_HEADER, HeaderValue::from_static(fallback.as_str()));
     }
 
-    info!("Waterheater should not be enabled");
-    StatusCode::BAD_REQUEST
+    response
 }
@@ -1,3 +1,6 @@
+pub(crate) mod decision_table;
+pub(crate) mod fallback;
 pub(crate) mod handler;
+pub(crate) mod prices;
 pub(crate) mod router;
 pub(crate) mod service;
@@ -0,0 +1,99 @@
+use axum::{
+    extract::{Path, Query, State},
+    http::StatusCode,
+    response::{IntoResponse, Response},
+    Json,
+};
+use chrono::{Duration, NaiveDate};
+use serde::Deserialize;
+use tracing::error;
+use utoipa::IntoParams;
+
+use wh_core::api::{DeliveryDayPrices, PriceRangeResponse};
+use wh_core::types::BiddingZone;
+use wh_core::units::{CentsPerKwh, Currency};
+
+use crate::http::{error_response, TooManyRequests, Unauthorized};
+use crate::AppState;
+
+const DEFAULT_PAGE_DAYS: u32 = 7;
+const MAX_PAGE_DAYS: u32 = 31;
+
+#[derive(Deserialize, IntoParams)]
+pub struct PriceRangeParams {
+    /// First delivery day of the range
+    #[param(value_type = String, format = Date)]
+    from: NaiveDate,
+    /// Last delivery day of the range, inclusive
+    #[param(value_type = String, format = Date)]
+    to: NaiveDate,
+    /// Number of delivery days per page, at most 31
+    limit: Option<u32>,
+}
+
+/// Archived prices of each delivery day in the range, in c/kWh.
+#[utoipa::path(
+    get,
+    path = "/api/v2/prices/{zone}",
+    responses(
+        (status = 200, description = "Prices of the delivery days in the range", body = PriceRangeResponse),
+        (status = 400, description = "Invalid range, or query parameters that could not be parsed", content(
+            ("application/json" = ErrorResponse, example = json!({ "status": "Bad Request", "message": "from must not be after to" })),
+            ("text/plain" = String, example = json!("Failed to deserialize query string: missing field `from`")),
+        )),
+        (status = 401, response = Unauthorized),
+        (status = 429, response = TooManyRequests),
+        (status = 500, description = "Archived prices could not be read", body = ErrorResponse),
+    ),
+    params(
+        ("zone" = BiddingZone, Path, description = "Bidding zone"),
+        PriceRangeParams,
+    ),
+)]
+pub async fn handle_get_prices(
+    State(app_state): State<AppState>,
+    Path(zone): Path<BiddingZone>,
+    Query(params): Query<PriceRangeParams>,
+) -> Response {
+    if params.from > params.to {
+        return error_response(StatusCode::BAD_REQUEST, "from must not be after to");
+    }
+
+    let limit = params.limit.unwrap_or(DEFAULT_PAGE_DAYS);
+    if limit == 0 || limit > MAX_PAGE_DAYS {
+        return error_response(
+            StatusCode::BAD_REQUEST,
+            &format!("limit must be between 1 and {MAX_PAGE_DAYS}"),
+        );
+    }
+
+    let page_end = (params.from + Duration::days(i64::from(limit) - 1)).min(params.to);
+
+    let days = match app_state
+        .pricing_repository
+        .get_delivery_days(&zone, params.from, page_end)
+        .await
+    {
+        Ok(days) => days,
+        Err(e) => {
+            error!(?e, "Error retrieving archived prices");
+            return error_response(StatusCode::INTERNAL_SERVER_ERROR, "Prices not available");
+        }
+    };
+
+    let currency = days
+        .iter()
+        .flat_map(|day| day.pricing.iter())
+        .map(|slot| slot.price.currency())
+        .next()
+        .unwrap_or(Currency::Eur);
+
+    Json(PriceRangeResponse {
+        zone,
+        unit: CentsPerKwh::UNIT.to_string(),
+        currency,
+        days: days.into_iter().map(DeliveryDayPrices::from).collect(),
+        next: (page_end < params.to).then(|| page_end + Duration::days(1)),
+    })
+    .into_response()
+}
@@ -1,16 +1,29 @@
-use axum::routing::get;
+use axum::routing::{get, MethodRouter};
 use axum::Router;
 
 use crate::http::not_found;
 use crate::AppState;
 
 use super::handler::handle_enable_water_heater;
+use super::prices::handle_get_prices;
 
-pub fn v2_routes() -> Router<AppState> {
-    Router::new()
-        .route(
+/// Routes of the v2 API relative to `/api/v2`. Every route has to be documented in the
+/// OpenAPI document, which the tests check from this list.
+pub(crate) fn routes() -> Vec<(&'static str, MethodRouter<AppState>)> {
+    vec![
+        (
             "/waterheater/country/:country_code/cheapest-period",
             get(handle_enable_water_heater),
-        )
+        ),
+        ("/prices/:zone", get(handle_get_prices)),
+    ]
+}
+
+pub fn v2_routes() -> Router<AppState> {
+    routes()
+        .into_iter()
+        .fold(Router::new(), |router, (path, method_router)| {
+            router.route(path, method_router)
+        })
         .fallback(not_found)
 }
@@ -1,152 +1,377 @@
-use chrono::TimeZone;
-use chrono::{DateTime, Duration, FixedOffset, Timelike};
-use chrono_tz::Tz;
-use tracing::{error, info};
-
 use std::sync::Arc;
 
-use wh_core::time_provider::{self, SystemTimeProvider, TimeProvider};
+use chrono::{DateTime, Duration, DurationRound, FixedOffset, NaiveDate, Timelike, Utc};
+use chrono_tz::Tz;
+use tracing::{error, info, warn};
+
+use wh_core::device::{DeviceMode, DeviceProfile};
+use wh_core::price_series::PriceSeries;
+use wh_core::repository::PricingRepository;
+use wh_core::schedule::{find_cheapest_start_time, is_within_operating_hours, HeatingPeriod};
+use wh_core::tariff::apply_tariffs;
 use wh_core::types::BiddingZone;
+use wh_core::units::CentsPerKwh;
+use wh_core::util::{get_storage_date, next_publication_time, storage_date_at};
+
+use crate::common::error::ApplicationError;
+
+use super::decision_table::{DecisionTable, DecisionTables};
+use super::fallback::{Fallback, FallbackPolicy, PricingFallback};
 
-use crate::common::db::{get_electricity_pricing_with_region, Pricing};
+// Decisions can only change at the boundaries of the price slots
+const CHANGE_RESOLUTION_MINUTES: i64 = 15;
 
-pub fn get_filtered_pricing<'a, T: TimeProvider>(
-    time_provider: &T,
+async fn get_pricing(
+    pricing_repository: &dyn PricingRepository,
     country_code: &BiddingZone,
-    pricing: &'a Arc<[Pricing]>,
-    starting_hour: u32,
-    ending_hour: u32,
-) -> Vec<&'a Pricing> {
-    let current_day = time_provider
-        .now()
-        .with_timezone(&country_code.to_tz())
-        .date_naive();
-
-    let current_hour = time_provider
-        .now()
-        .with_timezone(&country_code.to_tz())
-        .hour();
-
-    // If starting hour is larger than ending hour, it means we are crossing the night
-    // Which means to accurately filter the pricing info for the period, we need to
-    // also return the pricing info from the starting hour on the previous day, when
-    // clock has for example reached 02:00. Example is starting hour 22 ending hour 07
-    // To correctly calculate the cheapest period we need the pricing information from 22:00
-    // yesterday to 07 today.
-    pricing
-        .iter()
-        .filter(|p| {
-            let pricing_hour = p.date_time.hour();
-            let pricing_date = p.date_time.date_naive();
-
-            if starting_hour < ending_hour {
-                // Normal period, not crossing midnight, the date on the pricing information has to
-                // match current date (we want to filter out yesterdays pricing info as it's not
-                // relevant for the period)
-                pricing_hour >= starting_hour
-                    && pricing_hour < ending_hour
-                    && pricing_date == current_day
-            } else {
-                // Period crosses midnight, starting hour (e.g. 22) is LARGER than ending hour
-                // (e.g. 07). We need to include pricing data for any hours between 22 and 00 if
-                // the date matches to today
-                // OR ((if the pricing data hour is earlier than the periods ending hour and it's
-                // for the current day AND current hour is smaller than the starting hour ) OR (if
-                // the pricing hour is smaller than the ending hour AND the pricing date is ahead one
-                // day compared to current day)
-                (pricing_hour >= starting_hour && pricing_date == current_day)
-                    || ((pricing_hour < ending_hour
-                        && pricing_date == current_day
-                        && current_hour < starting_hour)
-                        || (pricing_hour < ending_hour
-                            && pricing_date == current_day + Duration::days(1)))
-            }
-        })
-        .collect()
+    storage_date: NaiveDate,
+) -> Result<PriceSeries<CentsPerKwh>, ApplicationError> {
+    pricing_repository
+        .get_pricing(country_code, storage_date)
+        .await?
+        .ok_or(ApplicationError::Service("Item not found".to_string()))
 }
 
-pub fn calculate_cheapest_start_time(
-    pricing: Vec<&Pricing>,
-    hours: u32,
-) -> Option<DateTime<FixedOffset>> {
-    let mut cheapest_sequence_start: Option<DateTime<FixedOffset>> = None;
-    let mut min_cost = 50_f32;
-
-    for window in pricing.windows(hours as usize) {
-        let total_cost: f32 = window.iter().map(|p| p.price_no_tax).sum();
-        if total_cost < min_cost {
-            min_cost = total_cost;
-            cheapest_sequence_start = Some(window.first().unwrap().date_time);
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct HeaterDecision {
+    pub enabled: bool,
+    /// Set when the published pricing was not available
+    pub fallback: Option<Fallback>,
+}
+
+impl HeaterDecision {
+    fn off() -> Self {
+        HeaterDecision {
+            enabled: false,
+            fallback: None,
         }
     }
+}
 
-    cheapest_sequence_start
+/// The decision together with the period it was made from and when it changes next
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct HeatingPlan {
+    pub enabled: bool,
+    pub fallback: Option<Fallback>,
+    /// Cheapest period of the current operating hours, not set with the fixed window
+    /// fallback or without enough pricing for the period
+    pub period: Option<(DateTime<FixedOffset>, DateTime<FixedOffset>)>,
+    /// Not set when the decision stays the same as far as it is known
+    pub next_change: Option<DateTime<FixedOffset>>,
 }
 
-fn is_within_operating_hours(
-    starting_hour: u32,
-    ending_hour: u32,
-    current_time: DateTime<Tz>,
-) -> bool {
-    let current_hour = current_time.hour();
+/// What the decisions of a zone are made from
+enum DecisionSource {
+    Table(Arc<DecisionTable>),
+    Window {
+        start: u32,
+        end: u32,
+    },
+    /// Pricing of a single device, e.g. with its tariffs added, evaluated per request
+    /// instead of building a table
+    Pricing(PriceSeries<CentsPerKwh>),
+}
 
-    if starting_hour < ending_hour {
-        return current_hour >= starting_hour && current_hour < ending_hour;
+impl DecisionSource {
+    fn cheapest_start_time(
+        &self,
+        current_time: &DateTime<Tz>,
+        hours: u32,
+        starting_hour: u32,
+        ending_hour: u32,
+    ) -> Option<DateTime<FixedOffset>> {
+        match self {
+            DecisionSource::Table(table) => {
+                table.cheapest_start_time(current_time, hours, starting_hour, ending_hour)
+            }
+            DecisionSource::Window { .. } => None,
+            DecisionSource::Pricing(pricing) => find_cheapest_start_time(
+                pricing,
+                current_time.date_naive(),
+                current_time.hour(),
+                hours,
+                starting_hour,
+                ending_hour,
+            ),
+        }
     }
 
-    current_hour >= starting_hour || current_hour < ending_hour
-}
+    /// How far ahead the decisions are known: the tables change when new prices are
+    /// published, the fixed window never does
+    fn known_until(&self, now: DateTime<Utc>) -> DateTime<Utc> {
+        match self {
+            DecisionSource::Table(_) | DecisionSource::Pricing(_) => next_publication_time(now),
+            DecisionSource::Window { .. } => now + Duration::days(1),
+        }
+    }
 
-pub async fn is_water_heater_enabled_for_current_hour(
-    dynamo_client: aws_sdk_dynamodb::Client,
-    country_code: BiddingZone,
-    hours: u32,
-    starting_hour: u32,
-    ending_hour: u32,
-) -> bool {
-    let pricing = match get_electricity_pricing_with_region(&country_code, dynamo_client).await {
-        Ok(p) => p,
-        Err(e) => {
-            error!("Error retrieving pricing from DynamoDB: {:?}", e);
+    fn is_enabled_at(
+        &self,
+        current_time: &DateTime<Tz>,
+        hours: u32,
+        starting_hour: u32,
+        ending_hour: u32,
+    ) -> bool {
+        if let DecisionSource::Window { start, end } = *self {
+            return is_within_operating_hours(start, end, *current_time);
+        }
+
+        if !is_within_operating_hours(starting_hour, ending_hour, *current_time) {
             return false;
         }
+
+        match self.cheapest_start_time(current_time, hours, starting_hour, ending_hour) {
+            Some(start) => {
+                let end = start + Duration::hours(i64::from(hours));
+                *current_time >= start && *current_time <= end
+            }
+            None => false,
+        }
+    }
+}
+
+/// Decision table of the published pricing, or of the fallback policy when the pricing
+/// is not available
+async fn decision_source(
+    pricing_repository: &dyn PricingRepository,
+    decision_tables: &DecisionTables,
+    pricing_fallback: &PricingFallback,
+    country_code: BiddingZone,
+    storage_date: NaiveDate,
+) -> Result<(DecisionSource, Option<Fallback>), ApplicationError> {
+    let error = match decision_tables
+        .get_or_build(&country_code, storage_date, || async {
+            let pricing = get_pricing(pricing_repository, &country_code, storage_date).await?;
+            pricing_fallback.remember_loaded(&country_code, storage_date, &pricing);
+            Ok(pricing)
+        })
+        .await
+    {
+        Ok(t) => return Ok((DecisionSource::Table(t), None)),
+        Err(e) => e,
     };
 
-    let filtered_pricing = get_filtered_pricing::<SystemTimeProvider>(
-        &time_provider::SystemTimeProvider,
-        &country_code,
-        &pricing,
-        starting_hour,
-        ending_hour,
-    );
+    error!("Error retrieving pricing: {:?}", error);
 
-    if filtered_pricing.is_empty() || filtered_pricing.len() < hours as usize {
-        return false;
+    let Some(fallback) = pricing_fallback.policy.fallback() else {
+        return Err(error);
+    };
+
+    warn!(%fallback, %country_code, "Using fallback for missing pricing");
+
+    if let FallbackPolicy::FixedWindow { start, end } = pricing_fallback.policy {
+        return Ok((DecisionSource::Window { start, end }, Some(fallback)));
     }
 
-    let cheapest_sequence_start = calculate_cheapest_start_time(filtered_pricing, hours);
+    match pricing_fallback
+        .decision_table(pricing_repository, &country_code, storage_date)
+        .await
+    {
+        Ok(t) => Ok((DecisionSource::Table(t), Some(fallback))),
+        Err(e) => {
+            error!(%fallback, "Error retrieving fallback pricing: {:?}", e);
+            Err(e)
+        }
+    }
+}
+
+pub async fn is_water_heater_enabled_for_current_hour(
+    pricing_repository: &dyn PricingRepository,
+    decision_tables: &DecisionTables,
+    pricing_fallback: &PricingFallback,
+    country_code: BiddingZone,
+    hours: u32,
+    starting_hour: u32,
+    ending_hour: u32,
+) -> HeaterDecision {
+    let current_time = Utc::now().with_timezone(&country_code.to_tz());
+
+    let Ok((source, fallback)) = decision_source(
+        pricing_repository,
+        decision_tables,
+        pricing_fallback,
+        country_code,
+        get_storage_date(),
+    )
+    .await
+    else {
+        return HeaterDecision::off();
+    };
 
     info!(
         "Cheapest start time: {:?} for {} hours starting from {} and ending at {}",
-        cheapest_sequence_start, hours, starting_hour, ending_hour
+        source.cheapest_start_time(&current_time, hours, starting_hour, ending_hour),
+        hours,
+        starting_hour,
+        ending_hour
     );
 
-    let current_time = country_code
-        .to_tz()
-        .from_utc_datetime(&chrono::Utc::now().naive_utc());
-
     if !is_within_operating_hours(starting_hour, ending_hour, current_time) {
         info!(
             starting_hour,
             ending_hour, "Current time is not within operation hours"
         );
-        return false;
     }
 
-    if let Some(start) = cheapest_sequence_start {
-        let end = start + Duration::hours(i64::from(hours));
-        return current_time >= start && current_time <= end;
+    HeaterDecision {
+        enabled: source.is_enabled_at(&current_time, hours, starting_hour, ending_hour),
+        fallback,
+    }
+}
+
+/// Like [`is_water_heater_enabled_for_current_hour`], but fails when no pricing is
+/// available instead of turning the heater off
+pub async fn plan_heating(
+    pricing_repository: &dyn PricingRepository,
+    decision_tables: &DecisionTables,
+    pricing_fallback: &PricingFallback,
+    country_code: BiddingZone,
+    (hours, starting_hour, ending_hour): (u32, u32, u32),
+    now: DateTime<Utc>,
+) -> Result<HeatingPlan, ApplicationError> {
+    let current_time = now.with_timezone(&country_code.to_tz());
+
+    let (source, fallback) = decision_source(
+        pricing_repository,
+        decision_tables,
+        pricing_fallback,
+        country_code,
+        storage_date_at(now),
+    )
+    .await?;
+
+    Ok(plan_from_source(
+        &source,
+        fallback,
+        (hours, starting_hour, ending_hour),
+        current_time,
+        now,
+    ))
+}
+
+fn plan_from_source(
+    source: &DecisionSource,
+    fallback: Option<Fallback>,
+    (hours, starting_hour, ending_hour): (u32, u32, u32),
+    current_time: DateTime<Tz>,
+    now: DateTime<Utc>,
+) -> HeatingPlan {
+    let is_enabled_at =
+        |time: &DateTime<Tz>| source.is_enabled_at(time, hours, starting_hour, ending_hour);
+
+    let period = source
+        .cheapest_start_time(&current_time, hours, starting_hour, ending_hour)
+        .map(|start| (start, start + Duration::hours(i64::from(hours))));
+
+    HeatingPlan {
+        enabled: is_enabled_at(&current_time),
+        fallback,
+        period,
+        next_change: next_change(current_time, source.known_until(now), is_enabled_at)
+            .map(|time| time.fixed_offset()),
+    }
+}
+
+/// Decision for the stored profile of a device. Tariffs are added to the published
+/// pricing, so profiles with tariffs don't use the pricing fallback.
+pub async fn plan_device(
+    pricing_repository: &dyn PricingRepository,
+    decision_tables: &DecisionTables,
+    pricing_fallback: &PricingFallback,
+    country_code: BiddingZone,
+    profile: &DeviceProfile,
+    now: DateTime<Utc>,
+) -> Result<HeatingPlan, ApplicationError> {
+    let constant = |enabled| HeatingPlan {
+        enabled,
+        fallback: None,
+        period: None,
+        next_change: None,
+    };
+    let current_time = now.with_timezone(&country_code.to_tz());
+
+    match profile.mode {
+        DeviceMode::AlwaysOn => Ok(constant(true)),
+        DeviceMode::AlwaysOff => Ok(constant(false)),
+        DeviceMode::CheapestPeriod { hours, start, end } => {
+            let period = HeatingPeriod::new(hours, start, end)
+                .map_err(|e| ApplicationError::Service(format!("Invalid profile: {e:?}")))?;
+            let period = (period.hours, period.starting_hour, period.ending_hour);
+
+            if profile.tariffs.is_empty() {
+                return plan_heating(
+                    pricing_repository,
+                    decision_tables,
+                    pricing_fallback,
+                    country_code,
+                    period,
+                    now,
+                )
+                .await;
+            }
+
+            let pricing = device_pricing(pricing_repository, country_code, profile, now).await?;
+
+            Ok(plan_from_source(
+                &DecisionSource::Pricing(pricing),
+                None,
+                period,
+                current_time,
+                now,
+            ))
+        }
+        DeviceMode::BelowPrice { max_price } => {
+            let pricing = device_pricing(pricing_repository, country_code, profile, now).await?;
+
+            let is_enabled_at = |time: &DateTime<Tz>| {
+                pricing
+                    .slot_at(&time.fixed_offset())
+                    .is_some_and(|slot| slot.price.amount() <= max_price)
+            };
+
+            Ok(HeatingPlan {
+                enabled: is_enabled_at(&current_time),
+                fallback: None,
+                period: None,
+                next_change: next_change(current_time, next_publication_time(now), is_enabled_at)
+                    .map(|time| time.fixed_offset()),
+            })
+        }
+    }
+}
+
+async fn device_pricing(
+    pricing_repository: &dyn PricingRepository,
+    country_code: BiddingZone,
+    profile: &DeviceProfile,
+    now: DateTime<Utc>,
+) -> Result<PriceSeries<CentsPerKwh>, ApplicationError> {
+    let pricing = get_pricing(pricing_repository, &country_code, storage_date_at(now)).await?;
+
+    Ok(apply_tariffs(
+        &pricing,
+        &country_code.to_tz(),
+        &profile.tariffs,
+    ))
+}
+
+/// Start of the first slot after `current_time` in which `is_enabled_at` differs from
+/// now, looking no further than `until`. Slots are evaluated at their midpoint.
+pub(crate) fn next_change(
+    current_time: DateTime<Tz>,
+    until: DateTime<Utc>,
+    is_enabled_at: impl Fn(&DateTime<Tz>) -> bool,
+) -> Option<DateTime<Tz>> {
+    let step = Duration::minutes(CHANGE_RESOLUTION_MINUTES);
+    let enabled = is_enabled_at(&current_time);
+
+    let mut slot = current_time.duration_trunc(step).ok()? + step;
+    while slot < until {
+        if is_enabled_at(&(slot + step / 2)) != enabled {
+            return Some(slot);
+        }
+        slot += step;
     }
 
-    false
+    None
 }
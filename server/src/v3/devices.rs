@@ -0,0 +1,161 @@
+use axum::{
+    extract::{
+        rejection::{JsonRejection, PathRejection},
+        Path, State,
+    },
+    http::StatusCode,
+    response::{IntoResponse, Response},
+    Extension, Json,
+};
+use tracing::error;
+
+use wh_core::api::{DeviceInfo, DeviceRegistration, FieldError, RegisteredDevice};
+
+use crate::devices::ClientId;
+use crate::http::{
+    body_validation_error, error_response, unauthorized, TooManyRequests, Unauthorized,
+};
+use crate::AppState;
+
+/// Every problem with the zone and profile of the body
+pub(crate) fn validate(registration: &DeviceRegistration) -> Result<(), Vec<FieldError>> {
+    let Some(profile) = &registration.profile else {
+        return Ok(());
+    };
+
+    let mut errors = Vec::new();
+
+    if registration.zone.is_none() {
+        errors.push(FieldError {
+            field: "zone".to_string(),
+            code: "missing".to_string(),
+            message: "zone is required with a profile".to_string(),
+        });
+    }
+
+    if let Err(profile_errors) = profile.validate() {
+        errors.extend(profile_errors.into_iter().map(|error| FieldError {
+            field: format!("profile.{}", error.field()),
+            code: "out_of_range".to_string(),
+            message: error.to_string(),
+        }));
+    }
+
+    if errors.is_empty() {
+        Ok(())
+    } else {
+        Err(errors)
+    }
+}
+
+/// Registers a device and returns its token. Requests sent with the token are rate
+/// limited and logged per device instead of per IP address, so devices behind the same
+/// address don't run out of requests because of each other. With a profile the device
+/// can use the short `/d/{device_id}` URL.
+#[utoipa::path(
+    post,
+    path = "/api/v3/devices",
+    request_body(content = DeviceRegistration, description = "Optional name, zone and profile of the device"),
+    responses(
+        (status = 201, description = "Device registered, the token is only returned here", body = RegisteredDevice),
+        (status = 400, description = "Invalid body, with the problem of each field in `errors`", body = ErrorResponse),
+        (status = 401, response = Unauthorized),
+        (status = 429, response = TooManyRequests),
+        (status = 500, description = "The device could not be stored", body = ErrorResponse),
+    ),
+)]
+pub async fn handle_register_device(
+    State(app_state): State<AppState>,
+    registration: Result<Json<DeviceRegistration>, JsonRejection>,
+) -> Response {
+    let Json(registration) = match registration {
+        Ok(registration) => registration,
+        Err(rejection) => {
+            return error_response(StatusCode::BAD_REQUEST, &rejection.body_text());
+        }
+    };
+    if let Err(errors) = validate(&registration) {
+        return body_validation_error(errors);
+    }
+
+    let (device, token) = match app_state.devices.register(registration).await {
+        Ok(registered) => registered,
+        Err(e) => {
+            error!("Failed to register device: {}", e);
+            return error_response(
+                StatusCode::INTERNAL_SERVER_ERROR,
+                "Failed to register device",
+            );
+        }
+    };
+
+    (
+        StatusCode::CREATED,
+        Json(RegisteredDevice {
+            id: device.id,
+            token,
+            name: device.name,
+            zone: device.zone,
+            profile: device.profile,
+            registered_at: device.registered_at,
+        }),
+    )
+        .into_response()
+}
+
+/// Replaces the name, zone and profile of a device. Only the device itself can update
+/// them, with its token in the `x-device-token` header or `device_token` parameter.
+#[utoipa::path(
+    put,
+    path = "/api/v3/devices/{device_id}",
+    request_body(content = DeviceRegistration, description = "New name, zone and profile of the device"),
+    responses(
+        (status = 200, description = "Device updated", body = DeviceInfo),
+        (status = 400, description = "Invalid body, with the problem of each field in `errors`", body = ErrorResponse),
+        (status = 401, response = Unauthorized),
+        (status = 403, description = "The token is of another device", body = ErrorResponse),
+        (status = 404, description = "Unknown device", body = ErrorResponse),
+        (status = 429, response = TooManyRequests),
+        (status = 500, description = "The device could not be stored", body = ErrorResponse),
+    ),
+    params(("device_id" = String, Path, description = "Id of the device")),
+)]
+pub async fn handle_update_device(
+    State(app_state): State<AppState>,
+    client_id: Option<Extension<ClientId>>,
+    device_id: Result<Path<String>, PathRejection>,
+    registration: Result<Json<DeviceRegistration>, JsonRejection>,
+) -> Response {
+    let Ok(Path(device_id)) = device_id else {
+        return error_response(StatusCode::BAD_REQUEST, "Invalid device id");
+    };
+
+    match client_id {
+        Some(Extension(ClientId::Device(id))) if id == device_id => {}
+        Some(Extension(ClientId::Device(_))) => {
+            return error_response(StatusCode::FORBIDDEN, "Token of another device");
+        }
+        _ => {
+            return unauthorized("Device token required");
+        }
+    }
+
+    let Json(registration) = match registration {
+        Ok(registration) => registration,
+        Err(rejection) => {
+            return error_response(StatusCode::BAD_REQUEST, &rejection.body_text());
+        }
+    };
+    if let Err(errors) = validate(&registration) {
+        return body_validation_error(errors);
+    }
+
+    match app_state.devices.update(&device_id, registration).await {
+        Ok(Some(device)) => Json(DeviceInfo::from(device)).into_response(),
+        Ok(None) => error_response(StatusCode::NOT_FOUND, "Unknown device"),
+        Err(e) => {
+            error!("Failed to update device: {}", e);
+            error_response(StatusCode::INTERNAL_SERVER_ERROR, "Failed to update device")
+        }
+    }
+}
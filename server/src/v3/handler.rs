@@ -0,0 +1,98 @@
+use axum::{
+    extract::{
+        rejection::{PathRejection, QueryRejection},
+        Path, Query, State,
+    },
+    http::StatusCode,
+    response::{IntoResponse, Response},
+    Json,
+};
+use chrono::Utc;
+use tracing::info;
+
+use wh_core::api::{Period, ScheduleResponse};
+use wh_core::types::BiddingZone;
+
+use crate::common::query::PeriodQuery;
+use crate::http::{error_response, validation_error, TooManyRequests, Unauthorized};
+use crate::v2::service::plan_heating;
+use crate::AppState;
+
+/// Whether the heater should be on now, the cheapest period it was decided from and when
+/// the decision changes next.
+#[utoipa::path(
+    get,
+    path = "/api/v3/zones/{zone}/schedule",
+    responses(
+        (status = 200, description = "Decision for the current time", body = ScheduleResponse),
+        (status = 400, description = "Invalid zone or query parameters, with the problem of each parameter in `errors`", body = ErrorResponse,
+            example = json!({
+                "status": "Bad Request",
+                "message": "Invalid query parameters",
+                "errors": [{ "field": "hours", "code": "out_of_range", "message": "hours must be between 1 and 9, the length of the period, was 10" }]
+            })),
+        (status = 401, response = Unauthorized),
+        (status = 429, response = TooManyRequests),
+        (status = 503, description = "No pricing or fallback available to decide from", body = ErrorResponse),
+    ),
+    params(
+        ("zone" = BiddingZone, Path, description = "Bidding zone"),
+        PeriodQuery,
+    ),
+)]
+pub async fn handle_get_schedule(
+    State(app_state): State<AppState>,
+    zone: Result<Path<BiddingZone>, PathRejection>,
+    query: Result<Query<PeriodQuery>, QueryRejection>,
+) -> Response {
+    let Path(zone) = match zone {
+        Ok(zone) => zone,
+        Err(rejection) => {
+            return error_response(StatusCode::BAD_REQUEST, &rejection.body_text());
+        }
+    };
+    let Query(query) = match query {
+        Ok(query) => query,
+        Err(rejection) => {
+            return error_response(StatusCode::BAD_REQUEST, &rejection.body_text());
+        }
+    };
+    let period = match query.validate() {
+        Ok(period) => period,
+        Err(errors) => return validation_error(errors),
+    };
+
+    let plan = match plan_heating(
+        app_state.pricing_repository.as_ref(),
+        &app_state.decision_tables,
+        &app_state.pricing_fallback,
+        zone,
+        (period.hours, period.starting_hour, period.ending_hour),
+        Utc::now(),
+    )
+    .await
+    {
+        Ok(plan) => plan,
+        Err(_) => {
+            return error_response(StatusCode::SERVICE_UNAVAILABLE, "Pricing not available");
+        }
+    };
+
+    info!(
+        %zone,
+        enabled = plan.enabled,
+        hours = period.hours,
+        start = period.starting_hour,
+        end = period.ending_hour,
+        "Schedule requested"
+    );
+
+    Json(ScheduleResponse {
+        zone,
+        enabled: plan.enabled,
+        period: plan.period.map(|(start, end)| Period { start, end }),
+        next_change: plan.next_change,
+        fallback: plan.fallback.map(|fallback| fallback.to_string()),
+    })
+    .into_response()
+}
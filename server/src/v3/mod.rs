@@ -0,0 +1,3 @@
+pub(crate) mod devices;
+pub(crate) mod handler;
+pub(crate) mod router;
@@ -0,0 +1,27 @@
+use axum::routing::{get, post, put, MethodRouter};
+use axum::Router;
+
+use crate::http::not_found;
+use crate::AppState;
+
+use super::devices::{handle_register_device, handle_update_device};
+use super::handler::handle_get_schedule;
+
+/// Routes of the v3 API relative to `/api/v3`, documented in the OpenAPI document
+/// like the v2 routes.
+pub(crate) fn routes() -> Vec<(&'static str, MethodRouter<AppState>)> {
+    vec![
+        ("/devices", post(handle_register_device)),
+        ("/devices/:device_id", put(handle_update_device)),
+        ("/zones/:zone/schedule", get(handle_get_schedule)),
+    ]
+}
+
+pub fn v3_routes() -> Router<AppState> {
+    routes()
+        .into_iter()
+        .fold(Router::new(), |router, (path, method_router)| {
+            router.route(path, method_router)
+        })
+        .fallback(not_found)
+}
@@ -0,0 +1,293 @@
+use std::path::PathBuf;
+
+use chrono::{DateTime, Duration, NaiveDate, Timelike, Utc};
+use clap::{Parser, Subcommand};
+use reqwest::Client;
+use rust_decimal::Decimal;
+
+use wh_core::backtest::{Backtest, Strategy};
+use wh_core::repository::file::FilePricingRepository;
+use wh_core::repository::PricingRepository;
+use wh_core::schedule::{find_cheapest_start_time, is_enabled_at};
+use wh_core::test_utils::MockTimeProvider;
+use wh_core::types::BiddingZone;
+use wh_core::units::{CentsPerKwh, PRESENTATION_DECIMALS};
+use wh_core::util::get_storage_date;
+use worker::consumer::parse_pricing_data;
+use worker::producer::fetch_pricing;
+
+use crate::source::PriceSource;
+
+mod source;
+
+type Error = Box<dyn std::error::Error + Send + Sync>;
+
+/// Plan water heating and inspect electricity prices without the API or AWS.
+#[derive(Parser)]
+#[command(name = "wh", version)]
+struct Cli {
+    /// Directory of stored prices, in the same layout as the standalone server's
+    #[arg(
+        long,
+        global = true,
+        env = "PRICING_STORAGE_PATH",
+        default_value = "./pricing"
+    )]
+    data: PathBuf,
+
+    /// Read prices from a file of `[start, price]` pairs in c/kWh instead
+    #[arg(long, global = true)]
+    file: Option<PathBuf>,
+
+    /// Bidding zone, e.g. fi or se3
+    #[arg(long, global = true, default_value = "fi", value_parser = parse_zone)]
+    zone: BiddingZone,
+
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Fetch the latest prices of the zone from energy-charts into the data directory
+    Fetch,
+    /// Print the prices of a delivery day
+    Prices {
+        /// Delivery day, today by default
+        #[arg(long)]
+        date: Option<NaiveDate>,
+    },
+    /// Print the cheapest period for the given settings, as the API would choose it
+    Plan {
+        #[arg(long)]
+        hours: u32,
+        #[arg(long)]
+        start: u32,
+        #[arg(long)]
+        end: u32,
+        /// Time to plan at, e.g. 2024-04-08T12:00:00Z, now by default
+        #[arg(long)]
+        at: Option<DateTime<Utc>>,
+    },
+    /// Compare the cost of the given settings to heating at a fixed time every day
+    Backtest {
+        #[arg(long)]
+        from: NaiveDate,
+        #[arg(long)]
+        to: NaiveDate,
+        #[arg(long)]
+        hours: u32,
+        #[arg(long)]
+        start: u32,
+        #[arg(long)]
+        end: u32,
+        /// First hour of the fixed-time baseline, the start of the period by default
+        #[arg(long)]
+        baseline_start: Option<u32>,
+        /// Power of the heater in kW
+        #[arg(long, default_value = "2")]
+        power_kw: Decimal,
+    },
+}
+
+fn parse_zone(zone: &str) -> Result<BiddingZone, String> {
+    serde_json::from_value(serde_json::Value::String(zone.to_lowercase()))
+        .map_err(|_| format!("Unknown bidding zone: {zone}"))
+}
+
+fn format_price(price: &CentsPerKwh) -> String {
+    format!(
+        "{:>8.*}",
+        PRESENTATION_DECIMALS as usize,
+        price.round_dp(PRESENTATION_DECIMALS).amount()
+    )
+}
+
+async fn fetch(zone: &BiddingZone, data: PathBuf) -> Result<(), Error> {
+    let repository = FilePricingRepository::new(data);
+
+    let response = fetch_pricing(&Client::new(), zone).await?;
+    let pricing = parse_pricing_data(&zone.to_tz(), &response)?;
+
+    repository
+        .store_pricing(zone, get_storage_date(), &pricing)
+        .await?;
+    repository.archive_pricing(zone, &pricing).await?;
+
+    println!(
+        "Stored {} prices for {} from {} to {}",
+        pricing.len(),
+        zone,
+        pricing
+            .first_start()
+            .map(|s| s.to_string())
+            .unwrap_or_default(),
+        pricing
+            .last_end()
+            .map(|s| s.to_string())
+            .unwrap_or_default(),
+    );
+
+    Ok(())
+}
+
+async fn prices(source: &PriceSource, zone: &BiddingZone, date: NaiveDate) -> Result<(), Error> {
+    let days = source.delivery_days(zone, date, date).await?;
+    let Some(day) = days.first() else {
+        return Err(format!("No prices for {zone} on {date}").into());
+    };
+
+    println!("Prices for {zone} on {date} ({})", CentsPerKwh::UNIT);
+    for slot in day.pricing.iter() {
+        println!(
+            "{}  {}",
+            slot.start.format("%H:%M"),
+            format_price(&slot.price)
+        );
+    }
+
+    Ok(())
+}
+
+async fn plan(
+    source: &PriceSource,
+    zone: &BiddingZone,
+    (hours, start, end): (u32, u32, u32),
+    at: DateTime<Utc>,
+) -> Result<(), Error> {
+    let current_time = at.with_timezone(&zone.to_tz());
+    let pricing = source
+        .pricing_around(zone, current_time.date_naive())
+        .await?;
+
+    let cheapest_start = find_cheapest_start_time(
+        &pricing,
+        current_time.date_naive(),
+        current_time.hour(),
+        hours,
+        start,
+        end,
+    );
+
+    let Some(cheapest_start) = cheapest_start else {
+        println!("Not enough prices for {hours} hours between {start} and {end}");
+        return Ok(());
+    };
+    let cheapest_end = cheapest_start + Duration::hours(i64::from(hours));
+
+    println!(
+        "Cheapest {hours} hours between {start} and {end} ({})",
+        CentsPerKwh::UNIT
+    );
+    for slot in pricing
+        .iter()
+        .filter(|slot| slot.start >= cheapest_start && slot.start < cheapest_end)
+    {
+        println!(
+            "{}  {}",
+            slot.start.format("%Y-%m-%d %H:%M"),
+            format_price(&slot.price)
+        );
+    }
+
+    let enabled = is_enabled_at(
+        &MockTimeProvider::new(at),
+        zone,
+        &pricing,
+        hours,
+        start,
+        end,
+    );
+    println!(
+        "Heater is {} at {}",
+        if enabled { "on" } else { "off" },
+        current_time.format("%Y-%m-%d %H:%M")
+    );
+
+    Ok(())
+}
+
+async fn backtest(
+    source: &PriceSource,
+    backtest: Backtest,
+    from: NaiveDate,
+    to: NaiveDate,
+) -> Result<(), Error> {
+    let days = source.delivery_days(&backtest.zone, from, to).await?;
+    if days.is_empty() {
+        return Err(format!("No prices for {} from {from} to {to}", backtest.zone).into());
+    }
+
+    let report = backtest.run(&days);
+
+    println!("Date        Hours  Cost (c)  Baseline (c)");
+    for day in &report.days {
+        println!(
+            "{}  {:>5}  {:>8}  {:>12}",
+            day.date,
+            day.hours_on.normalize(),
+            day.cost.round_dp(2),
+            day.baseline_cost.round_dp(2)
+        );
+    }
+
+    println!(
+        "Total: {} c, baseline {} c, saved {} c over {} days",
+        report.total_cost().round_dp(2),
+        report.total_baseline_cost().round_dp(2),
+        report.savings().round_dp(2),
+        report.days.len()
+    );
+
+    Ok(())
+}
+
+#[tokio::main]
+async fn main() -> Result<(), Error> {
+    let cli = Cli::parse();
+    let source = PriceSource::new(cli.file, cli.data.clone());
+    let zone = cli.zone;
+
+    match cli.command {
+        Command::Fetch => fetch(&zone, cli.data).await,
+        Command::Prices { date } => {
+            let today = Utc::now().with_timezone(&zone.to_tz()).date_naive();
+            prices(&source, &zone, date.unwrap_or(today)).await
+        }
+        Command::Plan {
+            hours,
+            start,
+            end,
+            at,
+        } => {
+            plan(
+                &source,
+                &zone,
+                (hours, start, end),
+                at.unwrap_or_else(Utc::now),
+            )
+            .await
+        }
+        Command::Backtest {
+            from,
+            to,
+            hours,
+            start,
+            end,
+            baseline_start,
+            power_kw,
+        } => {
+            let backtest_settings = Backtest {
+                zone,
+                strategy: Strategy::CheapestPeriod { hours, start, end },
+                baseline: Strategy::FixedTime {
+                    hours,
+                    start: baseline_start.unwrap_or(start),
+                },
+                power_kw,
+            };
+
+            backtest(&source, backtest_settings, from, to).await
+        }
+    }
+}
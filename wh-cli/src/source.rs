@@ -0,0 +1,125 @@
+use std::path::PathBuf;
+
+use chrono::{Duration, NaiveDate};
+use rust_decimal::Decimal;
+
+use wh_core::price_series::PriceSeries;
+use wh_core::repository::file::FilePricingRepository;
+use wh_core::repository::{DeliveryDay, PricingRepository};
+use wh_core::types::BiddingZone;
+use wh_core::units::CentsPerKwh;
+
+use crate::Error;
+
+/// Where the prices are read from: a single price file, or the archive of a directory
+/// in the same layout the standalone server uses.
+pub enum PriceSource {
+    File(PathBuf),
+    Archive(FilePricingRepository),
+}
+
+impl PriceSource {
+    pub fn new(file: Option<PathBuf>, data: PathBuf) -> Self {
+        match file {
+            Some(file) => PriceSource::File(file),
+            None => PriceSource::Archive(FilePricingRepository::new(data)),
+        }
+    }
+
+    pub async fn delivery_days(
+        &self,
+        zone: &BiddingZone,
+        from: NaiveDate,
+        to: NaiveDate,
+    ) -> Result<Vec<DeliveryDay>, Error> {
+        match self {
+            PriceSource::File(path) => {
+                let pricing = read_price_file(path, zone)?;
+
+                Ok(DeliveryDay::split(zone, &pricing)
+                    .into_iter()
+                    .filter(|day| day.date >= from && day.date <= to)
+                    .collect())
+            }
+            PriceSource::Archive(repository) => {
+                Ok(repository.get_delivery_days(zone, from, to).await?)
+            }
+        }
+    }
+
+    /// Prices of `date` together with the days around it, which periods crossing
+    /// midnight need.
+    pub async fn pricing_around(
+        &self,
+        zone: &BiddingZone,
+        date: NaiveDate,
+    ) -> Result<PriceSeries<CentsPerKwh>, Error> {
+        let days = self
+            .delivery_days(zone, date - Duration::days(1), date + Duration::days(1))
+            .await?;
+
+        let Some(first) = days.first() else {
+            return Err(format!("No prices for {zone} around {date}").into());
+        };
+
+        Ok(PriceSeries::from_slots(
+            first.pricing.resolution(),
+            days.iter()
+                .flat_map(|day| day.pricing.iter().map(|slot| (slot.start, slot.price))),
+        ))
+    }
+}
+
+/// Reads a file of `[start, price]` pairs in c/kWh, the format the pricing is stored in.
+/// Slots are moved to the local time of the zone, which the periods are given in.
+pub fn read_price_file(
+    path: &PathBuf,
+    zone: &BiddingZone,
+) -> Result<PriceSeries<CentsPerKwh>, Error> {
+    let contents =
+        std::fs::read_to_string(path).map_err(|e| format!("{}: {}", path.display(), e))?;
+    let pricing: PriceSeries<Decimal> = serde_json::from_str(&contents)?;
+
+    let tz = zone.to_tz();
+
+    Ok(PriceSeries::from_slots(
+        pricing.resolution(),
+        pricing.iter().map(|slot| {
+            (
+                slot.start.with_timezone(&tz).fixed_offset(),
+                CentsPerKwh::eur(slot.price),
+            )
+        }),
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::Timelike;
+
+    use super::*;
+
+    #[tokio::test]
+    async fn test_price_file_is_split_into_delivery_days() {
+        let path = std::env::temp_dir().join(format!("wh-cli-prices-{}.json", std::process::id()));
+        std::fs::write(
+            &path,
+            r#"[["2024-04-08T20:00:00Z",1.5],["2024-04-08T21:00:00Z",2.5],["2024-04-08T22:00:00Z",3.5]]"#,
+        )
+        .unwrap();
+
+        let source = PriceSource::new(Some(path.clone()), PathBuf::new());
+        let date = NaiveDate::from_ymd_opt(2024, 4, 9).unwrap();
+        let days = source
+            .delivery_days(&BiddingZone::FI, date, date)
+            .await
+            .unwrap();
+
+        std::fs::remove_file(&path).unwrap();
+
+        // 21:00 UTC is midnight in Helsinki
+        assert_eq!(days.len(), 1);
+        assert_eq!(days[0].pricing.len(), 2);
+        assert_eq!(days[0].pricing.first_start().unwrap().hour(), 0);
+    }
+}
@@ -0,0 +1,21 @@
+use reqwest::StatusCode;
+use thiserror::Error;
+use wh_core::schedule::PeriodError;
+
+#[derive(Error, Debug)]
+pub enum ClientError {
+    #[error("Invalid base URL: {0}")]
+    BaseUrl(String),
+    #[error("Invalid API key: {0}")]
+    ApiKey(String),
+    #[error("Invalid device token: {0}")]
+    DeviceToken(String),
+    #[error("Invalid admin key: {0}")]
+    AdminKey(String),
+    #[error("Invalid period: {}", .0.iter().map(ToString::to_string).collect::<Vec<_>>().join(", "))]
+    Period(Vec<PeriodError>),
+    #[error("Request failed: {0}")]
+    Http(#[from] reqwest::Error),
+    #[error("Unexpected response {status}: {body}")]
+    Status { status: StatusCode, body: String },
+}
@@ -0,0 +1,738 @@
+//! Typed client for the waterheater API.
+//!
+//! Every route of the OpenAPI document has a method here and its path in [`ROUTES`].
+//! The server tests compare [`ROUTES`] to the document, so a route added to the API
+//! without a client method fails the build.
+
+use std::time::Duration;
+
+use chrono::NaiveDate;
+use reqwest::header::{HeaderMap, HeaderValue, RETRY_AFTER};
+use reqwest::{Response, StatusCode};
+use serde::Serialize;
+
+use wh_core::api::{
+    ApiKeyInfo, ApiKeyRequest, ClientRateLimit, ClientUsage, CreatedApiKey, DeliveryDayPrices,
+    DeviceInfo, DeviceRegistration, PriceRangeResponse, RateLimitOverride, RegisteredDevice,
+    ScheduleResponse,
+};
+use wh_core::schedule::HeatingPeriod;
+use wh_core::types::BiddingZone;
+
+pub use crate::error::ClientError;
+
+mod error;
+
+pub const CHEAPEST_PERIOD_PATH: &str = "/api/v2/waterheater/country/{country_code}/cheapest-period";
+pub const PRICES_PATH: &str = "/api/v2/prices/{zone}";
+pub const SCHEDULE_PATH: &str = "/api/v3/zones/{zone}/schedule";
+pub const DEVICES_PATH: &str = "/api/v3/devices";
+pub const DEVICE_PATH: &str = "/api/v3/devices/{device_id}";
+pub const DEVICE_URL_PATH: &str = "/d/{device_id}";
+pub const ADMIN_API_KEYS_PATH: &str = "/api/admin/api-keys";
+pub const ADMIN_API_KEY_PATH: &str = "/api/admin/api-keys/{name}";
+pub const ADMIN_DEVICES_PATH: &str = "/api/admin/devices";
+pub const ADMIN_DEVICE_PATH: &str = "/api/admin/devices/{device_id}";
+pub const ADMIN_RATE_LIMITS_PATH: &str = "/api/admin/rate-limits";
+pub const ADMIN_RATE_LIMIT_PATH: &str = "/api/admin/rate-limits/{client}";
+pub const ADMIN_USAGE_PATH: &str = "/api/admin/usage";
+
+/// Paths of the routes the client covers, as written in the OpenAPI document
+pub const ROUTES: &[&str] = &[
+    CHEAPEST_PERIOD_PATH,
+    PRICES_PATH,
+    SCHEDULE_PATH,
+    DEVICES_PATH,
+    DEVICE_PATH,
+    DEVICE_URL_PATH,
+    ADMIN_API_KEYS_PATH,
+    ADMIN_API_KEY_PATH,
+    ADMIN_DEVICES_PATH,
+    ADMIN_DEVICE_PATH,
+    ADMIN_RATE_LIMITS_PATH,
+    ADMIN_RATE_LIMIT_PATH,
+    ADMIN_USAGE_PATH,
+];
+
+const FALLBACK_HEADER: &str = "x-pricing-fallback";
+const API_KEY_HEADER: &str = "x-api-key";
+const DEVICE_TOKEN_HEADER: &str = "x-device-token";
+const ADMIN_KEY_HEADER: &str = "x-admin-key";
+const NO_QUERY: [(&str, &str); 0] = [];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub struct CheapestPeriodRequest {
+    /// Number of hours in the period
+    pub hours: u32,
+    /// First hour of the period in 24h format
+    pub start: u32,
+    /// The hour when the period ends in 24h format
+    pub end: u32,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub struct PriceRangeRequest {
+    pub from: NaiveDate,
+    /// Last delivery day of the range, inclusive
+    pub to: NaiveDate,
+    /// Number of delivery days per page, the server default if not set
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub limit: Option<u32>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CheapestPeriod {
+    /// Whether the current hour is within the cheapest period
+    pub enabled: bool,
+    /// Fallback the server used when the published pricing was not available
+    pub fallback: Option<String>,
+}
+
+/// How failed requests are retried. Connection errors, timeouts, 429 and 5xx responses
+/// are retried with exponential backoff, or after `Retry-After` when the server sends it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RetryPolicy {
+    pub max_retries: u32,
+    pub initial_backoff: Duration,
+    pub max_backoff: Duration,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        RetryPolicy {
+            max_retries: 3,
+            initial_backoff: Duration::from_millis(200),
+            max_backoff: Duration::from_secs(5),
+        }
+    }
+}
+
+impl RetryPolicy {
+    pub fn none() -> Self {
+        RetryPolicy {
+            max_retries: 0,
+            ..Default::default()
+        }
+    }
+
+    fn backoff(&self, attempt: u32) -> Duration {
+        self.initial_backoff
+            .saturating_mul(2u32.saturating_pow(attempt))
+            .min(self.max_backoff)
+    }
+}
+
+pub struct ClientBuilder {
+    base_url: String,
+    api_key: Option<String>,
+    device_token: Option<String>,
+    admin_key: Option<String>,
+    timeout: Duration,
+    connect_timeout: Duration,
+    retry: RetryPolicy,
+}
+
+impl ClientBuilder {
+    /// Timeout of a single attempt, 10 seconds by default
+    pub fn timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    pub fn connect_timeout(mut self, timeout: Duration) -> Self {
+        self.connect_timeout = timeout;
+        self
+    }
+
+    /// Sent in the `x-api-key` header of every request
+    pub fn api_key(mut self, api_key: impl Into<String>) -> Self {
+        self.api_key = Some(api_key.into());
+        self
+    }
+
+    /// Sent in the `x-device-token` header of every request, so that the requests are
+    /// rate limited per device instead of per IP address
+    pub fn device_token(mut self, device_token: impl Into<String>) -> Self {
+        self.device_token = Some(device_token.into());
+        self
+    }
+
+    /// Key of the admin API, required by the `admin_*` methods
+    pub fn admin_key(mut self, admin_key: impl Into<String>) -> Self {
+        self.admin_key = Some(admin_key.into());
+        self
+    }
+
+    pub fn retry(mut self, retry: RetryPolicy) -> Self {
+        self.retry = retry;
+        self
+    }
+
+    pub fn build(self) -> Result<Client, ClientError> {
+        let base_url = self.base_url.trim_end_matches('/').to_string();
+        if !base_url.starts_with("http://") && !base_url.starts_with("https://") {
+            return Err(ClientError::BaseUrl(self.base_url));
+        }
+
+        let mut headers = HeaderMap::new();
+        if let Some(api_key) = &self.api_key {
+            let mut value = HeaderValue::from_str(api_key)
+                .map_err(|_| ClientError::ApiKey("not a valid header value".to_string()))?;
+            value.set_sensitive(true);
+            headers.insert(API_KEY_HEADER, value);
+        }
+        if let Some(device_token) = &self.device_token {
+            let mut value = HeaderValue::from_str(device_token)
+                .map_err(|_| ClientError::DeviceToken("not a valid header value".to_string()))?;
+            value.set_sensitive(true);
+            headers.insert(DEVICE_TOKEN_HEADER, value);
+        }
+        if let Some(admin_key) = &self.admin_key {
+            let mut value = HeaderValue::from_str(admin_key)
+                .map_err(|_| ClientError::AdminKey("not a valid header value".to_string()))?;
+            value.set_sensitive(true);
+            headers.insert(ADMIN_KEY_HEADER, value);
+        }
+
+        let http = reqwest::Client::builder()
+            .timeout(self.timeout)
+            .connect_timeout(self.connect_timeout)
+            .default_headers(headers)
+            .build()?;
+
+        Ok(Client {
+            http,
+            base_url,
+            retry: self.retry,
+        })
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Client {
+    http: reqwest::Client,
+    base_url: String,
+    retry: RetryPolicy,
+}
+
+impl Client {
+    /// Client with the default timeouts and retries, `base_url` without the `/api/v2` prefix
+    pub fn new(base_url: impl Into<String>) -> Result<Self, ClientError> {
+        Self::builder(base_url).build()
+    }
+
+    pub fn builder(base_url: impl Into<String>) -> ClientBuilder {
+        ClientBuilder {
+            base_url: base_url.into(),
+            api_key: None,
+            device_token: None,
+            admin_key: None,
+            timeout: Duration::from_secs(10),
+            connect_timeout: Duration::from_secs(5),
+            retry: RetryPolicy::default(),
+        }
+    }
+
+    /// Whether the water heater should be on during the current hour. The API answers
+    /// with 200 or 400 for the Shelly scripts, which are mapped to `enabled` here. The
+    /// API also answers an invalid period with a 400, so the period is validated before
+    /// sending and an invalid one is an error.
+    pub async fn cheapest_period(
+        &self,
+        zone: BiddingZone,
+        request: &CheapestPeriodRequest,
+    ) -> Result<CheapestPeriod, ClientError> {
+        HeatingPeriod::new(request.hours, request.start, request.end)
+            .map_err(ClientError::Period)?;
+
+        let path = CHEAPEST_PERIOD_PATH.replace("{country_code}", &zone_segment(zone));
+        let response = self.get(&path, request).await?;
+
+        let enabled = match response.status() {
+            StatusCode::OK => true,
+            StatusCode::BAD_REQUEST => false,
+            _ => return Err(status_error(response).await),
+        };
+        let fallback = response
+            .headers()
+            .get(FALLBACK_HEADER)
+            .and_then(|value| value.to_str().ok())
+            .map(str::to_string);
+
+        Ok(CheapestPeriod { enabled, fallback })
+    }
+
+    /// A single page of archived prices, `next` is set when the range continues
+    pub async fn prices(
+        &self,
+        zone: BiddingZone,
+        request: &PriceRangeRequest,
+    ) -> Result<PriceRangeResponse, ClientError> {
+        let path = PRICES_PATH.replace("{zone}", &zone_segment(zone));
+        let response = self.get(&path, request).await?;
+
+        if response.status() != StatusCode::OK {
+            return Err(status_error(response).await);
+        }
+
+        Ok(response.json().await?)
+    }
+
+    /// Archived prices of the whole range, following the pages
+    pub async fn all_prices(
+        &self,
+        zone: BiddingZone,
+        from: NaiveDate,
+        to: NaiveDate,
+    ) -> Result<Vec<DeliveryDayPrices>, ClientError> {
+        let mut days = Vec::new();
+        let mut request = PriceRangeRequest {
+            from,
+            to,
+            limit: None,
+        };
+
+        loop {
+            let page = self.prices(zone, &request).await?;
+            days.extend(page.days);
+
+            match page.next {
+                Some(next) => request.from = next,
+                None => return Ok(days),
+            }
+        }
+    }
+
+    /// The decision of the v3 API, with the cheapest period and the next change.
+    /// Unlike [`Client::cheapest_period`] a 400 is an error here.
+    pub async fn schedule(
+        &self,
+        zone: BiddingZone,
+        request: &CheapestPeriodRequest,
+    ) -> Result<ScheduleResponse, ClientError> {
+        let path = SCHEDULE_PATH.replace("{zone}", &zone_segment(zone));
+        let response = self.get(&path, request).await?;
+
+        if response.status() != StatusCode::OK {
+            return Err(status_error(response).await);
+        }
+
+        Ok(response.json().await?)
+    }
+
+    /// Registers a device and returns its token. Not retried, as a retry after a lost
+    /// response would register a second device.
+    pub async fn register_device(
+        &self,
+        registration: &DeviceRegistration,
+    ) -> Result<RegisteredDevice, ClientError> {
+        let url = format!("{}{}", self.base_url, DEVICES_PATH);
+        let response = self.http.post(&url).json(registration).send().await?;
+
+        if response.status() != StatusCode::CREATED {
+            return Err(status_error(response).await);
+        }
+
+        Ok(response.json().await?)
+    }
+
+    /// Replaces the name, zone and profile of the device. The client has to be built
+    /// with the token of the device.
+    pub async fn update_device(
+        &self,
+        device_id: &str,
+        registration: &DeviceRegistration,
+    ) -> Result<DeviceInfo, ClientError> {
+        let url = format!(
+            "{}{}",
+            self.base_url,
+            DEVICE_PATH.replace("{device_id}", device_id)
+        );
+        let response = self.http.put(&url).json(registration).send().await?;
+
+        if response.status() != StatusCode::OK {
+            return Err(status_error(response).await);
+        }
+
+        Ok(response.json().await?)
+    }
+
+    /// Decision for the stored profile of the device. The API answers with 200 or 400
+    /// for the Shelly scripts, both with the decision in the body.
+    pub async fn device_schedule(&self, device_id: &str) -> Result<ScheduleResponse, ClientError> {
+        let path = DEVICE_URL_PATH.replace("{device_id}", device_id);
+        let response = self.get(&path, &NO_QUERY).await?;
+
+        match response.status() {
+            StatusCode::OK | StatusCode::BAD_REQUEST => Ok(response.json().await?),
+            _ => Err(status_error(response).await),
+        }
+    }
+
+    /// Names of the API keys
+    pub async fn admin_api_keys(&self) -> Result<Vec<ApiKeyInfo>, ClientError> {
+        let response = self.get(ADMIN_API_KEYS_PATH, &NO_QUERY).await?;
+
+        if response.status() != StatusCode::OK {
+            return Err(status_error(response).await);
+        }
+
+        Ok(response.json().await?)
+    }
+
+    /// Generates an API key named `name`. Not retried, the key is only returned once.
+    pub async fn admin_create_api_key(&self, name: &str) -> Result<CreatedApiKey, ClientError> {
+        let url = format!("{}{}", self.base_url, ADMIN_API_KEYS_PATH);
+        let request = ApiKeyRequest {
+            name: name.to_string(),
+        };
+        let response = self.http.post(&url).json(&request).send().await?;
+
+        if response.status() != StatusCode::CREATED {
+            return Err(status_error(response).await);
+        }
+
+        Ok(response.json().await?)
+    }
+
+    /// Returns false if there is no key named `name`
+    pub async fn admin_delete_api_key(&self, name: &str) -> Result<bool, ClientError> {
+        self.delete(&ADMIN_API_KEY_PATH.replace("{name}", name))
+            .await
+    }
+
+    pub async fn admin_devices(&self) -> Result<Vec<DeviceInfo>, ClientError> {
+        let response = self.get(ADMIN_DEVICES_PATH, &NO_QUERY).await?;
+
+        if response.status() != StatusCode::OK {
+            return Err(status_error(response).await);
+        }
+
+        Ok(response.json().await?)
+    }
+
+    /// The device, `None` if it doesn't exist
+    pub async fn admin_device(&self, device_id: &str) -> Result<Option<DeviceInfo>, ClientError> {
+        let path = ADMIN_DEVICE_PATH.replace("{device_id}", device_id);
+        let response = self.get(&path, &NO_QUERY).await?;
+
+        match response.status() {
+            StatusCode::OK => Ok(Some(response.json().await?)),
+            StatusCode::NOT_FOUND => Ok(None),
+            _ => Err(status_error(response).await),
+        }
+    }
+
+    /// Replaces the name, zone and profile of any device, without its token
+    pub async fn admin_replace_device(
+        &self,
+        device_id: &str,
+        registration: &DeviceRegistration,
+    ) -> Result<DeviceInfo, ClientError> {
+        let url = format!(
+            "{}{}",
+            self.base_url,
+            ADMIN_DEVICE_PATH.replace("{device_id}", device_id)
+        );
+        let response = self.http.put(&url).json(registration).send().await?;
+
+        if response.status() != StatusCode::OK {
+            return Err(status_error(response).await);
+        }
+
+        Ok(response.json().await?)
+    }
+
+    /// Returns false if the device doesn't exist. Other server instances may accept the
+    /// token of the device for up to five minutes after.
+    pub async fn admin_delete_device(&self, device_id: &str) -> Result<bool, ClientError> {
+        self.delete(&ADMIN_DEVICE_PATH.replace("{device_id}", device_id))
+            .await
+    }
+
+    /// Clients with a rate limit other than the default
+    pub async fn admin_rate_limits(&self) -> Result<Vec<ClientRateLimit>, ClientError> {
+        let response = self.get(ADMIN_RATE_LIMITS_PATH, &NO_QUERY).await?;
+
+        if response.status() != StatusCode::OK {
+            return Err(status_error(response).await);
+        }
+
+        Ok(response.json().await?)
+    }
+
+    /// Sets the rate limit of `client`, `device:<id>`, `key:<name>` or an IP address
+    pub async fn admin_set_rate_limit(
+        &self,
+        client: &str,
+        limit: &RateLimitOverride,
+    ) -> Result<ClientRateLimit, ClientError> {
+        let url = format!(
+            "{}{}",
+            self.base_url,
+            ADMIN_RATE_LIMIT_PATH.replace("{client}", client)
+        );
+        let response = self.http.put(&url).json(limit).send().await?;
+
+        if response.status() != StatusCode::OK {
+            return Err(status_error(response).await);
+        }
+
+        Ok(response.json().await?)
+    }
+
+    /// Returns false if the client already has the default rate limit
+    pub async fn admin_delete_rate_limit(&self, client: &str) -> Result<bool, ClientError> {
+        self.delete(&ADMIN_RATE_LIMIT_PATH.replace("{client}", client))
+            .await
+    }
+
+    /// Rate limited clients seen within the last hour
+    pub async fn admin_usage(&self) -> Result<Vec<ClientUsage>, ClientError> {
+        let response = self.get(ADMIN_USAGE_PATH, &NO_QUERY).await?;
+
+        if response.status() != StatusCode::OK {
+            return Err(status_error(response).await);
+        }
+
+        Ok(response.json().await?)
+    }
+
+    /// `DELETE` answered with 204, or 404 when there was nothing to delete
+    async fn delete(&self, path: &str) -> Result<bool, ClientError> {
+        let url = format!("{}{}", self.base_url, path);
+        let response = self.http.delete(&url).send().await?;
+
+        match response.status() {
+            StatusCode::NO_CONTENT => Ok(true),
+            StatusCode::NOT_FOUND => Ok(false),
+            _ => Err(status_error(response).await),
+        }
+    }
+
+    async fn get<Q: Serialize>(&self, path: &str, query: &Q) -> Result<Response, ClientError> {
+        let url = format!("{}{}", self.base_url, path);
+        let mut attempt = 0;
+
+        loop {
+            let result = self.http.get(&url).query(query).send().await;
+
+            let retry_after = match &result {
+                Ok(response) if is_retryable(response.status()) => Some(retry_after(response)),
+                Err(e) if e.is_timeout() || e.is_connect() => Some(None),
+                _ => None,
+            };
+
+            match retry_after {
+                Some(wait) if attempt < self.retry.max_retries => {
+                    let wait = wait
+                        .map(|wait| wait.min(self.retry.max_backoff))
+                        .unwrap_or_else(|| self.retry.backoff(attempt));
+                    tokio::time::sleep(wait).await;
+                    attempt += 1;
+                }
+                _ => return Ok(result?),
+            }
+        }
+    }
+}
+
+fn zone_segment(zone: BiddingZone) -> String {
+    zone.to_string().to_lowercase()
+}
+
+fn is_retryable(status: StatusCode) -> bool {
+    status == StatusCode::TOO_MANY_REQUESTS
+        || (status.is_server_error() && status != StatusCode::NOT_IMPLEMENTED)
+}
+
+fn retry_after(response: &Response) -> Option<Duration> {
+    response
+        .headers()
+        .get(RETRY_AFTER)?
+        .to_str()
+        .ok()?
+        .parse()
+        .ok()
+        .map(Duration::from_secs)
+}
+
+async fn status_error(response: Response) -> ClientError {
+    let status = response.status();
+    let body = response.text().await.unwrap_or_default();
+
+    ClientError::Status { status, body }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::{AtomicU32, Ordering};
+    use std::sync::Arc;
+
+    use axum::{
+        extract::{Query, State},
+        http::StatusCode,
+        response::IntoResponse,
+        routing::get,
+        Json, Router,
+    };
+    use serde::Deserialize;
+    use wh_core::units::Currency;
+
+    use super::*;
+
+    async fn serve(router: Router) -> String {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move { axum::serve(listener, router).await.unwrap() });
+
+        format!("http://{addr}")
+    }
+
+    fn fast_retries() -> RetryPolicy {
+        RetryPolicy {
+            max_retries: 2,
+            initial_backoff: Duration::from_millis(1),
+            max_backoff: Duration::from_millis(10),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_cheapest_period_retries_unavailable_server() {
+        let calls = Arc::new(AtomicU32::new(0));
+        let router = Router::new()
+            .route(
+                "/api/v2/waterheater/country/fi/cheapest-period",
+                get(
+                    |State(calls): State<Arc<AtomicU32>>,
+                     Query(request): Query<serde_json::Value>| async move {
+                        assert_eq!(request["hours"], "3");
+                        if calls.fetch_add(1, Ordering::SeqCst) == 0 {
+                            StatusCode::SERVICE_UNAVAILABLE.into_response()
+                        } else {
+                            (StatusCode::BAD_REQUEST, [(FALLBACK_HEADER, "typical-day")])
+                                .into_response()
+                        }
+                    },
+                ),
+            )
+            .with_state(calls.clone());
+
+        let client = Client::builder(serve(router).await)
+            .retry(fast_retries())
+            .build()
+            .unwrap();
+        let decision = client
+            .cheapest_period(
+                BiddingZone::FI,
+                &CheapestPeriodRequest {
+                    hours: 3,
+                    start: 22,
+                    end: 6,
+                },
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(calls.load(Ordering::SeqCst), 2);
+        assert!(!decision.enabled);
+        assert_eq!(decision.fallback.as_deref(), Some("typical-day"));
+    }
+
+    #[tokio::test]
+    async fn test_cheapest_period_rejects_invalid_period() {
+        let calls = Arc::new(AtomicU32::new(0));
+        let router = Router::new()
+            .route(
+                "/api/v2/waterheater/country/fi/cheapest-period",
+                get(|State(calls): State<Arc<AtomicU32>>| async move {
+                    calls.fetch_add(1, Ordering::SeqCst);
+                    StatusCode::BAD_REQUEST
+                }),
+            )
+            .with_state(calls.clone());
+
+        let client = Client::new(serve(router).await).unwrap();
+        let result = client
+            .cheapest_period(
+                BiddingZone::FI,
+                &CheapestPeriodRequest {
+                    hours: 0,
+                    start: 25,
+                    end: 30,
+                },
+            )
+            .await;
+
+        assert!(matches!(result, Err(ClientError::Period(errors)) if errors.len() == 2));
+        assert_eq!(calls.load(Ordering::SeqCst), 0);
+    }
+
+    #[derive(Deserialize)]
+    struct PageParams {
+        from: NaiveDate,
+    }
+
+    #[tokio::test]
+    async fn test_all_prices_follows_pages() {
+        let router = Router::new().route(
+            "/api/v2/prices/se3",
+            get(|Query(params): Query<PageParams>| async move {
+                let last = NaiveDate::from_ymd_opt(2024, 4, 9).unwrap();
+                let next = (params.from < last).then(|| params.from.succ_opt().unwrap());
+                Json(PriceRangeResponse {
+                    zone: BiddingZone::SE3,
+                    unit: "c/kWh".to_string(),
+                    currency: Currency::Eur,
+                    days: vec![DeliveryDayPrices {
+                        date: params.from,
+                        prices: vec![],
+                    }],
+                    next,
+                })
+            }),
+        );
+
+        let client = Client::new(serve(router).await).unwrap();
+        let days = client
+            .all_prices(
+                BiddingZone::SE3,
+                NaiveDate::from_ymd_opt(2024, 4, 8).unwrap(),
+                NaiveDate::from_ymd_opt(2024, 4, 9).unwrap(),
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(days.len(), 2);
+        assert_eq!(days[1].date, NaiveDate::from_ymd_opt(2024, 4, 9).unwrap());
+    }
+
+    #[tokio::test]
+    async fn test_unexpected_status_is_an_error() {
+        let router = Router::new().route(
+            "/api/v2/prices/fi",
+            get(|| async { (StatusCode::BAD_REQUEST, "from must not be after to") }),
+        );
+
+        let client = Client::new(serve(router).await).unwrap();
+        let date = NaiveDate::from_ymd_opt(2024, 4, 8).unwrap();
+        let result = client
+            .prices(
+                BiddingZone::FI,
+                &PriceRangeRequest {
+                    from: date,
+                    to: date,
+                    limit: Some(1),
+                },
+            )
+            .await;
+
+        assert!(matches!(
+            result,
+            Err(ClientError::Status {
+                status: StatusCode::BAD_REQUEST,
+                ..
+            })
+        ));
+    }
+}
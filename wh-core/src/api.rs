@@ -0,0 +1,199 @@
+//! Request and response bodies of the HTTP API, shared by the server and the client.
+
+use chrono::{DateTime, FixedOffset, NaiveDate, Utc};
+use rust_decimal::Decimal;
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+use crate::device::{Device, DeviceProfile};
+use crate::repository::DeliveryDay;
+use crate::types::BiddingZone;
+use crate::units::Currency;
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, ToSchema)]
+pub struct PriceSlot {
+    #[schema(value_type = String, format = DateTime)]
+    pub start: DateTime<FixedOffset>,
+    #[serde(with = "rust_decimal::serde::float")]
+    #[schema(value_type = f64)]
+    pub price: Decimal,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, ToSchema)]
+pub struct DeliveryDayPrices {
+    #[schema(value_type = String, format = Date)]
+    pub date: NaiveDate,
+    pub prices: Vec<PriceSlot>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, ToSchema)]
+pub struct PriceRangeResponse {
+    pub zone: BiddingZone,
+    pub unit: String,
+    pub currency: Currency,
+    /// Delivery days of the page with prices, days without prices are left out
+    pub days: Vec<DeliveryDayPrices>,
+    /// `from` of the next page, missing on the last page
+    #[schema(value_type = Option<String>, format = Date)]
+    pub next: Option<NaiveDate>,
+}
+
+impl From<DeliveryDay> for DeliveryDayPrices {
+    fn from(day: DeliveryDay) -> Self {
+        DeliveryDayPrices {
+            date: day.date,
+            prices: day
+                .pricing
+                .iter()
+                .map(|slot| PriceSlot {
+                    start: slot.start,
+                    price: slot.price.amount(),
+                })
+                .collect(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+pub struct Period {
+    #[schema(value_type = String, format = DateTime)]
+    pub start: DateTime<FixedOffset>,
+    #[schema(value_type = String, format = DateTime)]
+    pub end: DateTime<FixedOffset>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+pub struct ScheduleResponse {
+    pub zone: BiddingZone,
+    /// Whether the heater should be on now
+    pub enabled: bool,
+    /// Cheapest period of the current operating hours, missing when it could not be
+    /// calculated or the fixed window fallback was used
+    pub period: Option<Period>,
+    /// When `enabled` changes next, missing if it doesn't change before the next prices
+    /// are published
+    #[schema(value_type = Option<String>, format = DateTime)]
+    pub next_change: Option<DateTime<FixedOffset>>,
+    /// Fallback used when the published pricing was not available
+    pub fallback: Option<String>,
+}
+
+/// Body of the JSON error responses
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, ToSchema)]
+pub struct ErrorResponse {
+    /// Reason phrase of the status code, e.g. `Not Found`
+    pub status: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub message: Option<String>,
+    /// Problems with each invalid parameter of the request
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub errors: Vec<FieldError>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+pub struct FieldError {
+    /// Name of the parameter, e.g. `hours`
+    pub field: String,
+    /// `missing`, `invalid` if the value could not be parsed, or `out_of_range`
+    pub code: String,
+    pub message: String,
+}
+
+/// Body of a device registration or update, everything is optional
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+pub struct DeviceRegistration {
+    /// Name to recognize the device by, e.g. `Boiler`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+    /// Required with a profile
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub zone: Option<BiddingZone>,
+    /// Evaluated by `/d/{device_id}`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub profile: Option<DeviceProfile>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+pub struct DeviceInfo {
+    pub id: String,
+    pub name: Option<String>,
+    pub zone: Option<BiddingZone>,
+    pub profile: Option<DeviceProfile>,
+    #[schema(value_type = String, format = DateTime)]
+    pub registered_at: DateTime<Utc>,
+}
+
+impl From<Device> for DeviceInfo {
+    fn from(device: Device) -> Self {
+        DeviceInfo {
+            id: device.id,
+            name: device.name,
+            zone: device.zone,
+            profile: device.profile,
+            registered_at: device.registered_at,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+pub struct RegisteredDevice {
+    /// Public id of the device
+    pub id: String,
+    /// Sent by the device in the `x-device-token` header or the `device_token` query
+    /// parameter. Only returned here, it can't be recovered later.
+    pub token: String,
+    pub name: Option<String>,
+    pub zone: Option<BiddingZone>,
+    pub profile: Option<DeviceProfile>,
+    #[schema(value_type = String, format = DateTime)]
+    pub registered_at: DateTime<Utc>,
+}
+
+/// Body of creating an API key, the key itself is generated
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+pub struct ApiKeyRequest {
+    /// Letters, digits, `-` and `_`, e.g. `home-assistant`
+    pub name: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+pub struct ApiKeyInfo {
+    pub name: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+pub struct CreatedApiKey {
+    pub name: String,
+    /// Only returned here, it can't be recovered later
+    pub key: String,
+}
+
+/// Rate limit of a single client in place of the default
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize, ToSchema)]
+pub struct RateLimitOverride {
+    /// Requests that can be made at once
+    pub capacity: u32,
+    /// Requests added back per minute
+    pub refill_per_minute: f64,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, ToSchema)]
+pub struct ClientRateLimit {
+    /// `device:<id>` for devices, `key:<name>` for API keys, otherwise the IP address
+    pub client: String,
+    pub capacity: u32,
+    pub refill_per_minute: f64,
+}
+
+/// Requests of a rate limited client, kept for an hour after its last request
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, ToSchema)]
+pub struct ClientUsage {
+    /// `device:<id>` for devices, `key:<name>` for API keys, otherwise the IP address,
+    /// followed by `@<route>` for a route limited separately
+    pub client: String,
+    pub requests: u64,
+    /// Requests the client can still make at once
+    pub remaining: f64,
+    #[schema(value_type = String, format = DateTime)]
+    pub last_request: DateTime<Utc>,
+}
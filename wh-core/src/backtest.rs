@@ -0,0 +1,222 @@
+use std::collections::BTreeMap;
+
+use chrono::{Duration, NaiveDate};
+use rust_decimal::Decimal;
+
+use crate::price_series::PriceSeries;
+use crate::repository::DeliveryDay;
+use crate::schedule::{is_enabled_at, is_within_operating_hours};
+use crate::test_utils::MockTimeProvider;
+use crate::time_provider::TimeProvider;
+use crate::types::BiddingZone;
+use crate::units::CentsPerKwh;
+use crate::util::storage_date_at;
+
+/// How the heater is scheduled during a backtest.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Strategy {
+    /// The cheapest `hours` long period between `start` and `end`, as served by the API
+    CheapestPeriod { hours: u32, start: u32, end: u32 },
+    /// On for `hours` from `start` every day regardless of the price
+    FixedTime { hours: u32, start: u32 },
+}
+
+impl Strategy {
+    pub fn is_enabled_at<T: TimeProvider>(
+        &self,
+        time_provider: &T,
+        zone: &BiddingZone,
+        pricing: &PriceSeries<CentsPerKwh>,
+    ) -> bool {
+        match *self {
+            Strategy::CheapestPeriod { hours, start, end } => {
+                is_enabled_at(time_provider, zone, pricing, hours, start, end)
+            }
+            Strategy::FixedTime { hours, start } => {
+                let current_time = time_provider.now().with_timezone(&zone.to_tz());
+                hours > 0 && is_within_operating_hours(start, (start + hours) % 24, current_time)
+            }
+        }
+    }
+}
+
+/// Heating of a single delivery day, costs in hundredths of the pricing currency (e.g. c).
+#[derive(Debug, Clone, PartialEq)]
+pub struct DayCost {
+    pub date: NaiveDate,
+    pub hours_on: Decimal,
+    pub cost: Decimal,
+    pub baseline_hours_on: Decimal,
+    pub baseline_cost: Decimal,
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct BacktestReport {
+    pub days: Vec<DayCost>,
+}
+
+impl BacktestReport {
+    pub fn total_cost(&self) -> Decimal {
+        self.days.iter().map(|day| day.cost).sum()
+    }
+
+    pub fn total_baseline_cost(&self) -> Decimal {
+        self.days.iter().map(|day| day.baseline_cost).sum()
+    }
+
+    /// Cost saved compared to the baseline, negative if the strategy was more expensive
+    pub fn savings(&self) -> Decimal {
+        self.total_baseline_cost() - self.total_cost()
+    }
+}
+
+/// Replays historical prices through the same scheduling functions the server uses and
+/// compares the cost of a strategy to a baseline.
+///
+/// Every price slot is evaluated once at its midpoint, as a device polling the API once
+/// per slot would, and only with the prices that had been published by then.
+pub struct Backtest {
+    pub zone: BiddingZone,
+    pub strategy: Strategy,
+    pub baseline: Strategy,
+    /// Power of the heater in kW
+    pub power_kw: Decimal,
+}
+
+impl Backtest {
+    pub fn run(&self, history: &[DeliveryDay]) -> BacktestReport {
+        let days: BTreeMap<NaiveDate, &PriceSeries<CentsPerKwh>> =
+            history.iter().map(|day| (day.date, &day.pricing)).collect();
+
+        let mut report = BacktestReport::default();
+
+        for (date, pricing) in &days {
+            let slot_hours =
+                Decimal::from(pricing.resolution().num_seconds()) / Decimal::from(3600);
+            let energy = self.power_kw * slot_hours;
+
+            let mut day_cost = DayCost {
+                date: *date,
+                hours_on: Decimal::ZERO,
+                cost: Decimal::ZERO,
+                baseline_hours_on: Decimal::ZERO,
+                baseline_cost: Decimal::ZERO,
+            };
+
+            for slot in pricing.iter() {
+                let now = (slot.start + pricing.resolution() / 2).to_utc();
+                let time_provider = MockTimeProvider::new(now);
+
+                // The cheapest period only looks at the current and the next day, and the
+                // next day's prices are only known after they have been published
+                let published_until = storage_date_at(now) + Duration::days(1);
+                let available = PriceSeries::from_slots(
+                    pricing.resolution(),
+                    days.range(*date - Duration::days(1)..=published_until)
+                        .flat_map(|(_, pricing)| pricing.iter())
+                        .map(|slot| (slot.start, slot.price)),
+                );
+
+                let price = slot.price.amount();
+
+                if self
+                    .strategy
+                    .is_enabled_at(&time_provider, &self.zone, &available)
+                {
+                    day_cost.hours_on += slot_hours;
+                    day_cost.cost += price * energy;
+                }
+
+                if self
+                    .baseline
+                    .is_enabled_at(&time_provider, &self.zone, &available)
+                {
+                    day_cost.baseline_hours_on += slot_hours;
+                    day_cost.baseline_cost += price * energy;
+                }
+            }
+
+            report.days.push(day_cost);
+        }
+
+        report
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::TimeZone;
+    use chrono_tz::Europe::Helsinki;
+    use rust_decimal_macros::dec;
+
+    use super::*;
+
+    fn delivery_day(day: u32, cheap_hours: &[i64]) -> DeliveryDay {
+        let midnight = Helsinki.with_ymd_and_hms(2024, 4, day, 0, 0, 0).unwrap();
+
+        DeliveryDay {
+            date: midnight.date_naive(),
+            pricing: PriceSeries::from_slots(
+                Duration::hours(1),
+                (0..24).map(|hour| {
+                    let price = if cheap_hours.contains(&hour) {
+                        dec!(1)
+                    } else {
+                        dec!(10)
+                    };
+                    (
+                        (midnight + Duration::hours(hour)).fixed_offset(),
+                        CentsPerKwh::eur(price),
+                    )
+                }),
+            ),
+        }
+    }
+
+    #[test]
+    fn test_cheapest_period_beats_fixed_time() {
+        let backtest = Backtest {
+            zone: BiddingZone::FI,
+            strategy: Strategy::CheapestPeriod {
+                hours: 2,
+                start: 0,
+                end: 6,
+            },
+            baseline: Strategy::FixedTime { hours: 2, start: 0 },
+            power_kw: dec!(2),
+        };
+
+        let report = backtest.run(&[delivery_day(8, &[3, 4]), delivery_day(9, &[0, 1])]);
+
+        assert_eq!(report.days.len(), 2);
+        assert_eq!(report.days[0].hours_on, dec!(2));
+        // 2 hours at 1 c/kWh with a 2 kW heater
+        assert_eq!(report.days[0].cost, dec!(4));
+        assert_eq!(report.days[0].baseline_cost, dec!(40));
+        assert_eq!(report.days[1].cost, dec!(4));
+        assert_eq!(report.days[1].baseline_cost, dec!(4));
+        assert_eq!(report.savings(), dec!(36));
+    }
+
+    #[test]
+    fn test_fixed_time_crossing_midnight() {
+        let strategy = Strategy::FixedTime {
+            hours: 3,
+            start: 23,
+        };
+        let pricing = PriceSeries::new(Duration::hours(1));
+
+        let enabled_at = |hour| {
+            let now = Helsinki
+                .with_ymd_and_hms(2024, 4, 8, hour, 30, 0)
+                .unwrap()
+                .to_utc();
+            strategy.is_enabled_at(&MockTimeProvider::new(now), &BiddingZone::FI, &pricing)
+        };
+
+        assert!(enabled_at(23));
+        assert!(enabled_at(1));
+        assert!(!enabled_at(2));
+        assert!(!enabled_at(22));
+    }
+}
@@ -0,0 +1,153 @@
+use async_trait::async_trait;
+use aws_sdk_dynamodb::types::{AttributeValue, ReturnValue};
+
+use crate::repository::RepositoryError;
+
+use super::{Device, DeviceRepository};
+
+pub const DEVICE_TABLE_NAME: &str = "waterheater_devices";
+pub const TOKEN_HASH_INDEX_NAME: &str = "token_hash_index";
+
+/// Stores one item per device, keyed by `id`, with the device serialized as JSON in the
+/// `device` attribute. Tokens are looked up through a global secondary index on
+/// `token_hash`.
+pub struct DynamoDeviceRepository {
+    client: aws_sdk_dynamodb::Client,
+    table_name: String,
+}
+
+impl DynamoDeviceRepository {
+    pub fn new(client: aws_sdk_dynamodb::Client, table_name: impl Into<String>) -> Self {
+        DynamoDeviceRepository {
+            client,
+            table_name: table_name.into(),
+        }
+    }
+}
+
+fn parse_device(
+    item: &std::collections::HashMap<String, AttributeValue>,
+) -> Result<Device, RepositoryError> {
+    let device = item
+        .get("device")
+        .and_then(|value| value.as_s().ok())
+        .ok_or(RepositoryError::Data("device missing".to_string()))?;
+
+    Ok(serde_json::from_str(device)?)
+}
+
+#[async_trait]
+impl DeviceRepository for DynamoDeviceRepository {
+    async fn get_device(&self, id: &str) -> Result<Option<Device>, RepositoryError> {
+        let output = self
+            .client
+            .get_item()
+            .table_name(&self.table_name)
+            .key("id", AttributeValue::S(id.to_string()))
+            .send()
+            .await
+            .map_err(|e| Box::new(e.into()))?;
+
+        output.item.as_ref().map(parse_device).transpose()
+    }
+
+    async fn get_device_by_token(
+        &self,
+        token_hash: &str,
+    ) -> Result<Option<Device>, RepositoryError> {
+        let output = self
+            .client
+            .query()
+            .table_name(&self.table_name)
+            .index_name(TOKEN_HASH_INDEX_NAME)
+            .key_condition_expression("token_hash = :token_hash")
+            .expression_attribute_values(":token_hash", AttributeValue::S(token_hash.to_string()))
+            .limit(1)
+            .send()
+            .await
+            .map_err(|e| Box::new(e.into()))?;
+
+        output.items().first().map(parse_device).transpose()
+    }
+
+    async fn store_device(&self, token_hash: &str, device: &Device) -> Result<(), RepositoryError> {
+        self.client
+            .put_item()
+            .table_name(&self.table_name)
+            .item("id", AttributeValue::S(device.id.clone()))
+            .item("token_hash", AttributeValue::S(token_hash.to_string()))
+            .item("device", AttributeValue::S(serde_json::to_string(device)?))
+            .send()
+            .await
+            .map_err(|e| Box::new(e.into()))?;
+
+        Ok(())
+    }
+
+    async fn update_device(&self, device: &Device) -> Result<bool, RepositoryError> {
+        let result = self
+            .client
+            .update_item()
+            .table_name(&self.table_name)
+            .key("id", AttributeValue::S(device.id.clone()))
+            .update_expression("SET device = :device")
+            .condition_expression("attribute_exists(id)")
+            .expression_attribute_values(
+                ":device",
+                AttributeValue::S(serde_json::to_string(device)?),
+            )
+            .send()
+            .await;
+
+        match result {
+            Ok(_) => Ok(true),
+            Err(e)
+                if e.as_service_error()
+                    .is_some_and(|e| e.is_conditional_check_failed_exception()) =>
+            {
+                Ok(false)
+            }
+            Err(e) => Err(RepositoryError::DynamoDb(Box::new(e.into()))),
+        }
+    }
+
+    async fn list_devices(&self) -> Result<Vec<Device>, RepositoryError> {
+        let mut devices = Vec::new();
+        let mut exclusive_start_key = None;
+
+        loop {
+            let output = self
+                .client
+                .scan()
+                .table_name(&self.table_name)
+                .set_exclusive_start_key(exclusive_start_key)
+                .send()
+                .await
+                .map_err(|e| Box::new(e.into()))?;
+
+            for item in output.items() {
+                devices.push(parse_device(item)?);
+            }
+
+            exclusive_start_key = output.last_evaluated_key;
+            if exclusive_start_key.is_none() {
+                devices.sort_by(|a, b| a.id.cmp(&b.id));
+                return Ok(devices);
+            }
+        }
+    }
+
+    async fn delete_device(&self, id: &str) -> Result<bool, RepositoryError> {
+        let output = self
+            .client
+            .delete_item()
+            .table_name(&self.table_name)
+            .key("id", AttributeValue::S(id.to_string()))
+            .return_values(ReturnValue::AllOld)
+            .send()
+            .await
+            .map_err(|e| Box::new(e.into()))?;
+
+        Ok(output.attributes.is_some())
+    }
+}
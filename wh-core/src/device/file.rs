@@ -0,0 +1,222 @@
+use std::io::ErrorKind;
+use std::path::{Path, PathBuf};
+
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+
+use crate::repository::file::io_error;
+use crate::repository::RepositoryError;
+
+use super::{Device, DeviceRepository};
+
+#[derive(Serialize, Deserialize)]
+struct StoredDevice {
+    token_hash: String,
+    device: Device,
+}
+
+/// Stores each device as JSON in `<directory>/devices/<id>.json`, with the id of the
+/// device of each token hash in `<directory>/devices/tokens/<hash>`.
+pub struct FileDeviceRepository {
+    directory: PathBuf,
+}
+
+impl FileDeviceRepository {
+    pub fn new(directory: impl Into<PathBuf>) -> Self {
+        FileDeviceRepository {
+            directory: directory.into().join("devices"),
+        }
+    }
+
+    fn device_path(&self, id: &str) -> PathBuf {
+        self.directory.join(format!("{id}.json"))
+    }
+
+    fn token_path(&self, token_hash: &str) -> PathBuf {
+        self.directory.join("tokens").join(token_hash)
+    }
+
+    fn read_device(&self, id: &str) -> Result<Option<StoredDevice>, RepositoryError> {
+        match read(&self.device_path(id))? {
+            Some(contents) => Ok(Some(serde_json::from_str(&contents)?)),
+            None => Ok(None),
+        }
+    }
+}
+
+fn read(path: &Path) -> Result<Option<String>, RepositoryError> {
+    match std::fs::read_to_string(path) {
+        Ok(contents) => Ok(Some(contents)),
+        Err(e) if e.kind() == ErrorKind::NotFound => Ok(None),
+        Err(e) => Err(io_error(path, e)),
+    }
+}
+
+fn write(path: &Path, contents: &str) -> Result<(), RepositoryError> {
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent).map_err(|e| io_error(parent, e))?;
+    }
+
+    std::fs::write(path, contents).map_err(|e| io_error(path, e))
+}
+
+/// Ids and hashes become file names, so only the characters they are generated from
+/// are accepted
+fn is_valid_name(name: &str) -> bool {
+    !name.is_empty() && name.chars().all(|c| c.is_ascii_alphanumeric())
+}
+
+#[async_trait]
+impl DeviceRepository for FileDeviceRepository {
+    async fn get_device(&self, id: &str) -> Result<Option<Device>, RepositoryError> {
+        if !is_valid_name(id) {
+            return Ok(None);
+        }
+
+        Ok(self.read_device(id)?.map(|stored| stored.device))
+    }
+
+    async fn get_device_by_token(
+        &self,
+        token_hash: &str,
+    ) -> Result<Option<Device>, RepositoryError> {
+        if !is_valid_name(token_hash) {
+            return Ok(None);
+        }
+
+        let Some(id) = read(&self.token_path(token_hash))? else {
+            return Ok(None);
+        };
+
+        // The token file is left behind when a device gets a new token
+        Ok(self
+            .read_device(id.trim())?
+            .filter(|stored| stored.token_hash == token_hash)
+            .map(|stored| stored.device))
+    }
+
+    async fn store_device(&self, token_hash: &str, device: &Device) -> Result<(), RepositoryError> {
+        if !is_valid_name(token_hash) || !is_valid_name(&device.id) {
+            return Err(RepositoryError::Data(format!(
+                "Invalid device id {} or token hash",
+                device.id
+            )));
+        }
+
+        let stored = StoredDevice {
+            token_hash: token_hash.to_string(),
+            device: device.clone(),
+        };
+
+        write(&self.token_path(token_hash), &device.id)?;
+        write(
+            &self.device_path(&device.id),
+            &serde_json::to_string(&stored)?,
+        )
+    }
+
+    async fn update_device(&self, device: &Device) -> Result<bool, RepositoryError> {
+        if !is_valid_name(&device.id) {
+            return Ok(false);
+        }
+
+        let Some(mut stored) = self.read_device(&device.id)? else {
+            return Ok(false);
+        };
+        stored.device = device.clone();
+
+        write(
+            &self.device_path(&device.id),
+            &serde_json::to_string(&stored)?,
+        )?;
+
+        Ok(true)
+    }
+
+    async fn list_devices(&self) -> Result<Vec<Device>, RepositoryError> {
+        let entries = match std::fs::read_dir(&self.directory) {
+            Ok(entries) => entries,
+            Err(e) if e.kind() == ErrorKind::NotFound => return Ok(Vec::new()),
+            Err(e) => return Err(io_error(&self.directory, e)),
+        };
+
+        let mut devices = Vec::new();
+        for entry in entries {
+            let path = entry.map_err(|e| io_error(&self.directory, e))?.path();
+            if path
+                .extension()
+                .is_some_and(|extension| extension == "json")
+            {
+                if let Some(contents) = read(&path)? {
+                    devices.push(serde_json::from_str::<StoredDevice>(&contents)?.device);
+                }
+            }
+        }
+        devices.sort_by(|a, b| a.id.cmp(&b.id));
+
+        Ok(devices)
+    }
+
+    async fn delete_device(&self, id: &str) -> Result<bool, RepositoryError> {
+        if !is_valid_name(id) {
+            return Ok(false);
+        }
+
+        let Some(stored) = self.read_device(id)? else {
+            return Ok(false);
+        };
+
+        for path in [self.token_path(&stored.token_hash), self.device_path(id)] {
+            match std::fs::remove_file(&path) {
+                Ok(()) => {}
+                Err(e) if e.kind() == ErrorKind::NotFound => {}
+                Err(e) => return Err(io_error(&path, e)),
+            }
+        }
+
+        Ok(true)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::{TimeZone, Utc};
+
+    use crate::types::BiddingZone;
+
+    use super::*;
+
+    #[tokio::test]
+    async fn test_store_and_get_device() {
+        let directory =
+            std::env::temp_dir().join(format!("wh-core-file-devices-{}", std::process::id()));
+        let repository = FileDeviceRepository::new(&directory);
+        let device = Device {
+            id: "a1b2c3d4e5f6".to_string(),
+            name: Some("Boiler".to_string()),
+            zone: Some(BiddingZone::FI),
+            profile: None,
+            registered_at: Utc.with_ymd_and_hms(2024, 4, 8, 12, 0, 0).unwrap(),
+        };
+
+        repository.store_device("0f1e2d", &device).await.unwrap();
+
+        let by_token = repository.get_device_by_token("0f1e2d").await.unwrap();
+        let by_id = repository.get_device(&device.id).await.unwrap();
+        let unknown = repository.get_device_by_token("abcdef").await.unwrap();
+        let traversal = repository.get_device("../devices").await.unwrap();
+        let listed = repository.list_devices().await.unwrap();
+        let deleted = repository.delete_device(&device.id).await.unwrap();
+        let after_delete = repository.get_device_by_token("0f1e2d").await.unwrap();
+
+        std::fs::remove_dir_all(&directory).unwrap();
+
+        assert_eq!(by_token, Some(device.clone()));
+        assert_eq!(by_id, Some(device.clone()));
+        assert_eq!(unknown, None);
+        assert_eq!(traversal, None);
+        assert_eq!(listed, vec![device]);
+        assert!(deleted);
+        assert_eq!(after_delete, None);
+    }
+}
@@ -0,0 +1,75 @@
+use std::collections::HashMap;
+use std::sync::RwLock;
+
+use async_trait::async_trait;
+
+use crate::repository::RepositoryError;
+
+use super::{Device, DeviceRepository};
+
+/// Keeps devices in memory only, for tests and running without any database.
+#[derive(Default)]
+pub struct InMemoryDeviceRepository {
+    devices: RwLock<HashMap<String, (String, Device)>>,
+}
+
+impl InMemoryDeviceRepository {
+    pub fn new() -> Self {
+        InMemoryDeviceRepository::default()
+    }
+}
+
+#[async_trait]
+impl DeviceRepository for InMemoryDeviceRepository {
+    async fn get_device(&self, id: &str) -> Result<Option<Device>, RepositoryError> {
+        let devices = self.devices.read().unwrap();
+
+        Ok(devices.get(id).map(|(_, device)| device.clone()))
+    }
+
+    async fn get_device_by_token(
+        &self,
+        token_hash: &str,
+    ) -> Result<Option<Device>, RepositoryError> {
+        let devices = self.devices.read().unwrap();
+
+        Ok(devices
+            .values()
+            .find(|(hash, _)| hash == token_hash)
+            .map(|(_, device)| device.clone()))
+    }
+
+    async fn store_device(&self, token_hash: &str, device: &Device) -> Result<(), RepositoryError> {
+        let mut devices = self.devices.write().unwrap();
+        devices.insert(device.id.clone(), (token_hash.to_string(), device.clone()));
+
+        Ok(())
+    }
+
+    async fn update_device(&self, device: &Device) -> Result<bool, RepositoryError> {
+        let mut devices = self.devices.write().unwrap();
+
+        Ok(match devices.get_mut(&device.id) {
+            Some((_, stored)) => {
+                *stored = device.clone();
+                true
+            }
+            None => false,
+        })
+    }
+
+    async fn list_devices(&self) -> Result<Vec<Device>, RepositoryError> {
+        let devices = self.devices.read().unwrap();
+
+        let mut devices: Vec<Device> = devices.values().map(|(_, device)| device.clone()).collect();
+        devices.sort_by(|a, b| a.id.cmp(&b.id));
+
+        Ok(devices)
+    }
+
+    async fn delete_device(&self, id: &str) -> Result<bool, RepositoryError> {
+        let mut devices = self.devices.write().unwrap();
+
+        Ok(devices.remove(id).is_some())
+    }
+}
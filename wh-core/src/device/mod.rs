@@ -0,0 +1,137 @@
+//! Devices registered with the API, such as Shelly relays, identified by an opaque token.
+//!
+//! Only a hash of the token is stored. Devices are looked up by that hash on every
+//! request sent with a token, and by their short public id.
+
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use rust_decimal::Decimal;
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+use utoipa::ToSchema;
+
+use crate::repository::RepositoryError;
+use crate::schedule::{HeatingPeriod, PeriodError};
+use crate::tariff::Tariff;
+use crate::types::BiddingZone;
+
+#[cfg(feature = "dynamodb")]
+pub mod dynamodb;
+pub mod file;
+pub mod memory;
+#[cfg(feature = "sqlite")]
+pub mod sqlite;
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Device {
+    /// Short public id, safe to show in logs and URLs unlike the token
+    pub id: String,
+    pub name: Option<String>,
+    pub zone: Option<BiddingZone>,
+    /// What the device is told to do, evaluated in the zone of the device
+    #[serde(default)]
+    pub profile: Option<DeviceProfile>,
+    pub registered_at: DateTime<Utc>,
+}
+
+/// When a device is turned on
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+#[serde(tag = "mode", rename_all = "snake_case")]
+pub enum DeviceMode {
+    /// On during the cheapest `hours` between `start` and `end`, like the v2 API
+    CheapestPeriod {
+        hours: u32,
+        start: u32,
+        end: u32,
+    },
+    /// On whenever the price, tariffs included, is at most `max_price` c/kWh
+    BelowPrice {
+        #[serde(with = "rust_decimal::serde::float")]
+        #[schema(value_type = f64)]
+        max_price: Decimal,
+    },
+    AlwaysOn,
+    AlwaysOff,
+}
+
+/// Settings of a device stored server-side, so that they can be changed without
+/// editing the script on the device
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+pub struct DeviceProfile {
+    #[serde(flatten)]
+    pub mode: DeviceMode,
+    /// Charges added to the spot price before comparing prices
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub tariffs: Vec<Tariff>,
+}
+
+#[derive(Error, Debug, Clone, PartialEq, Eq)]
+pub enum ProfileError {
+    #[error(transparent)]
+    Period(PeriodError),
+    #[error("tariff start must be between 0 and 23, was {1}")]
+    TariffStart(usize, u32),
+    #[error("tariff end must be between 0 and 24, was {1}")]
+    TariffEnd(usize, u32),
+}
+
+impl ProfileError {
+    /// Path of the field in the profile the error is about, e.g. `tariffs[1].end`
+    pub fn field(&self) -> String {
+        match self {
+            ProfileError::Period(error) => error.field().to_string(),
+            ProfileError::TariffStart(index, _) => format!("tariffs[{index}].start"),
+            ProfileError::TariffEnd(index, _) => format!("tariffs[{index}].end"),
+        }
+    }
+}
+
+impl DeviceProfile {
+    pub fn validate(&self) -> Result<(), Vec<ProfileError>> {
+        let mut errors = Vec::new();
+
+        if let DeviceMode::CheapestPeriod { hours, start, end } = self.mode {
+            if let Err(period_errors) = HeatingPeriod::new(hours, start, end) {
+                errors.extend(period_errors.into_iter().map(ProfileError::Period));
+            }
+        }
+
+        for (index, tariff) in self.tariffs.iter().enumerate() {
+            if tariff.start > 23 {
+                errors.push(ProfileError::TariffStart(index, tariff.start));
+            }
+            if tariff.end > 24 {
+                errors.push(ProfileError::TariffEnd(index, tariff.end));
+            }
+        }
+
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            Err(errors)
+        }
+    }
+}
+
+#[async_trait]
+pub trait DeviceRepository: Send + Sync {
+    async fn get_device(&self, id: &str) -> Result<Option<Device>, RepositoryError>;
+
+    async fn get_device_by_token(
+        &self,
+        token_hash: &str,
+    ) -> Result<Option<Device>, RepositoryError>;
+
+    /// Stores a new device or replaces the one with the same id
+    async fn store_device(&self, token_hash: &str, device: &Device) -> Result<(), RepositoryError>;
+
+    /// Replaces the settings of an existing device, keeping its token. Returns false if
+    /// there is no device with the id.
+    async fn update_device(&self, device: &Device) -> Result<bool, RepositoryError>;
+
+    /// Every device, ordered by id
+    async fn list_devices(&self) -> Result<Vec<Device>, RepositoryError>;
+
+    /// Removes the device and its token. Returns false if there is no device with the id.
+    async fn delete_device(&self, id: &str) -> Result<bool, RepositoryError>;
+}
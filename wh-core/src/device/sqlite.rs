@@ -0,0 +1,142 @@
+use std::path::Path;
+use std::sync::Mutex;
+
+use async_trait::async_trait;
+use rusqlite::{params, Connection, OptionalExtension};
+
+use crate::repository::RepositoryError;
+
+use super::{Device, DeviceRepository};
+
+/// Stores one row per device with the device serialized as JSON, so that new settings
+/// don't need a migration.
+pub struct SqliteDeviceRepository {
+    connection: Mutex<Connection>,
+}
+
+impl SqliteDeviceRepository {
+    pub fn open(path: impl AsRef<Path>) -> Result<Self, RepositoryError> {
+        SqliteDeviceRepository::from_connection(Connection::open(path)?)
+    }
+
+    pub fn from_connection(connection: Connection) -> Result<Self, RepositoryError> {
+        connection.execute(
+            "CREATE TABLE IF NOT EXISTS devices (
+                id TEXT PRIMARY KEY,
+                token_hash TEXT NOT NULL UNIQUE,
+                device TEXT NOT NULL
+            )",
+            [],
+        )?;
+
+        Ok(SqliteDeviceRepository {
+            connection: Mutex::new(connection),
+        })
+    }
+
+    fn query_device(&self, sql: &str, value: &str) -> Result<Option<Device>, RepositoryError> {
+        let connection = self.connection.lock().unwrap();
+
+        let device = connection
+            .query_row(sql, params![value], |row| row.get::<_, String>(0))
+            .optional()?;
+
+        Ok(device
+            .map(|device| serde_json::from_str(&device))
+            .transpose()?)
+    }
+}
+
+#[async_trait]
+impl DeviceRepository for SqliteDeviceRepository {
+    async fn get_device(&self, id: &str) -> Result<Option<Device>, RepositoryError> {
+        self.query_device("SELECT device FROM devices WHERE id = ?1", id)
+    }
+
+    async fn get_device_by_token(
+        &self,
+        token_hash: &str,
+    ) -> Result<Option<Device>, RepositoryError> {
+        self.query_device(
+            "SELECT device FROM devices WHERE token_hash = ?1",
+            token_hash,
+        )
+    }
+
+    async fn store_device(&self, token_hash: &str, device: &Device) -> Result<(), RepositoryError> {
+        let connection = self.connection.lock().unwrap();
+
+        connection.execute(
+            "INSERT OR REPLACE INTO devices (id, token_hash, device) VALUES (?1, ?2, ?3)",
+            params![device.id, token_hash, serde_json::to_string(device)?],
+        )?;
+
+        Ok(())
+    }
+
+    async fn update_device(&self, device: &Device) -> Result<bool, RepositoryError> {
+        let connection = self.connection.lock().unwrap();
+
+        let updated = connection.execute(
+            "UPDATE devices SET device = ?2 WHERE id = ?1",
+            params![device.id, serde_json::to_string(device)?],
+        )?;
+
+        Ok(updated > 0)
+    }
+
+    async fn list_devices(&self) -> Result<Vec<Device>, RepositoryError> {
+        let connection = self.connection.lock().unwrap();
+
+        let mut statement = connection.prepare("SELECT device FROM devices ORDER BY id")?;
+        let rows = statement
+            .query_map([], |row| row.get::<_, String>(0))?
+            .collect::<Result<Vec<_>, _>>()?;
+
+        Ok(rows
+            .iter()
+            .map(|device| serde_json::from_str(device))
+            .collect::<Result<_, _>>()?)
+    }
+
+    async fn delete_device(&self, id: &str) -> Result<bool, RepositoryError> {
+        let connection = self.connection.lock().unwrap();
+
+        let deleted = connection.execute("DELETE FROM devices WHERE id = ?1", params![id])?;
+
+        Ok(deleted > 0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::{TimeZone, Utc};
+
+    use super::*;
+
+    #[tokio::test]
+    async fn test_new_token_replaces_old() {
+        let repository =
+            SqliteDeviceRepository::from_connection(Connection::open_in_memory().unwrap()).unwrap();
+        let device = Device {
+            id: "a1b2c3d4e5f6".to_string(),
+            name: None,
+            zone: None,
+            profile: None,
+            registered_at: Utc.with_ymd_and_hms(2024, 4, 8, 12, 0, 0).unwrap(),
+        };
+
+        repository.store_device("old", &device).await.unwrap();
+        repository.store_device("new", &device).await.unwrap();
+
+        assert_eq!(repository.get_device_by_token("old").await.unwrap(), None);
+        assert_eq!(
+            repository.get_device_by_token("new").await.unwrap(),
+            Some(device.clone())
+        );
+        assert_eq!(
+            repository.get_device(&device.id).await.unwrap(),
+            Some(device)
+        );
+    }
+}
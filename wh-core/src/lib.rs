@@ -1,4 +1,14 @@
+pub mod api;
+pub mod backtest;
+pub mod device;
+pub mod price_series;
+pub mod profile;
+pub mod repository;
+pub mod retry;
+pub mod schedule;
+pub mod tariff;
 pub mod test_utils;
 pub mod time_provider;
 pub mod types;
+pub mod units;
 pub mod util;
@@ -0,0 +1,329 @@
+use std::fmt;
+use std::marker::PhantomData;
+use std::str::FromStr;
+
+use chrono::{DateTime, Duration, FixedOffset};
+use serde::{
+    de::{self, SeqAccess, Visitor},
+    ser::SerializeSeq,
+    Deserialize, Deserializer, Serialize, Serializer,
+};
+
+/// Resolution used when it can not be inferred from the data, e.g. for a series
+/// with a single slot. Day-ahead prices have historically been published per hour.
+pub const DEFAULT_RESOLUTION: Duration = Duration::hours(1);
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Slot<P> {
+    pub start: DateTime<FixedOffset>,
+    pub price: P,
+}
+
+/// Period between two consecutive slots for which there is no price.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Gap {
+    pub start: DateTime<FixedOffset>,
+    pub end: DateTime<FixedOffset>,
+}
+
+/// Prices for consecutive time slots of a fixed length, ordered and indexed by the
+/// start time of the slot.
+///
+/// Missing slots are not filled in, so a window of slots is only considered
+/// contiguous if every slot starts exactly where the previous one ended.
+#[derive(Debug, Clone, PartialEq)]
+pub struct PriceSeries<P> {
+    resolution: Duration,
+    slots: Vec<Slot<P>>,
+}
+
+impl<P> PriceSeries<P> {
+    pub fn new(resolution: Duration) -> Self {
+        PriceSeries {
+            resolution,
+            slots: Vec::new(),
+        }
+    }
+
+    pub fn from_slots<I>(resolution: Duration, slots: I) -> Self
+    where
+        I: IntoIterator<Item = (DateTime<FixedOffset>, P)>,
+    {
+        let mut series = PriceSeries::new(resolution);
+        for (start, price) in slots {
+            series.insert(start, price);
+        }
+        series
+    }
+
+    /// Builds a series using the shortest distance between two slots as the
+    /// resolution, falling back to [`DEFAULT_RESOLUTION`].
+    pub fn with_inferred_resolution<I>(slots: I) -> Self
+    where
+        I: IntoIterator<Item = (DateTime<FixedOffset>, P)>,
+    {
+        let mut series = PriceSeries::from_slots(DEFAULT_RESOLUTION, slots);
+        series.resolution = series
+            .slots
+            .windows(2)
+            .map(|w| w[1].start - w[0].start)
+            .min()
+            .unwrap_or(DEFAULT_RESOLUTION);
+        series
+    }
+
+    /// Inserts a price for the slot starting at `start`, replacing any existing
+    /// price for the same slot.
+    pub fn insert(&mut self, start: DateTime<FixedOffset>, price: P) {
+        match self.slots.binary_search_by(|s| s.start.cmp(&start)) {
+            Ok(index) => self.slots[index].price = price,
+            Err(index) => self.slots.insert(index, Slot { start, price }),
+        }
+    }
+
+    pub fn resolution(&self) -> Duration {
+        self.resolution
+    }
+
+    /// Number of slots needed to cover `duration`, rounded up.
+    pub fn slots_in(&self, duration: Duration) -> usize {
+        let resolution = self.resolution.num_seconds().max(1);
+        let seconds = duration.num_seconds().max(0);
+
+        ((seconds + resolution - 1) / resolution) as usize
+    }
+
+    pub fn len(&self) -> usize {
+        self.slots.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.slots.is_empty()
+    }
+
+    pub fn slots(&self) -> &[Slot<P>] {
+        &self.slots
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &Slot<P>> {
+        self.slots.iter()
+    }
+
+    pub fn get(&self, start: &DateTime<FixedOffset>) -> Option<&P> {
+        self.slots
+            .binary_search_by(|s| s.start.cmp(start))
+            .ok()
+            .map(|index| &self.slots[index].price)
+    }
+
+    /// Returns the slot `at` falls into, if there is a price for it.
+    pub fn slot_at(&self, at: &DateTime<FixedOffset>) -> Option<&Slot<P>> {
+        let index = self.slots.partition_point(|s| s.start <= *at);
+        let slot = self.slots.get(index.checked_sub(1)?)?;
+
+        (*at < self.end_of(slot)).then_some(slot)
+    }
+
+    pub fn end_of(&self, slot: &Slot<P>) -> DateTime<FixedOffset> {
+        slot.start + self.resolution
+    }
+
+    pub fn first_start(&self) -> Option<DateTime<FixedOffset>> {
+        self.slots.first().map(|s| s.start)
+    }
+
+    pub fn last_end(&self) -> Option<DateTime<FixedOffset>> {
+        self.slots.last().map(|s| self.end_of(s))
+    }
+
+    pub fn gaps(&self) -> Vec<Gap> {
+        self.slots
+            .windows(2)
+            .filter_map(|w| {
+                let end = self.end_of(&w[0]);
+                (end < w[1].start).then_some(Gap {
+                    start: end,
+                    end: w[1].start,
+                })
+            })
+            .collect()
+    }
+
+    pub fn has_gaps(&self) -> bool {
+        !self.gaps().is_empty()
+    }
+
+    /// Iterates over every run of `size` slots that has no gaps in it.
+    pub fn contiguous_windows(&self, size: usize) -> impl Iterator<Item = &[Slot<P>]> {
+        // `windows` panics on a zero size, an empty slice yields nothing instead
+        let slots = if size == 0 { &[][..] } else { &self.slots[..] };
+
+        slots.windows(size.max(1)).filter(move |window| {
+            window
+                .windows(2)
+                .all(|pair| pair[1].start - pair[0].start == self.resolution)
+        })
+    }
+
+    /// Returns a new series with the slots matching `predicate`, keeping the resolution.
+    pub fn filter<F>(&self, mut predicate: F) -> Self
+    where
+        P: Clone,
+        F: FnMut(&Slot<P>) -> bool,
+    {
+        PriceSeries {
+            resolution: self.resolution,
+            slots: self
+                .slots
+                .iter()
+                .filter(|s| predicate(s))
+                .cloned()
+                .collect(),
+        }
+    }
+
+    pub fn map<Q, F>(&self, mut f: F) -> PriceSeries<Q>
+    where
+        F: FnMut(&P) -> Q,
+    {
+        PriceSeries {
+            resolution: self.resolution,
+            slots: self
+                .slots
+                .iter()
+                .map(|s| Slot {
+                    start: s.start,
+                    price: f(&s.price),
+                })
+                .collect(),
+        }
+    }
+}
+
+/// Serialized as a sequence of `[start, price]` pairs, which is the format the pricing
+/// data has been stored in from the start.
+impl<P: Serialize> Serialize for PriceSeries<P> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut seq = serializer.serialize_seq(Some(self.slots.len()))?;
+        for slot in &self.slots {
+            seq.serialize_element(&(slot.start.to_rfc3339(), &slot.price))?;
+        }
+        seq.end()
+    }
+}
+
+impl<'de, P: Deserialize<'de>> Deserialize<'de> for PriceSeries<P> {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        struct SeriesVisitor<P>(PhantomData<P>);
+
+        impl<'de, P: Deserialize<'de>> Visitor<'de> for SeriesVisitor<P> {
+            type Value = PriceSeries<P>;
+
+            fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+                write!(f, "a sequence of [start, price] pairs")
+            }
+
+            fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<Self::Value, A::Error> {
+                let mut slots = Vec::with_capacity(seq.size_hint().unwrap_or_default());
+
+                while let Some((start, price)) = seq.next_element::<(String, P)>()? {
+                    let start =
+                        DateTime::<FixedOffset>::from_str(&start).map_err(de::Error::custom)?;
+                    slots.push((start, price));
+                }
+
+                Ok(PriceSeries::with_inferred_resolution(slots))
+            }
+        }
+
+        deserializer.deserialize_seq(SeriesVisitor(PhantomData))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::TimeZone;
+
+    use super::*;
+
+    fn at(day: u32, hour: u32) -> DateTime<FixedOffset> {
+        FixedOffset::east_opt(0)
+            .unwrap()
+            .with_ymd_and_hms(2024, 4, day, hour, 0, 0)
+            .unwrap()
+    }
+
+    #[test]
+    fn test_insert_keeps_slots_ordered() {
+        let series = PriceSeries::from_slots(
+            Duration::hours(1),
+            [
+                (at(8, 2), 0.3),
+                (at(8, 0), 0.1),
+                (at(8, 1), 0.2),
+                (at(8, 0), 0.4),
+            ],
+        );
+
+        assert_eq!(series.len(), 3);
+        assert_eq!(series.first_start(), Some(at(8, 0)));
+        assert_eq!(series.get(&at(8, 0)), Some(&0.4));
+        assert_eq!(series.last_end(), Some(at(8, 3)));
+    }
+
+    #[test]
+    fn test_gaps() {
+        let series = PriceSeries::from_slots(
+            Duration::hours(1),
+            [(at(8, 22), 0.1), (at(8, 23), 0.1), (at(9, 2), 0.1)],
+        );
+
+        assert_eq!(
+            series.gaps(),
+            vec![Gap {
+                start: at(9, 0),
+                end: at(9, 2)
+            }]
+        );
+    }
+
+    #[test]
+    fn test_contiguous_windows_skip_gaps() {
+        let series = PriceSeries::from_slots(
+            Duration::hours(1),
+            [
+                (at(8, 0), 0.1),
+                (at(8, 1), 0.1),
+                (at(8, 3), 0.1),
+                (at(8, 4), 0.1),
+            ],
+        );
+
+        let starts: Vec<_> = series.contiguous_windows(2).map(|w| w[0].start).collect();
+
+        assert_eq!(starts, vec![at(8, 0), at(8, 3)]);
+        assert_eq!(series.contiguous_windows(3).count(), 0);
+        assert_eq!(series.contiguous_windows(0).count(), 0);
+    }
+
+    #[test]
+    fn test_slot_at() {
+        let series =
+            PriceSeries::from_slots(Duration::hours(1), [(at(8, 0), 0.1), (at(8, 2), 0.2)]);
+
+        let half_past = at(8, 0) + Duration::minutes(30);
+        assert_eq!(series.slot_at(&half_past).map(|s| s.price), Some(0.1));
+        assert!(series.slot_at(&at(8, 1)).is_none());
+        assert!(series.slot_at(&at(8, 3)).is_none());
+    }
+
+    #[test]
+    fn test_deserialize_infers_resolution() {
+        let json = r#"[["2024-04-08T00:00:00+03:00",0.1],["2024-04-08T00:15:00+03:00",0.2]]"#;
+
+        let series: PriceSeries<f32> = serde_json::from_str(json).unwrap();
+
+        assert_eq!(series.resolution(), Duration::minutes(15));
+        assert_eq!(series.len(), 2);
+    }
+}
@@ -0,0 +1,162 @@
+use std::collections::{BTreeMap, HashMap};
+
+use chrono::{DateTime, Datelike, FixedOffset, NaiveDate, TimeZone, Timelike, Weekday};
+use chrono_tz::Tz;
+use rust_decimal::Decimal;
+
+use crate::price_series::{PriceSeries, DEFAULT_RESOLUTION};
+use crate::units::CentsPerKwh;
+
+/// Typical price of each hour of the week in a zone, averaged over historical pricing.
+///
+/// Used in place of the published pricing when it is not available, e.g. during an
+/// API outage or when the market has been decoupled.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct PriceProfile {
+    weekly: HashMap<(Weekday, u32), CentsPerKwh>,
+    /// Averages over all weekdays, for hours of the week missing from the history
+    daily: HashMap<u32, CentsPerKwh>,
+}
+
+fn average(prices: Vec<CentsPerKwh>) -> Option<CentsPerKwh> {
+    let count = Decimal::from(prices.len());
+    let total = CentsPerKwh::checked_sum(prices)?;
+
+    Some(CentsPerKwh::new(total.amount() / count, total.currency()))
+}
+
+impl PriceProfile {
+    /// Builds the profile from `history` in local time of `tz`. The history may overlap,
+    /// a later series replaces the price of a slot from an earlier one.
+    pub fn from_history<'a, I>(tz: &Tz, history: I) -> Self
+    where
+        I: IntoIterator<Item = &'a PriceSeries<CentsPerKwh>>,
+    {
+        let mut slots: BTreeMap<DateTime<FixedOffset>, CentsPerKwh> = BTreeMap::new();
+        for series in history {
+            for slot in series.iter() {
+                slots.insert(slot.start, slot.price);
+            }
+        }
+
+        let mut weekly: HashMap<(Weekday, u32), Vec<CentsPerKwh>> = HashMap::new();
+        let mut daily: HashMap<u32, Vec<CentsPerKwh>> = HashMap::new();
+
+        for (start, price) in slots {
+            let local = start.with_timezone(tz);
+            weekly
+                .entry((local.weekday(), local.hour()))
+                .or_default()
+                .push(price);
+            daily.entry(local.hour()).or_default().push(price);
+        }
+
+        PriceProfile {
+            weekly: weekly
+                .into_iter()
+                .filter_map(|(key, prices)| Some((key, average(prices)?)))
+                .collect(),
+            daily: daily
+                .into_iter()
+                .filter_map(|(hour, prices)| Some((hour, average(prices)?)))
+                .collect(),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.daily.is_empty()
+    }
+
+    pub fn price_at(&self, weekday: Weekday, hour: u32) -> Option<CentsPerKwh> {
+        self.weekly
+            .get(&(weekday, hour))
+            .or_else(|| self.daily.get(&hour))
+            .copied()
+    }
+
+    /// Hourly pricing for `days` in local time of `tz` following the profile.
+    pub fn to_series<I>(&self, tz: &Tz, days: I) -> PriceSeries<CentsPerKwh>
+    where
+        I: IntoIterator<Item = NaiveDate>,
+    {
+        let slots = days
+            .into_iter()
+            .flat_map(|day| (0..24).map(move |hour| (day, hour)))
+            .filter_map(|(day, hour)| {
+                let price = self.price_at(day.weekday(), hour)?;
+                let start = tz
+                    .from_local_datetime(&day.and_hms_opt(hour, 0, 0)?)
+                    .earliest()?;
+
+                Some((start.fixed_offset(), price))
+            });
+
+        PriceSeries::from_slots(DEFAULT_RESOLUTION, slots)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::Duration;
+    use chrono_tz::Europe::Helsinki;
+    use rust_decimal_macros::dec;
+
+    use super::*;
+
+    fn day(day: u32, price: impl Fn(u32) -> Decimal) -> PriceSeries<CentsPerKwh> {
+        let midnight = Helsinki.with_ymd_and_hms(2024, 4, day, 0, 0, 0).unwrap();
+
+        PriceSeries::from_slots(
+            Duration::hours(1),
+            (0..24).map(|hour| {
+                (
+                    (midnight + Duration::hours(i64::from(hour))).fixed_offset(),
+                    CentsPerKwh::eur(price(hour)),
+                )
+            }),
+        )
+    }
+
+    #[test]
+    fn test_profile_averages_by_weekday_and_hour() {
+        // 2024-04-01 and 2024-04-08 are Mondays, 2024-04-02 a Tuesday
+        let history = [
+            day(1, |_| dec!(2)),
+            day(2, |_| dec!(10)),
+            day(8, |hour| if hour == 3 { dec!(4) } else { dec!(2) }),
+        ];
+
+        let profile = PriceProfile::from_history(&Helsinki, &history);
+
+        assert_eq!(
+            profile.price_at(Weekday::Mon, 3),
+            Some(CentsPerKwh::eur(dec!(3)))
+        );
+        assert_eq!(
+            profile.price_at(Weekday::Tue, 3),
+            Some(CentsPerKwh::eur(dec!(10)))
+        );
+        // No history for Wednesdays, the average of the hour over all days is used
+        assert_eq!(
+            profile.price_at(Weekday::Wed, 0),
+            Some(CentsPerKwh::eur(dec!(14) / dec!(3)))
+        );
+    }
+
+    #[test]
+    fn test_to_series() {
+        let profile = PriceProfile::from_history(&Helsinki, &[day(1, Decimal::from)]);
+
+        let monday = NaiveDate::from_ymd_opt(2024, 4, 15).unwrap();
+        let series = profile.to_series(&Helsinki, [monday, monday.succ_opt().unwrap()]);
+
+        let five = Helsinki
+            .with_ymd_and_hms(2024, 4, 16, 5, 0, 0)
+            .unwrap()
+            .fixed_offset();
+
+        assert_eq!(series.len(), 48);
+        assert!(!series.has_gaps());
+        assert_eq!(series.get(&five), Some(&CentsPerKwh::eur(dec!(5))));
+    }
+}
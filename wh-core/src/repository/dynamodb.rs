@@ -0,0 +1,260 @@
+use std::collections::HashMap;
+
+use async_trait::async_trait;
+use aws_sdk_dynamodb::types::AttributeValue;
+use chrono::NaiveDate;
+use rust_decimal::Decimal;
+
+use crate::price_series::PriceSeries;
+use crate::types::BiddingZone;
+use crate::units::{CentsPerKwh, Currency};
+
+use super::{DeliveryDay, PricingRepository, RepositoryError};
+
+pub const PRICING_TABLE_NAME: &str = "electricity_pricing";
+pub const PRICE_ARCHIVE_TABLE_NAME: &str = "electricity_price_archive";
+
+/// Stores one item per country and date, with the prices of the day serialized as a
+/// JSON list of `[start, amount]` pairs in the `pricing_data` attribute. Delivery days
+/// are archived with the same item layout in a separate table, keyed by the bidding zone
+/// instead of the country as a country may have several zones.
+pub struct DynamoPricingRepository {
+    client: aws_sdk_dynamodb::Client,
+    table_name: String,
+    archive_table_name: String,
+}
+
+impl DynamoPricingRepository {
+    pub fn new(client: aws_sdk_dynamodb::Client, table_name: impl Into<String>) -> Self {
+        DynamoPricingRepository {
+            client,
+            table_name: table_name.into(),
+            archive_table_name: PRICE_ARCHIVE_TABLE_NAME.into(),
+        }
+    }
+
+    pub fn with_archive_table(mut self, archive_table_name: impl Into<String>) -> Self {
+        self.archive_table_name = archive_table_name.into();
+        self
+    }
+
+    async fn put_pricing(
+        &self,
+        table_name: &str,
+        key: (&str, String),
+        date: NaiveDate,
+        pricing: &PriceSeries<CentsPerKwh>,
+    ) -> Result<(), RepositoryError> {
+        self.client
+            .put_item()
+            .table_name(table_name)
+            .set_item(Some(pricing_item(key, date, pricing)?))
+            .send()
+            .await
+            .map_err(|e| Box::new(e.into()))?;
+
+        Ok(())
+    }
+}
+
+fn country_key(zone: &BiddingZone) -> (&'static str, String) {
+    ("country", zone.to_country_string())
+}
+
+fn archive_key(zone: &BiddingZone) -> (&'static str, String) {
+    ("zone", zone.to_string())
+}
+
+fn pricing_item(
+    (key_name, key): (&str, String),
+    date: NaiveDate,
+    pricing: &PriceSeries<CentsPerKwh>,
+) -> Result<HashMap<String, AttributeValue>, RepositoryError> {
+    let currency = pricing
+        .iter()
+        .next()
+        .map(|slot| slot.price.currency())
+        .unwrap_or(Currency::Eur);
+
+    Ok(HashMap::from([
+        (key_name.to_string(), AttributeValue::S(key)),
+        ("date".to_string(), AttributeValue::S(date.to_string())),
+        (
+            "pricing_data".to_string(),
+            AttributeValue::S(serde_json::to_string(&pricing.map(|p| p.amount()))?),
+        ),
+        (
+            "unit".to_string(),
+            AttributeValue::S(CentsPerKwh::UNIT.to_string()),
+        ),
+        (
+            "currency".to_string(),
+            AttributeValue::S(currency.to_string()),
+        ),
+    ]))
+}
+
+fn string_attribute<'a>(item: &'a HashMap<String, AttributeValue>, name: &str) -> Option<&'a str> {
+    item.get(name)
+        .and_then(|value| value.as_s().ok())
+        .map(|s| s.as_str())
+}
+
+fn parse_item(
+    item: &HashMap<String, AttributeValue>,
+) -> Result<PriceSeries<CentsPerKwh>, RepositoryError> {
+    let pricing_data = string_attribute(item, "pricing_data")
+        .ok_or(RepositoryError::Data("pricing_data missing".to_string()))?;
+    let pricing = serde_json::from_str::<PriceSeries<Decimal>>(pricing_data)?;
+
+    let currency = match string_attribute(item, "currency") {
+        Some(currency) => currency.parse().map_err(RepositoryError::Data)?,
+        None => Currency::Eur,
+    };
+
+    // Items written before the unit was stored hold €/kWh
+    match string_attribute(item, "unit") {
+        Some(CentsPerKwh::UNIT) => Ok(pricing.map(|amount| CentsPerKwh::new(*amount, currency))),
+        None => Ok(pricing.map(|amount| CentsPerKwh::new(amount * Decimal::ONE_HUNDRED, currency))),
+        Some(unit) => Err(RepositoryError::Data(format!(
+            "Unknown pricing unit: {unit}"
+        ))),
+    }
+}
+
+#[async_trait]
+impl PricingRepository for DynamoPricingRepository {
+    async fn get_pricing(
+        &self,
+        zone: &BiddingZone,
+        date: NaiveDate,
+    ) -> Result<Option<PriceSeries<CentsPerKwh>>, RepositoryError> {
+        let (key_name, key) = country_key(zone);
+        let get_item_output = self
+            .client
+            .get_item()
+            .table_name(&self.table_name)
+            .key(key_name, AttributeValue::S(key))
+            .key("date", AttributeValue::S(date.to_string()))
+            .send()
+            .await
+            .map_err(|e| Box::new(e.into()))?;
+
+        let Some(item) = get_item_output.item else {
+            return Ok(None);
+        };
+
+        parse_item(&item).map(Some)
+    }
+
+    async fn store_pricing(
+        &self,
+        zone: &BiddingZone,
+        date: NaiveDate,
+        pricing: &PriceSeries<CentsPerKwh>,
+    ) -> Result<(), RepositoryError> {
+        self.put_pricing(&self.table_name, country_key(zone), date, pricing)
+            .await
+    }
+
+    async fn get_delivery_days(
+        &self,
+        zone: &BiddingZone,
+        from: NaiveDate,
+        to: NaiveDate,
+    ) -> Result<Vec<DeliveryDay>, RepositoryError> {
+        let (key_name, key) = archive_key(zone);
+        let mut days = Vec::new();
+        let mut exclusive_start_key = None;
+
+        loop {
+            let output = self
+                .client
+                .query()
+                .table_name(&self.archive_table_name)
+                .key_condition_expression("#key = :key AND #date BETWEEN :from AND :to")
+                .expression_attribute_names("#key", key_name)
+                .expression_attribute_names("#date", "date")
+                .expression_attribute_values(":key", AttributeValue::S(key.clone()))
+                .expression_attribute_values(":from", AttributeValue::S(from.to_string()))
+                .expression_attribute_values(":to", AttributeValue::S(to.to_string()))
+                .set_exclusive_start_key(exclusive_start_key)
+                .send()
+                .await
+                .map_err(|e| Box::new(e.into()))?;
+
+            for item in output.items() {
+                let date = string_attribute(item, "date")
+                    .ok_or(RepositoryError::Data("date missing".to_string()))?
+                    .parse()
+                    .map_err(|e: chrono::ParseError| RepositoryError::Data(e.to_string()))?;
+
+                days.push(DeliveryDay {
+                    date,
+                    pricing: parse_item(item)?,
+                });
+            }
+
+            exclusive_start_key = output.last_evaluated_key;
+            if exclusive_start_key.is_none() {
+                return Ok(days);
+            }
+        }
+    }
+
+    async fn store_delivery_day(
+        &self,
+        zone: &BiddingZone,
+        day: &DeliveryDay,
+    ) -> Result<(), RepositoryError> {
+        self.put_pricing(
+            &self.archive_table_name,
+            archive_key(zone),
+            day.date,
+            &day.pricing,
+        )
+        .await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::{Duration, TimeZone, Utc};
+    use rust_decimal_macros::dec;
+
+    use super::*;
+
+    fn pricing(price: Decimal) -> PriceSeries<CentsPerKwh> {
+        let midnight = Utc.with_ymd_and_hms(2024, 4, 8, 0, 0, 0).unwrap();
+
+        PriceSeries::from_slots(
+            Duration::hours(1),
+            [(midnight.fixed_offset(), CentsPerKwh::eur(price))],
+        )
+    }
+
+    #[test]
+    fn test_archive_keeps_zones_of_a_country_apart() {
+        let date = NaiveDate::from_ymd_opt(2024, 4, 8).unwrap();
+        let zones = [(BiddingZone::SE1, dec!(1)), (BiddingZone::SE4, dec!(4))];
+
+        // The archive table as items by their primary key
+        let mut table = HashMap::new();
+        for (zone, price) in zones {
+            let item = pricing_item(archive_key(&zone), date, &pricing(price)).unwrap();
+            let key = (
+                string_attribute(&item, "zone").unwrap().to_string(),
+                string_attribute(&item, "date").unwrap().to_string(),
+            );
+            table.insert(key, item);
+        }
+
+        assert_eq!(table.len(), 2);
+        for (zone, price) in zones {
+            let (_, key) = archive_key(&zone);
+            let item = &table[&(key, date.to_string())];
+
+            assert_eq!(parse_item(item).unwrap(), pricing(price));
+        }
+    }
+}
@@ -0,0 +1,159 @@
+use std::io::ErrorKind;
+use std::path::{Path, PathBuf};
+
+use async_trait::async_trait;
+use chrono::NaiveDate;
+
+use crate::price_series::PriceSeries;
+use crate::types::BiddingZone;
+use crate::units::{CentsPerKwh, Currency};
+
+use super::{DeliveryDay, PricingRepository, RepositoryError};
+
+/// Stores the pricing of each zone and date as a JSON file in `<directory>/<zone>/<date>.json`,
+/// using the same `[start, amount]` pairs as the DynamoDB items. Amounts are in c/kWh.
+/// Delivery days are archived in `<directory>/archive/<zone>/<date>.json`.
+pub struct FilePricingRepository {
+    directory: PathBuf,
+    currency: Currency,
+}
+
+impl FilePricingRepository {
+    pub fn new(directory: impl Into<PathBuf>) -> Self {
+        FilePricingRepository {
+            directory: directory.into(),
+            currency: Currency::Eur,
+        }
+    }
+
+    fn path(&self, zone: &BiddingZone, date: NaiveDate) -> PathBuf {
+        self.directory
+            .join(zone.to_string())
+            .join(format!("{date}.json"))
+    }
+
+    fn archive_path(&self, zone: &BiddingZone, date: NaiveDate) -> PathBuf {
+        self.directory
+            .join("archive")
+            .join(zone.to_string())
+            .join(format!("{date}.json"))
+    }
+
+    fn read(&self, path: &Path) -> Result<Option<PriceSeries<CentsPerKwh>>, RepositoryError> {
+        let contents = match std::fs::read_to_string(path) {
+            Ok(contents) => contents,
+            Err(e) if e.kind() == ErrorKind::NotFound => return Ok(None),
+            Err(e) => return Err(io_error(path, e)),
+        };
+
+        let pricing = serde_json::from_str::<PriceSeries<rust_decimal::Decimal>>(&contents)?;
+
+        Ok(Some(
+            pricing.map(|amount| CentsPerKwh::new(*amount, self.currency)),
+        ))
+    }
+
+    fn write(
+        &self,
+        path: &Path,
+        pricing: &PriceSeries<CentsPerKwh>,
+    ) -> Result<(), RepositoryError> {
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent).map_err(|e| io_error(parent, e))?;
+        }
+
+        let contents = serde_json::to_string(&pricing.map(|p| p.amount()))?;
+
+        std::fs::write(path, contents).map_err(|e| io_error(path, e))
+    }
+}
+
+pub(crate) fn io_error(path: &Path, e: std::io::Error) -> RepositoryError {
+    RepositoryError::Data(format!("{}: {}", path.display(), e))
+}
+
+#[async_trait]
+impl PricingRepository for FilePricingRepository {
+    async fn get_pricing(
+        &self,
+        zone: &BiddingZone,
+        date: NaiveDate,
+    ) -> Result<Option<PriceSeries<CentsPerKwh>>, RepositoryError> {
+        self.read(&self.path(zone, date))
+    }
+
+    async fn store_pricing(
+        &self,
+        zone: &BiddingZone,
+        date: NaiveDate,
+        pricing: &PriceSeries<CentsPerKwh>,
+    ) -> Result<(), RepositoryError> {
+        self.write(&self.path(zone, date), pricing)
+    }
+
+    async fn get_delivery_days(
+        &self,
+        zone: &BiddingZone,
+        from: NaiveDate,
+        to: NaiveDate,
+    ) -> Result<Vec<DeliveryDay>, RepositoryError> {
+        let mut days = Vec::new();
+
+        for date in from.iter_days().take_while(|date| *date <= to) {
+            if let Some(pricing) = self.read(&self.archive_path(zone, date))? {
+                days.push(DeliveryDay { date, pricing });
+            }
+        }
+
+        Ok(days)
+    }
+
+    async fn store_delivery_day(
+        &self,
+        zone: &BiddingZone,
+        day: &DeliveryDay,
+    ) -> Result<(), RepositoryError> {
+        self.write(&self.archive_path(zone, day.date), &day.pricing)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::{Duration, FixedOffset, TimeZone};
+    use rust_decimal_macros::dec;
+
+    use super::*;
+
+    #[tokio::test]
+    async fn test_store_and_get_pricing() {
+        let directory =
+            std::env::temp_dir().join(format!("wh-core-file-repo-{}", std::process::id()));
+        let repository = FilePricingRepository::new(&directory);
+        let date = NaiveDate::from_ymd_opt(2024, 4, 8).unwrap();
+        let start = FixedOffset::east_opt(3 * 3600)
+            .unwrap()
+            .with_ymd_and_hms(2024, 4, 8, 0, 0, 0)
+            .unwrap();
+        let pricing =
+            PriceSeries::from_slots(Duration::hours(1), [(start, CentsPerKwh::eur(dec!(3.843)))]);
+
+        repository
+            .store_pricing(&BiddingZone::FI, date, &pricing)
+            .await
+            .unwrap();
+
+        let stored = repository
+            .get_pricing(&BiddingZone::FI, date)
+            .await
+            .unwrap();
+        let missing = repository
+            .get_pricing(&BiddingZone::SE1, date)
+            .await
+            .unwrap();
+
+        std::fs::remove_dir_all(&directory).unwrap();
+
+        assert_eq!(stored, Some(pricing));
+        assert_eq!(missing, None);
+    }
+}
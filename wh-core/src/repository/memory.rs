@@ -0,0 +1,172 @@
+use std::collections::{BTreeMap, HashMap};
+use std::sync::RwLock;
+
+use async_trait::async_trait;
+use chrono::NaiveDate;
+
+use crate::price_series::PriceSeries;
+use crate::types::BiddingZone;
+use crate::units::CentsPerKwh;
+
+use super::{DeliveryDay, PricingRepository, RepositoryError};
+
+/// Keeps pricing in memory only, for tests and running without any database.
+#[derive(Default)]
+pub struct InMemoryPricingRepository {
+    pricing: RwLock<HashMap<(BiddingZone, NaiveDate), PriceSeries<CentsPerKwh>>>,
+    archive: RwLock<BTreeMap<(BiddingZone, NaiveDate), PriceSeries<CentsPerKwh>>>,
+}
+
+impl InMemoryPricingRepository {
+    pub fn new() -> Self {
+        InMemoryPricingRepository::default()
+    }
+}
+
+#[async_trait]
+impl PricingRepository for InMemoryPricingRepository {
+    async fn get_pricing(
+        &self,
+        zone: &BiddingZone,
+        date: NaiveDate,
+    ) -> Result<Option<PriceSeries<CentsPerKwh>>, RepositoryError> {
+        let pricing = self.pricing.read().unwrap();
+
+        Ok(pricing.get(&(*zone, date)).cloned())
+    }
+
+    async fn store_pricing(
+        &self,
+        zone: &BiddingZone,
+        date: NaiveDate,
+        pricing: &PriceSeries<CentsPerKwh>,
+    ) -> Result<(), RepositoryError> {
+        self.pricing
+            .write()
+            .unwrap()
+            .insert((*zone, date), pricing.clone());
+
+        Ok(())
+    }
+
+    async fn get_delivery_days(
+        &self,
+        zone: &BiddingZone,
+        from: NaiveDate,
+        to: NaiveDate,
+    ) -> Result<Vec<DeliveryDay>, RepositoryError> {
+        if from > to {
+            return Ok(Vec::new());
+        }
+
+        let archive = self.archive.read().unwrap();
+
+        Ok(archive
+            .range((*zone, from)..=(*zone, to))
+            .map(|((_, date), pricing)| DeliveryDay {
+                date: *date,
+                pricing: pricing.clone(),
+            })
+            .collect())
+    }
+
+    async fn store_delivery_day(
+        &self,
+        zone: &BiddingZone,
+        day: &DeliveryDay,
+    ) -> Result<(), RepositoryError> {
+        self.archive
+            .write()
+            .unwrap()
+            .insert((*zone, day.date), day.pricing.clone());
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::{Duration, FixedOffset, TimeZone};
+    use rust_decimal_macros::dec;
+
+    use super::*;
+
+    #[tokio::test]
+    async fn test_store_and_get_pricing() {
+        let repository = InMemoryPricingRepository::new();
+        let date = NaiveDate::from_ymd_opt(2024, 4, 8).unwrap();
+        let start = FixedOffset::east_opt(3 * 3600)
+            .unwrap()
+            .with_ymd_and_hms(2024, 4, 8, 0, 0, 0)
+            .unwrap();
+        let pricing =
+            PriceSeries::from_slots(Duration::hours(1), [(start, CentsPerKwh::eur(dec!(3.843)))]);
+
+        repository
+            .store_pricing(&BiddingZone::FI, date, &pricing)
+            .await
+            .unwrap();
+
+        let stored = repository
+            .get_pricing(&BiddingZone::FI, date)
+            .await
+            .unwrap();
+        let missing = repository
+            .get_pricing(&BiddingZone::SE1, date)
+            .await
+            .unwrap();
+
+        assert_eq!(stored, Some(pricing));
+        assert_eq!(missing, None);
+    }
+
+    #[tokio::test]
+    async fn test_archive_pricing_merges_delivery_days() {
+        let repository = InMemoryPricingRepository::new();
+        let offset = FixedOffset::east_opt(3 * 3600).unwrap();
+        let at = |day, hour| offset.with_ymd_and_hms(2024, 4, day, hour, 0, 0).unwrap();
+
+        // Stored pricing covers the end of one delivery day and the next one
+        let first = PriceSeries::from_slots(
+            Duration::hours(1),
+            [
+                (at(8, 22), CentsPerKwh::eur(dec!(1))),
+                (at(8, 23), CentsPerKwh::eur(dec!(2))),
+                (at(9, 0), CentsPerKwh::eur(dec!(3))),
+            ],
+        );
+        let second = PriceSeries::from_slots(
+            Duration::hours(1),
+            [
+                (at(9, 1), CentsPerKwh::eur(dec!(4))),
+                (at(10, 0), CentsPerKwh::eur(dec!(5))),
+            ],
+        );
+
+        repository
+            .archive_pricing(&BiddingZone::FI, &first)
+            .await
+            .unwrap();
+        repository
+            .archive_pricing(&BiddingZone::FI, &second)
+            .await
+            .unwrap();
+
+        let days = repository
+            .get_delivery_days(
+                &BiddingZone::FI,
+                NaiveDate::from_ymd_opt(2024, 4, 9).unwrap(),
+                NaiveDate::from_ymd_opt(2024, 4, 30).unwrap(),
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(days.len(), 2);
+        assert_eq!(days[0].date, NaiveDate::from_ymd_opt(2024, 4, 9).unwrap());
+        assert_eq!(days[0].pricing.len(), 2);
+        assert_eq!(
+            days[1].pricing.get(&at(10, 0)),
+            Some(&CentsPerKwh::eur(dec!(5)))
+        );
+    }
+}
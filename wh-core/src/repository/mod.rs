@@ -0,0 +1,119 @@
+use std::collections::BTreeMap;
+
+use async_trait::async_trait;
+use chrono::NaiveDate;
+use thiserror::Error;
+
+use crate::price_series::PriceSeries;
+use crate::types::BiddingZone;
+use crate::units::CentsPerKwh;
+
+#[cfg(feature = "dynamodb")]
+pub mod dynamodb;
+pub mod file;
+pub mod memory;
+#[cfg(feature = "sqlite")]
+pub mod sqlite;
+
+#[derive(Error, Debug)]
+pub enum RepositoryError {
+    #[cfg(feature = "dynamodb")]
+    #[error("DynamoDB operation failed: {0}")]
+    DynamoDb(#[from] Box<aws_sdk_dynamodb::Error>),
+    #[cfg(feature = "sqlite")]
+    #[error("SQLite operation failed: {0}")]
+    Sqlite(#[from] rusqlite::Error),
+    #[error("Error serializing data: {0}")]
+    Serialization(#[from] serde_json::Error),
+    #[error("Invalid pricing data: {0}")]
+    Data(String),
+}
+
+/// Prices of a single delivery day, in local time of the bidding zone.
+#[derive(Debug, Clone, PartialEq)]
+pub struct DeliveryDay {
+    pub date: NaiveDate,
+    pub pricing: PriceSeries<CentsPerKwh>,
+}
+
+impl DeliveryDay {
+    /// Splits `pricing` into the delivery days its slots fall on.
+    pub fn split(zone: &BiddingZone, pricing: &PriceSeries<CentsPerKwh>) -> Vec<DeliveryDay> {
+        let tz = zone.to_tz();
+        let mut days: BTreeMap<NaiveDate, PriceSeries<CentsPerKwh>> = BTreeMap::new();
+
+        for slot in pricing.iter() {
+            days.entry(slot.start.with_timezone(&tz).date_naive())
+                .or_insert_with(|| PriceSeries::new(pricing.resolution()))
+                .insert(slot.start, slot.price);
+        }
+
+        days.into_iter()
+            .map(|(date, pricing)| DeliveryDay { date, pricing })
+            .collect()
+    }
+}
+
+/// Storage for the day-ahead prices of each bidding zone, keyed by the storage date
+/// (see [`crate::util::get_storage_date`]).
+///
+/// The prices are also archived per delivery day and kept indefinitely, as the pricing
+/// stored for a storage date covers parts of two delivery days.
+#[async_trait]
+pub trait PricingRepository: Send + Sync {
+    async fn get_pricing(
+        &self,
+        zone: &BiddingZone,
+        date: NaiveDate,
+    ) -> Result<Option<PriceSeries<CentsPerKwh>>, RepositoryError>;
+
+    /// Replaces any pricing previously stored for the zone and date.
+    async fn store_pricing(
+        &self,
+        zone: &BiddingZone,
+        date: NaiveDate,
+        pricing: &PriceSeries<CentsPerKwh>,
+    ) -> Result<(), RepositoryError>;
+
+    /// Archived delivery days of the zone from `from` to `to`, inclusive and in order.
+    /// Days without any prices are left out.
+    async fn get_delivery_days(
+        &self,
+        zone: &BiddingZone,
+        from: NaiveDate,
+        to: NaiveDate,
+    ) -> Result<Vec<DeliveryDay>, RepositoryError>;
+
+    /// Replaces the archived prices of the delivery day.
+    async fn store_delivery_day(
+        &self,
+        zone: &BiddingZone,
+        day: &DeliveryDay,
+    ) -> Result<(), RepositoryError>;
+
+    /// Adds the prices in `pricing` to the archive, keeping the already archived prices
+    /// of the same delivery days.
+    async fn archive_pricing(
+        &self,
+        zone: &BiddingZone,
+        pricing: &PriceSeries<CentsPerKwh>,
+    ) -> Result<(), RepositoryError> {
+        for day in DeliveryDay::split(zone, pricing) {
+            let archived = self.get_delivery_days(zone, day.date, day.date).await?;
+
+            let merged = match archived.into_iter().next() {
+                Some(mut archived) => {
+                    for slot in day.pricing.iter() {
+                        archived.pricing.insert(slot.start, slot.price);
+                    }
+                    archived
+                }
+                None => day,
+            };
+
+            self.store_delivery_day(zone, &merged).await?;
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,333 @@
+use std::collections::BTreeMap;
+use std::path::Path;
+use std::str::FromStr;
+use std::sync::Mutex;
+
+use async_trait::async_trait;
+use chrono::{DateTime, FixedOffset, NaiveDate};
+use rusqlite::{params, Connection};
+use rust_decimal::Decimal;
+
+use crate::price_series::PriceSeries;
+use crate::types::BiddingZone;
+use crate::units::{CentsPerKwh, Currency};
+
+use super::{DeliveryDay, PricingRepository, RepositoryError};
+
+/// Stores one row per price slot, with the delivery day archive in a separate table.
+/// Queries are short, so the connection is used behind a plain mutex instead of moving
+/// them to a blocking thread.
+pub struct SqlitePricingRepository {
+    connection: Mutex<Connection>,
+}
+
+impl SqlitePricingRepository {
+    pub fn open(path: impl AsRef<Path>) -> Result<Self, RepositoryError> {
+        SqlitePricingRepository::from_connection(Connection::open(path)?)
+    }
+
+    pub fn from_connection(connection: Connection) -> Result<Self, RepositoryError> {
+        connection.execute(
+            "CREATE TABLE IF NOT EXISTS electricity_pricing (
+                zone TEXT NOT NULL,
+                date TEXT NOT NULL,
+                slot_start TEXT NOT NULL,
+                price TEXT NOT NULL,
+                currency TEXT NOT NULL,
+                PRIMARY KEY (zone, date, slot_start)
+            )",
+            [],
+        )?;
+
+        connection.execute(
+            "CREATE TABLE IF NOT EXISTS price_archive (
+                zone TEXT NOT NULL,
+                date TEXT NOT NULL,
+                slot_start TEXT NOT NULL,
+                price TEXT NOT NULL,
+                currency TEXT NOT NULL,
+                PRIMARY KEY (zone, date, slot_start)
+            )",
+            [],
+        )?;
+
+        Ok(SqlitePricingRepository {
+            connection: Mutex::new(connection),
+        })
+    }
+}
+
+fn parse_column<T: FromStr>(value: &str) -> Result<T, RepositoryError>
+where
+    T::Err: std::fmt::Display,
+{
+    value
+        .parse()
+        .map_err(|e: T::Err| RepositoryError::Data(format!("{value}: {e}")))
+}
+
+fn parse_series(
+    rows: &[(String, String, String)],
+) -> Result<PriceSeries<CentsPerKwh>, RepositoryError> {
+    let slots = rows
+        .iter()
+        .map(|(start, price, currency)| {
+            Ok((
+                DateTime::<FixedOffset>::parse_from_rfc3339(start)
+                    .map_err(|e| RepositoryError::Data(e.to_string()))?,
+                CentsPerKwh::new(
+                    parse_column::<Decimal>(price)?,
+                    parse_column::<Currency>(currency)?,
+                ),
+            ))
+        })
+        .collect::<Result<Vec<_>, RepositoryError>>()?;
+
+    Ok(PriceSeries::with_inferred_resolution(slots))
+}
+
+#[async_trait]
+impl PricingRepository for SqlitePricingRepository {
+    async fn get_pricing(
+        &self,
+        zone: &BiddingZone,
+        date: NaiveDate,
+    ) -> Result<Option<PriceSeries<CentsPerKwh>>, RepositoryError> {
+        let connection = self.connection.lock().unwrap();
+
+        let mut statement = connection.prepare(
+            "SELECT slot_start, price, currency FROM electricity_pricing
+             WHERE zone = ?1 AND date = ?2",
+        )?;
+
+        let rows = statement
+            .query_map(params![zone.to_string(), date.to_string()], |row| {
+                Ok((
+                    row.get::<_, String>(0)?,
+                    row.get::<_, String>(1)?,
+                    row.get::<_, String>(2)?,
+                ))
+            })?
+            .collect::<Result<Vec<_>, _>>()?;
+
+        if rows.is_empty() {
+            return Ok(None);
+        }
+
+        Ok(Some(parse_series(&rows)?))
+    }
+
+    async fn store_pricing(
+        &self,
+        zone: &BiddingZone,
+        date: NaiveDate,
+        pricing: &PriceSeries<CentsPerKwh>,
+    ) -> Result<(), RepositoryError> {
+        let mut connection = self.connection.lock().unwrap();
+        let transaction = connection.transaction()?;
+
+        transaction.execute(
+            "DELETE FROM electricity_pricing WHERE zone = ?1 AND date = ?2",
+            params![zone.to_string(), date.to_string()],
+        )?;
+
+        for slot in pricing.iter() {
+            transaction.execute(
+                "INSERT INTO electricity_pricing (zone, date, slot_start, price, currency)
+                 VALUES (?1, ?2, ?3, ?4, ?5)",
+                params![
+                    zone.to_string(),
+                    date.to_string(),
+                    slot.start.to_rfc3339(),
+                    slot.price.amount().to_string(),
+                    slot.price.currency().to_string(),
+                ],
+            )?;
+        }
+
+        transaction.commit()?;
+
+        Ok(())
+    }
+
+    async fn get_delivery_days(
+        &self,
+        zone: &BiddingZone,
+        from: NaiveDate,
+        to: NaiveDate,
+    ) -> Result<Vec<DeliveryDay>, RepositoryError> {
+        let connection = self.connection.lock().unwrap();
+
+        let mut statement = connection.prepare(
+            "SELECT date, slot_start, price, currency FROM price_archive
+             WHERE zone = ?1 AND date BETWEEN ?2 AND ?3
+             ORDER BY date",
+        )?;
+
+        let rows = statement
+            .query_map(
+                params![zone.to_string(), from.to_string(), to.to_string()],
+                |row| {
+                    Ok((
+                        row.get::<_, String>(0)?,
+                        (
+                            row.get::<_, String>(1)?,
+                            row.get::<_, String>(2)?,
+                            row.get::<_, String>(3)?,
+                        ),
+                    ))
+                },
+            )?
+            .collect::<Result<Vec<_>, _>>()?;
+
+        let mut days: BTreeMap<String, Vec<(String, String, String)>> = BTreeMap::new();
+        for (date, slot) in rows {
+            days.entry(date).or_default().push(slot);
+        }
+
+        days.into_iter()
+            .map(|(date, rows)| {
+                Ok(DeliveryDay {
+                    date: parse_column(&date)?,
+                    pricing: parse_series(&rows)?,
+                })
+            })
+            .collect()
+    }
+
+    async fn store_delivery_day(
+        &self,
+        zone: &BiddingZone,
+        day: &DeliveryDay,
+    ) -> Result<(), RepositoryError> {
+        let mut connection = self.connection.lock().unwrap();
+        let transaction = connection.transaction()?;
+
+        transaction.execute(
+            "DELETE FROM price_archive WHERE zone = ?1 AND date = ?2",
+            params![zone.to_string(), day.date.to_string()],
+        )?;
+
+        for slot in day.pricing.iter() {
+            transaction.execute(
+                "INSERT INTO price_archive (zone, date, slot_start, price, currency)
+                 VALUES (?1, ?2, ?3, ?4, ?5)",
+                params![
+                    zone.to_string(),
+                    day.date.to_string(),
+                    slot.start.to_rfc3339(),
+                    slot.price.amount().to_string(),
+                    slot.price.currency().to_string(),
+                ],
+            )?;
+        }
+
+        transaction.commit()?;
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::{Duration, TimeZone};
+    use rust_decimal_macros::dec;
+
+    use super::*;
+
+    fn at(hour: u32) -> DateTime<FixedOffset> {
+        FixedOffset::east_opt(3 * 3600)
+            .unwrap()
+            .with_ymd_and_hms(2024, 4, 8, hour, 0, 0)
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_store_replaces_pricing_for_date() {
+        let repository =
+            SqlitePricingRepository::from_connection(Connection::open_in_memory().unwrap())
+                .unwrap();
+        let date = NaiveDate::from_ymd_opt(2024, 4, 8).unwrap();
+
+        let first = PriceSeries::from_slots(
+            Duration::hours(1),
+            [
+                (at(0), CentsPerKwh::eur(dec!(3.843))),
+                (at(1), CentsPerKwh::eur(dec!(-0.001))),
+            ],
+        );
+        let second =
+            PriceSeries::from_slots(Duration::hours(1), [(at(0), CentsPerKwh::eur(dec!(1.5)))]);
+
+        repository
+            .store_pricing(&BiddingZone::FI, date, &first)
+            .await
+            .unwrap();
+        assert_eq!(
+            repository
+                .get_pricing(&BiddingZone::FI, date)
+                .await
+                .unwrap(),
+            Some(first)
+        );
+
+        repository
+            .store_pricing(&BiddingZone::FI, date, &second)
+            .await
+            .unwrap();
+        assert_eq!(
+            repository
+                .get_pricing(&BiddingZone::FI, date)
+                .await
+                .unwrap(),
+            Some(second)
+        );
+        assert_eq!(
+            repository
+                .get_pricing(&BiddingZone::SE3, date)
+                .await
+                .unwrap(),
+            None
+        );
+    }
+
+    #[tokio::test]
+    async fn test_delivery_days_in_range() {
+        let repository =
+            SqlitePricingRepository::from_connection(Connection::open_in_memory().unwrap())
+                .unwrap();
+        let date = NaiveDate::from_ymd_opt(2024, 4, 8).unwrap();
+
+        let day = DeliveryDay {
+            date,
+            pricing: PriceSeries::from_slots(
+                Duration::hours(1),
+                [
+                    (at(0), CentsPerKwh::eur(dec!(3.843))),
+                    (at(1), CentsPerKwh::eur(dec!(-0.001))),
+                ],
+            ),
+        };
+
+        repository
+            .store_delivery_day(&BiddingZone::FI, &day)
+            .await
+            .unwrap();
+
+        let days = repository
+            .get_delivery_days(&BiddingZone::FI, date - Duration::days(3), date)
+            .await
+            .unwrap();
+        let outside = repository
+            .get_delivery_days(
+                &BiddingZone::FI,
+                date + Duration::days(1),
+                date + Duration::days(3),
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(days, vec![day]);
+        assert!(outside.is_empty());
+    }
+}
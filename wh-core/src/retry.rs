@@ -0,0 +1,33 @@
+use chrono::Duration;
+
+/// Retries after the scheduled price fetch, the fetch is given up after this.
+pub const MAX_RETRY_ATTEMPTS: u16 = 5;
+
+/// Delay before the next attempt after `retry_attempt` has failed, 1 being the first
+/// retry. The first retry itself waits five minutes after the scheduled fetch.
+pub fn calculate_retry_delay(retry_attempt: u16) -> Duration {
+    match retry_attempt {
+        1 => Duration::minutes(5),
+        2 => Duration::minutes(5),
+        3 => Duration::minutes(10),
+        4 => Duration::minutes(20),
+        _ => Duration::minutes(20),
+    }
+}
+
+/// Delays before each retry, in order, as the message handler schedules them
+pub fn retry_delays() -> impl Iterator<Item = Duration> {
+    std::iter::once(Duration::minutes(5)).chain((1..MAX_RETRY_ATTEMPTS).map(calculate_retry_delay))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_retry_delays() {
+        let minutes: Vec<i64> = retry_delays().map(|delay| delay.num_minutes()).collect();
+
+        assert_eq!(minutes, vec![5, 5, 5, 10, 20]);
+    }
+}
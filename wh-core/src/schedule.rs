@@ -0,0 +1,277 @@
+use chrono::{DateTime, Duration, FixedOffset, NaiveDate, Timelike};
+use chrono_tz::Tz;
+use thiserror::Error;
+
+use crate::price_series::PriceSeries;
+use crate::time_provider::TimeProvider;
+use crate::types::BiddingZone;
+use crate::units::CentsPerKwh;
+
+#[derive(Error, Debug, Clone, PartialEq, Eq)]
+pub enum PeriodError {
+    #[error("start must be between 0 and 23, was {0}")]
+    Start(u32),
+    #[error("end must be between 0 and 24, was {0}")]
+    End(u32),
+    #[error("hours must be between 1 and {max}, the length of the period, was {hours}")]
+    Hours { hours: u32, max: u32 },
+}
+
+impl PeriodError {
+    /// Name of the query parameter the error is about
+    pub fn field(&self) -> &'static str {
+        match self {
+            PeriodError::Start(_) => "start",
+            PeriodError::End(_) => "end",
+            PeriodError::Hours { .. } => "hours",
+        }
+    }
+}
+
+/// Validated `hours/start/end` of a request.
+///
+/// `end` 24 is the same as midnight, 0, and `start == end` is a period of the whole day
+/// starting at `start`, which is how the scheduling functions treat a period where
+/// `starting_hour >= ending_hour`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct HeatingPeriod {
+    pub hours: u32,
+    pub starting_hour: u32,
+    /// Always less than 24
+    pub ending_hour: u32,
+}
+
+impl HeatingPeriod {
+    pub fn new(hours: u32, starting_hour: u32, ending_hour: u32) -> Result<Self, Vec<PeriodError>> {
+        let mut errors = Vec::new();
+
+        if starting_hour > 23 {
+            errors.push(PeriodError::Start(starting_hour));
+        }
+        if ending_hour > 24 {
+            errors.push(PeriodError::End(ending_hour));
+        }
+        if !errors.is_empty() {
+            return Err(errors);
+        }
+
+        let period = HeatingPeriod {
+            hours,
+            starting_hour,
+            ending_hour: ending_hour % 24,
+        };
+
+        let max = period.length();
+        if hours == 0 || hours > max {
+            return Err(vec![PeriodError::Hours { hours, max }]);
+        }
+
+        Ok(period)
+    }
+
+    /// Number of hours between the start and the end
+    pub fn length(&self) -> u32 {
+        if self.starting_hour < self.ending_hour {
+            self.ending_hour - self.starting_hour
+        } else {
+            24 - self.starting_hour + self.ending_hour
+        }
+    }
+}
+
+pub fn get_filtered_pricing<T: TimeProvider>(
+    time_provider: &T,
+    country_code: &BiddingZone,
+    pricing: &PriceSeries<CentsPerKwh>,
+    starting_hour: u32,
+    ending_hour: u32,
+) -> PriceSeries<CentsPerKwh> {
+    let current_day = time_provider
+        .now()
+        .with_timezone(&country_code.to_tz())
+        .date_naive();
+
+    let current_hour = time_provider
+        .now()
+        .with_timezone(&country_code.to_tz())
+        .hour();
+
+    filter_pricing_for_period(
+        pricing,
+        current_day,
+        current_hour,
+        starting_hour,
+        ending_hour,
+    )
+}
+
+pub fn filter_pricing_for_period(
+    pricing: &PriceSeries<CentsPerKwh>,
+    current_day: NaiveDate,
+    current_hour: u32,
+    starting_hour: u32,
+    ending_hour: u32,
+) -> PriceSeries<CentsPerKwh> {
+    // If starting hour is larger than ending hour, it means we are crossing the night
+    // Which means to accurately filter the pricing info for the period, we need to
+    // also return the pricing info from the starting hour on the previous day, when
+    // clock has for example reached 02:00. Example is starting hour 22 ending hour 07
+    // To correctly calculate the cheapest period we need the pricing information from 22:00
+    // yesterday to 07 today.
+    pricing.filter(|p| {
+        let pricing_hour = p.start.hour();
+        let pricing_date = p.start.date_naive();
+
+        if starting_hour < ending_hour {
+            // Normal period, not crossing midnight, the date on the pricing information has to
+            // match current date (we want to filter out yesterdays pricing info as it's not
+            // relevant for the period)
+            pricing_hour >= starting_hour
+                && pricing_hour < ending_hour
+                && pricing_date == current_day
+        } else {
+            // Period crosses midnight, starting hour (e.g. 22) is LARGER than ending hour
+            // (e.g. 07). We need to include pricing data for any hours between 22 and 00 if
+            // the date matches to today
+            // OR ((if the pricing data hour is earlier than the periods ending hour and it's
+            // for the current day AND current hour is smaller than the starting hour ) OR (if
+            // the pricing hour is smaller than the ending hour AND the pricing date is ahead one
+            // day compared to current day)
+            (pricing_hour >= starting_hour && pricing_date == current_day)
+                || ((pricing_hour < ending_hour
+                    && pricing_date == current_day
+                    && current_hour < starting_hour)
+                    || (pricing_hour < ending_hour
+                        && pricing_date == current_day + Duration::days(1)))
+        }
+    })
+}
+
+/// Only windows without gaps are considered, so a missing hour in the pricing data
+/// can not join two separate periods into one.
+pub fn calculate_cheapest_start_time(
+    pricing: &PriceSeries<CentsPerKwh>,
+    hours: u32,
+) -> Option<DateTime<FixedOffset>> {
+    let mut cheapest_sequence_start: Option<DateTime<FixedOffset>> = None;
+    let mut min_cost: Option<CentsPerKwh> = None;
+
+    let window_size = pricing.slots_in(Duration::hours(i64::from(hours)));
+
+    for window in pricing.contiguous_windows(window_size) {
+        let Some(total_cost) = CentsPerKwh::checked_sum(window.iter().map(|p| p.price)) else {
+            continue;
+        };
+
+        if min_cost.is_none_or(|min| total_cost < min) {
+            min_cost = Some(total_cost);
+            cheapest_sequence_start = Some(window.first().unwrap().start);
+        }
+    }
+
+    cheapest_sequence_start
+}
+
+/// Cheapest start time for a period of `hours` between `starting_hour` and `ending_hour`
+/// when evaluated on `current_day` at `current_hour`.
+pub fn find_cheapest_start_time(
+    pricing: &PriceSeries<CentsPerKwh>,
+    current_day: NaiveDate,
+    current_hour: u32,
+    hours: u32,
+    starting_hour: u32,
+    ending_hour: u32,
+) -> Option<DateTime<FixedOffset>> {
+    let filtered_pricing = filter_pricing_for_period(
+        pricing,
+        current_day,
+        current_hour,
+        starting_hour,
+        ending_hour,
+    );
+
+    if filtered_pricing.is_empty()
+        || filtered_pricing.len() < filtered_pricing.slots_in(Duration::hours(i64::from(hours)))
+    {
+        return None;
+    }
+
+    calculate_cheapest_start_time(&filtered_pricing, hours)
+}
+
+pub fn is_within_operating_hours(
+    starting_hour: u32,
+    ending_hour: u32,
+    current_time: DateTime<Tz>,
+) -> bool {
+    let current_hour = current_time.hour();
+
+    if starting_hour < ending_hour {
+        return current_hour >= starting_hour && current_hour < ending_hour;
+    }
+
+    current_hour >= starting_hour || current_hour < ending_hour
+}
+
+/// Whether the heater should be on at the current time for a period of `hours` between
+/// `starting_hour` and `ending_hour`, evaluating the period directly from `pricing`.
+pub fn is_enabled_at<T: TimeProvider>(
+    time_provider: &T,
+    country_code: &BiddingZone,
+    pricing: &PriceSeries<CentsPerKwh>,
+    hours: u32,
+    starting_hour: u32,
+    ending_hour: u32,
+) -> bool {
+    let current_time = time_provider.now().with_timezone(&country_code.to_tz());
+
+    if !is_within_operating_hours(starting_hour, ending_hour, current_time) {
+        return false;
+    }
+
+    let cheapest_start = find_cheapest_start_time(
+        pricing,
+        current_time.date_naive(),
+        current_time.hour(),
+        hours,
+        starting_hour,
+        ending_hour,
+    );
+
+    match cheapest_start {
+        Some(start) => {
+            let end = start + Duration::hours(i64::from(hours));
+            current_time >= start && current_time <= end
+        }
+        None => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_heating_period_validation() {
+        let period = HeatingPeriod::new(2, 22, 24).unwrap();
+        assert_eq!(period.ending_hour, 0);
+        assert_eq!(period.length(), 2);
+        assert_eq!(
+            HeatingPeriod::new(3, 22, 24),
+            Err(vec![PeriodError::Hours { hours: 3, max: 2 }])
+        );
+
+        // The whole day from the starting hour
+        assert_eq!(HeatingPeriod::new(24, 5, 5).unwrap().length(), 24);
+        assert_eq!(HeatingPeriod::new(24, 0, 24).unwrap().length(), 24);
+
+        assert_eq!(
+            HeatingPeriod::new(0, 22, 7),
+            Err(vec![PeriodError::Hours { hours: 0, max: 9 }])
+        );
+        assert_eq!(
+            HeatingPeriod::new(3, 25, 30),
+            Err(vec![PeriodError::Start(25), PeriodError::End(30)])
+        );
+    }
+}
@@ -0,0 +1,97 @@
+use chrono_tz::Tz;
+use rust_decimal::Decimal;
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+use crate::price_series::PriceSeries;
+use crate::schedule::is_within_operating_hours;
+use crate::units::CentsPerKwh;
+
+/// Charge added to the spot price between two hours of the day in local time, e.g. the
+/// night rate of a time-of-use transfer fee
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+pub struct Tariff {
+    /// First hour the tariff applies to, 0-23
+    pub start: u32,
+    /// Hour the tariff ends at, 0-24. The tariff applies to the whole day when the same
+    /// as `start`.
+    pub end: u32,
+    /// c/kWh
+    #[serde(with = "rust_decimal::serde::float")]
+    #[schema(value_type = f64)]
+    pub price: Decimal,
+}
+
+impl Tariff {
+    pub fn applies_at(&self, time: chrono::DateTime<Tz>) -> bool {
+        is_within_operating_hours(self.start, self.end % 24, time)
+    }
+}
+
+/// `pricing` with the tariffs of each slot added, overlapping tariffs are summed
+pub fn apply_tariffs(
+    pricing: &PriceSeries<CentsPerKwh>,
+    tz: &Tz,
+    tariffs: &[Tariff],
+) -> PriceSeries<CentsPerKwh> {
+    if tariffs.is_empty() {
+        return pricing.clone();
+    }
+
+    let mut adjusted = PriceSeries::new(pricing.resolution());
+    for slot in pricing.iter() {
+        let local = slot.start.with_timezone(tz);
+        let charges: Decimal = tariffs
+            .iter()
+            .filter(|tariff| tariff.applies_at(local))
+            .map(|tariff| tariff.price)
+            .sum();
+
+        adjusted.insert(
+            slot.start,
+            CentsPerKwh::new(slot.price.amount() + charges, slot.price.currency()),
+        );
+    }
+
+    adjusted
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::{Duration, TimeZone};
+    use chrono_tz::Europe::Helsinki;
+    use rust_decimal_macros::dec;
+
+    use super::*;
+
+    #[test]
+    fn test_night_tariff_makes_night_cheaper() {
+        let start = Helsinki.with_ymd_and_hms(2024, 4, 8, 21, 0, 0).unwrap();
+        let pricing = PriceSeries::from_slots(
+            Duration::hours(1),
+            (0..3).map(|hour| {
+                (
+                    (start + Duration::hours(hour)).fixed_offset(),
+                    CentsPerKwh::eur(dec!(5)),
+                )
+            }),
+        );
+        let tariffs = [
+            Tariff {
+                start: 7,
+                end: 22,
+                price: dec!(3.5),
+            },
+            Tariff {
+                start: 22,
+                end: 7,
+                price: dec!(1.5),
+            },
+        ];
+
+        let adjusted = apply_tariffs(&pricing, &Helsinki, &tariffs);
+        let prices: Vec<Decimal> = adjusted.iter().map(|slot| slot.price.amount()).collect();
+
+        assert_eq!(prices, vec![dec!(8.5), dec!(6.5), dec!(6.5)]);
+    }
+}
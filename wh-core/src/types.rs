@@ -13,7 +13,20 @@ const CENTRAL: Tz = Stockholm;
 const EASTERN: Tz = Helsinki;
 const WESTERN: Tz = Lisbon;
 
-#[derive(Debug, Deserialize, Serialize, ToSchema, EnumIter)]
+#[derive(
+    Debug,
+    Clone,
+    Copy,
+    PartialEq,
+    Eq,
+    PartialOrd,
+    Ord,
+    Hash,
+    Deserialize,
+    Serialize,
+    ToSchema,
+    EnumIter,
+)]
 #[serde(rename_all = "lowercase")]
 pub enum BiddingZone {
     FI,
@@ -0,0 +1,191 @@
+use std::fmt;
+use std::str::FromStr;
+
+use rust_decimal::{Decimal, RoundingStrategy};
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+/// Number of decimal places prices are rounded to when presented.
+pub const PRESENTATION_DECIMALS: u32 = 3;
+
+#[derive(
+    Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Deserialize, Serialize, ToSchema,
+)]
+#[serde(rename_all = "UPPERCASE")]
+pub enum Currency {
+    Eur,
+}
+
+impl Currency {
+    pub fn symbol(&self) -> &'static str {
+        match self {
+            Currency::Eur => "€",
+        }
+    }
+}
+
+impl fmt::Display for Currency {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Currency::Eur => write!(f, "EUR"),
+        }
+    }
+}
+
+impl FromStr for Currency {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "EUR" => Ok(Currency::Eur),
+            _ => Err(format!("Unknown currency: {s}")),
+        }
+    }
+}
+
+/// Wholesale price of one megawatt hour, as published by the exchanges (e.g. €/MWh).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct PricePerMwh {
+    amount: Decimal,
+    currency: Currency,
+}
+
+impl PricePerMwh {
+    pub fn new(amount: Decimal, currency: Currency) -> Self {
+        PricePerMwh { amount, currency }
+    }
+
+    pub fn eur(amount: Decimal) -> Self {
+        PricePerMwh::new(amount, Currency::Eur)
+    }
+
+    pub fn amount(&self) -> Decimal {
+        self.amount
+    }
+
+    pub fn currency(&self) -> Currency {
+        self.currency
+    }
+}
+
+impl fmt::Display for PricePerMwh {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{} {}/MWh", self.amount, self.currency.symbol())
+    }
+}
+
+/// Price of one kilowatt hour in hundredths of the currency (e.g. c/kWh), which is
+/// the unit consumer prices are quoted in.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct CentsPerKwh {
+    amount: Decimal,
+    currency: Currency,
+}
+
+impl CentsPerKwh {
+    /// Unit identifier used when the amount is stored without its type.
+    pub const UNIT: &'static str = "c/kWh";
+
+    pub fn new(amount: Decimal, currency: Currency) -> Self {
+        CentsPerKwh { amount, currency }
+    }
+
+    pub fn eur(amount: Decimal) -> Self {
+        CentsPerKwh::new(amount, Currency::Eur)
+    }
+
+    pub fn amount(&self) -> Decimal {
+        self.amount
+    }
+
+    pub fn currency(&self) -> Currency {
+        self.currency
+    }
+
+    /// Sum of the prices, `None` when they are in different currencies
+    pub fn checked_add(&self, rhs: CentsPerKwh) -> Option<CentsPerKwh> {
+        (self.currency == rhs.currency)
+            .then(|| CentsPerKwh::new(self.amount + rhs.amount, self.currency))
+    }
+
+    /// Difference of the prices, `None` when they are in different currencies
+    pub fn checked_sub(&self, rhs: CentsPerKwh) -> Option<CentsPerKwh> {
+        (self.currency == rhs.currency)
+            .then(|| CentsPerKwh::new(self.amount - rhs.amount, self.currency))
+    }
+
+    /// Sum of `prices`, `None` when there are none or they are in different currencies
+    pub fn checked_sum(prices: impl IntoIterator<Item = CentsPerKwh>) -> Option<CentsPerKwh> {
+        let mut prices = prices.into_iter();
+        let first = prices.next()?;
+
+        prices.try_fold(first, |total, price| total.checked_add(price))
+    }
+
+    /// Rounds half away from zero, so the same price always rounds the same way
+    /// regardless of where it is presented.
+    pub fn round_dp(&self, decimal_places: u32) -> Self {
+        CentsPerKwh {
+            amount: self
+                .amount
+                .round_dp_with_strategy(decimal_places, RoundingStrategy::MidpointAwayFromZero),
+            currency: self.currency,
+        }
+    }
+}
+
+impl From<PricePerMwh> for CentsPerKwh {
+    fn from(price: PricePerMwh) -> Self {
+        // 1 €/MWh = 100 c / 1000 kWh
+        CentsPerKwh {
+            amount: price.amount / Decimal::TEN,
+            currency: price.currency,
+        }
+    }
+}
+
+impl fmt::Display for CentsPerKwh {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{} c/kWh", self.amount)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rust_decimal_macros::dec;
+
+    use super::*;
+
+    #[test]
+    fn test_mwh_to_cents_per_kwh() {
+        let price = CentsPerKwh::from(PricePerMwh::eur(dec!(38.43)));
+
+        assert_eq!(price, CentsPerKwh::eur(dec!(3.843)));
+    }
+
+    #[test]
+    fn test_checked_arithmetic_in_same_currency() {
+        let a = CentsPerKwh::eur(dec!(1.5));
+        let b = CentsPerKwh::eur(dec!(0.25));
+
+        assert_eq!(a.checked_add(b), Some(CentsPerKwh::eur(dec!(1.75))));
+        assert_eq!(a.checked_sub(b), Some(CentsPerKwh::eur(dec!(1.25))));
+        assert_eq!(
+            CentsPerKwh::checked_sum([a, b, b]),
+            Some(CentsPerKwh::eur(dec!(2.0)))
+        );
+        assert_eq!(CentsPerKwh::checked_sum([]), None);
+    }
+
+    #[test]
+    fn test_round_dp_is_half_away_from_zero() {
+        assert_eq!(
+            CentsPerKwh::eur(dec!(0.0125)).round_dp(3),
+            CentsPerKwh::eur(dec!(0.013))
+        );
+        assert_eq!(
+            CentsPerKwh::eur(dec!(-0.0125)).round_dp(3),
+            CentsPerKwh::eur(dec!(-0.013))
+        );
+    }
+}
@@ -1,14 +1,58 @@
-use chrono::{Duration, NaiveDate, Timelike, Utc};
+use chrono::{DateTime, Duration, NaiveDate, TimeZone, Timelike, Utc};
 use chrono_tz::Europe::Helsinki;
 
+/// Hour (Helsinki time) after which the pricing for the next day is expected to be
+/// published, and the storage date moves to the current day.
+pub const PUBLICATION_HOUR: u32 = 14;
+
 pub fn get_storage_date() -> NaiveDate {
-    let utc_now = Utc::now();
+    storage_date_at(Utc::now())
+}
 
-    let helsinki_time = utc_now.with_timezone(&Helsinki);
+/// Storage date of the pricing available at `now`
+pub fn storage_date_at(now: DateTime<Utc>) -> NaiveDate {
+    let helsinki_time = now.with_timezone(&Helsinki);
 
-    if helsinki_time.hour() < 14 {
+    if helsinki_time.hour() < PUBLICATION_HOUR {
         return helsinki_time.date_naive() - Duration::days(1);
     }
 
     helsinki_time.date_naive()
 }
+
+/// The next time the storage date changes after `now`
+pub fn next_publication_time(now: DateTime<Utc>) -> DateTime<Utc> {
+    let helsinki_date = now.with_timezone(&Helsinki).date_naive();
+
+    [helsinki_date, helsinki_date + Duration::days(1)]
+        .iter()
+        .filter_map(|date| {
+            Helsinki
+                .from_local_datetime(&date.and_hms_opt(PUBLICATION_HOUR, 0, 0)?)
+                .single()
+        })
+        .map(|publication| publication.with_timezone(&Utc))
+        .find(|publication| *publication > now)
+        .unwrap_or(now + Duration::days(1))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_next_publication_time() {
+        let before = Utc.with_ymd_and_hms(2024, 9, 17, 10, 0, 0).unwrap();
+        let after = Utc.with_ymd_and_hms(2024, 9, 17, 11, 0, 0).unwrap();
+
+        // 14:00 in Helsinki is 11:00 UTC in the summer
+        assert_eq!(
+            next_publication_time(before),
+            Utc.with_ymd_and_hms(2024, 9, 17, 11, 0, 0).unwrap()
+        );
+        assert_eq!(
+            next_publication_time(after),
+            Utc.with_ymd_and_hms(2024, 9, 18, 11, 0, 0).unwrap()
+        );
+    }
+}
@@ -34,6 +34,24 @@ const dynamoTable = new aws.dynamodb.Table("electricityPricingData", {
   },
 });
 
+// Prices of every delivery day by bidding zone, kept indefinitely for reports and
+// backtests
+const archiveTable = new aws.dynamodb.Table("electricityPriceArchive", {
+  name: "electricity_price_archive",
+  attributes: [
+    { name: "zone", type: "S" },
+    { name: "date", type: "S" },
+  ],
+  hashKey: "zone",
+  rangeKey: "date",
+  billingMode: "PROVISIONED",
+  writeCapacity: 1,
+  readCapacity: 1,
+  tags: {
+    ...commonTags,
+  },
+});
+
 const eventRole = new aws.iam.Role("eventRole", {
   tags: commonTags,
   assumeRolePolicy: JSON.stringify({
@@ -73,6 +91,14 @@ new aws.iam.RolePolicy("eventRolePolicy", {
   ),
 });
 
-provisionWorker(queue, dlq, eventRole, dynamoTable, alarmTopic, commonTags);
+provisionWorker(
+  queue,
+  dlq,
+  eventRole,
+  dynamoTable,
+  archiveTable,
+  alarmTopic,
+  commonTags,
+);
 provisionMessageHandler(queue, proxyDLQ, eventRole, alarmTopic, commonTags);
 provisionMonthlyPricingWorker(eventRole, alarmTopic, config, commonTags);
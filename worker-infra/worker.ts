@@ -8,6 +8,7 @@ export function provisionWorker(
   dlq: aws.sqs.Queue,
   eventRole: aws.iam.Role,
   dynamoTable: aws.dynamodb.Table,
+  archiveTable: aws.dynamodb.Table,
   alarmTopic: aws.sns.Topic,
   commonTags: Tags,
 ) {
@@ -44,7 +45,7 @@ export function provisionWorker(
     tags: commonTags,
     imageUri: imageOutput.imageUri,
     packageType: "Image",
-    role: createWorkerRole(dynamoTable, queue, commonTags).arn,
+    role: createWorkerRole(dynamoTable, archiveTable, queue, commonTags).arn,
     timeout: 60,
   });
 
@@ -68,6 +69,7 @@ export function provisionWorker(
 
 function createWorkerRole(
   dynamoTable: aws.dynamodb.Table,
+  archiveTable: aws.dynamodb.Table,
   queue: aws.sqs.Queue,
   commonTags: Tags,
 ) {
@@ -82,6 +84,12 @@ function createWorkerRole(
           Effect: "Allow",
           Resource: [dynamoTable.arn],
         },
+        {
+          // Archived days are merged with the prices already stored for the day
+          Action: ["dynamodb:PutItem", "dynamodb:Query"],
+          Effect: "Allow",
+          Resource: [archiveTable.arn],
+        },
       ],
     },
   });
@@ -1,24 +1,23 @@
-use aws_sdk_dynamodb as dynamodb;
-use dynamodb::types::AttributeValue;
-
 use chrono::{offset::LocalResult, TimeZone};
 use chrono_tz::Tz;
 use tokio::sync::mpsc;
-use tracing::{error, info};
+use tracing::{error, info, warn};
 
+use wh_core::price_series::PriceSeries;
+use wh_core::repository::PricingRepository;
 use wh_core::types::BiddingZone;
+use wh_core::units::{CentsPerKwh, PricePerMwh};
 use wh_core::util::get_storage_date;
 
-use crate::types::{EnergyChartApiResponse, HourlyPrice, WorkerError};
+use crate::types::{EnergyChartApiResponse, WorkerError};
 
 pub async fn process_and_store_data(
-    client: &dynamodb::Client,
+    pricing_repository: &dyn PricingRepository,
     mut receiver: mpsc::Receiver<(BiddingZone, EnergyChartApiResponse)>,
 ) -> Result<(), WorkerError> {
     while let Some((zone, data)) = receiver.recv().await {
-        let cloned_client = client.clone();
         let parsed_data = parse_pricing_data(&zone.to_tz(), &data)?;
-        store_pricing_data(cloned_client, &zone, &parsed_data).await?;
+        store_pricing_data(pricing_repository, &zone, &parsed_data).await?;
     }
     Ok(())
 }
@@ -26,8 +25,8 @@ pub async fn process_and_store_data(
 pub fn parse_pricing_data(
     timezone: &Tz,
     pricing_data: &EnergyChartApiResponse,
-) -> Result<Vec<HourlyPrice>, WorkerError> {
-    let mut kwh_pricing_data: Vec<HourlyPrice> = vec![];
+) -> Result<PriceSeries<CentsPerKwh>, WorkerError> {
+    let mut kwh_pricing_data = vec![];
 
     for (index, price) in pricing_data.price.iter().enumerate() {
         let unix_timestamp = pricing_data.unix_seconds[index] as i64;
@@ -41,38 +40,105 @@ pub fn parse_pricing_data(
             }
         };
 
-        kwh_pricing_data.push(HourlyPrice(date_time, price / 1000_f32))
+        let price = CentsPerKwh::from(PricePerMwh::eur(*price));
+        kwh_pricing_data.push((date_time.fixed_offset(), price))
+    }
+
+    let kwh_pricing_data = PriceSeries::with_inferred_resolution(kwh_pricing_data);
+
+    for gap in kwh_pricing_data.gaps() {
+        warn!(?gap, "Pricing data is missing a period");
     }
 
     Ok(kwh_pricing_data)
 }
 
 async fn store_pricing_data(
-    client: dynamodb::Client,
+    pricing_repository: &dyn PricingRepository,
     bzn: &BiddingZone,
-    pricing: &[HourlyPrice],
+    pricing: &PriceSeries<CentsPerKwh>,
 ) -> Result<(), WorkerError> {
-    client
-        .put_item()
-        .table_name("electricity_pricing")
-        .item("country", AttributeValue::S(bzn.to_country_string()))
-        .item("date", AttributeValue::S(get_storage_date().to_string()))
-        .item(
-            "pricing_data",
-            AttributeValue::S(serde_json::to_string(pricing).unwrap()),
-        )
-        .send()
+    pricing_repository
+        .store_pricing(bzn, get_storage_date(), pricing)
         .await
         .inspect(|_| {
-            info!(
-                "Pricing data successfully inserted to DynamoDB for zone: {}",
-                bzn
-            );
+            info!("Pricing data successfully stored for zone: {}", bzn);
         })
-        .map_err(|e| {
-            error!(?e, "Inserting pricing data to DynamoDB failed");
-            Box::new(e.into_service_error())
+        .inspect_err(|e| {
+            error!(?e, "Storing pricing data failed");
+        })?;
+
+    pricing_repository
+        .archive_pricing(bzn, pricing)
+        .await
+        .inspect_err(|e| {
+            error!(?e, "Archiving pricing data failed");
         })?;
 
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use chrono::{Duration, FixedOffset, NaiveDate};
+    use rust_decimal_macros::dec;
+    use wh_core::repository::memory::InMemoryPricingRepository;
+
+    use super::*;
+
+    #[tokio::test]
+    async fn test_process_and_store_data() {
+        let repository = InMemoryPricingRepository::new();
+        let (tx, rx) = mpsc::channel(1);
+
+        tx.send((
+            BiddingZone::FI,
+            EnergyChartApiResponse {
+                unix_seconds: Arc::new([1726689600, 1726693200]),
+                price: Arc::new([dec!(38.43), dec!(-1.5)]),
+            },
+        ))
+        .await
+        .unwrap();
+        drop(tx);
+
+        process_and_store_data(&repository, rx).await.unwrap();
+
+        let stored = repository
+            .get_pricing(&BiddingZone::FI, get_storage_date())
+            .await
+            .unwrap()
+            .unwrap();
+
+        let helsinki = FixedOffset::east_opt(3 * 3600).unwrap();
+        let first_start = helsinki.timestamp_opt(1726689600, 0).unwrap();
+
+        assert_eq!(stored.resolution(), Duration::hours(1));
+        assert_eq!(
+            stored.get(&first_start),
+            Some(&CentsPerKwh::eur(dec!(3.843)))
+        );
+        assert_eq!(
+            stored.get(&(first_start + Duration::hours(1))),
+            Some(&CentsPerKwh::eur(dec!(-0.15)))
+        );
+
+        // 23:00 and 00:00 in Helsinki fall on different delivery days
+        let archived = repository
+            .get_delivery_days(
+                &BiddingZone::FI,
+                NaiveDate::from_ymd_opt(2024, 9, 18).unwrap(),
+                NaiveDate::from_ymd_opt(2024, 9, 19).unwrap(),
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(archived.len(), 2);
+        assert_eq!(
+            archived[1].pricing.first_start(),
+            Some(first_start + Duration::hours(1))
+        );
+    }
+}
@@ -0,0 +1,57 @@
+use std::sync::Arc;
+
+use aws_sdk_dynamodb::error::BoxError;
+use reqwest::Client;
+use tokio::sync::mpsc;
+use tracing::error;
+
+use wh_core::repository::PricingRepository;
+use wh_core::time_provider;
+use wh_core::types::BiddingZone;
+
+use crate::producer::fetch_pricing;
+use crate::service::has_new_results;
+use crate::types::WorkerError;
+
+pub mod consumer;
+pub mod producer;
+pub mod service;
+pub mod types;
+
+/// Fetches the pricing for the next day for every bidding zone and stores it. Fails if
+/// the prices for the next day have not been published yet, so the fetch can be retried.
+pub async fn fetch_and_store_pricing(
+    pricing_repository: Arc<dyn PricingRepository>,
+    reqwest_client: Arc<Client>,
+) -> Result<(), BoxError> {
+    let data = match fetch_pricing(&reqwest_client, &BiddingZone::FI).await {
+        Ok(data) => data,
+        Err(e) => return Err(Box::new(e)),
+    };
+
+    let new_pricing_data_available = has_new_results(data, &time_provider::SystemTimeProvider)?;
+
+    if !new_pricing_data_available {
+        error!("New electricity pricing data not available");
+        return Err(Box::new(WorkerError::Data(
+            "New electricity pricing data not available".to_string(),
+        )));
+    }
+
+    let (tx, rx) = mpsc::channel(32);
+
+    let fetch_handle =
+        tokio::spawn(async move { producer::get_pricing_data(&reqwest_client, tx).await });
+
+    let process_handle = tokio::spawn(async move {
+        consumer::process_and_store_data(pricing_repository.as_ref(), rx).await
+    });
+
+    // Wait for both tasks to complete
+    let (fetch_result, process_result) = tokio::try_join!(fetch_handle, process_handle)?;
+
+    fetch_result?;
+    process_result?;
+
+    Ok(())
+}
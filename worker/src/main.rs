@@ -1,3 +1,4 @@
+use std::env;
 use std::sync::Arc;
 
 use aws_lambda_events::sqs::SqsEvent;
@@ -6,53 +7,21 @@ use aws_sdk_dynamodb as dynamodb;
 use dynamodb::error::BoxError;
 use lambda_runtime::{run, service_fn, tracing, Error, LambdaEvent};
 
-use producer::fetch_pricing;
-
 use reqwest::Client;
-use service::has_new_results;
-use tokio::sync::mpsc;
-use tracing::error;
 
-use types::WorkerError;
-use wh_core::time_provider;
-use wh_core::types::BiddingZone;
+use wh_core::repository::dynamodb::{
+    DynamoPricingRepository, PRICE_ARCHIVE_TABLE_NAME, PRICING_TABLE_NAME,
+};
+use wh_core::repository::PricingRepository;
 
-mod consumer;
-mod producer;
-mod service;
-mod types;
+use worker::fetch_and_store_pricing;
 
 async fn handle_store_electricity_pricing(
     _event: LambdaEvent<SqsEvent>,
-    dynamo_client: Arc<dynamodb::Client>,
+    pricing_repository: Arc<dyn PricingRepository>,
     reqwest_client: Arc<reqwest::Client>,
 ) -> Result<(), BoxError> {
-    let data = match fetch_pricing(&reqwest_client, &BiddingZone::FI).await {
-        Ok(data) => data,
-        Err(e) => return Err(Box::new(e)),
-    };
-
-    let new_pricing_data_available = has_new_results(data, &time_provider::SystemTimeProvider)?;
-
-    if !new_pricing_data_available {
-        error!("New electricity pricing data not available");
-        return Err(Box::new(WorkerError::Data(
-            "New electricity pricing data not available".to_string(),
-        )));
-    }
-
-    let (tx, rx) = mpsc::channel(32);
-
-    let fetch_handle =
-        tokio::spawn(async move { producer::get_pricing_data(&reqwest_client, tx).await });
-
-    let process_handle =
-        tokio::spawn(async move { consumer::process_and_store_data(&dynamo_client, rx).await });
-
-    // Wait for both tasks to complete
-    let _ = tokio::try_join!(fetch_handle, process_handle)?;
-
-    Ok(())
+    fetch_and_store_pricing(pricing_repository, reqwest_client).await
 }
 
 #[tokio::main]
@@ -67,11 +36,17 @@ async fn main() -> Result<(), Error> {
         .init();
 
     let config = aws_config::load_from_env().await;
-    let dynamo_client = Arc::new(dynamodb::Client::new(&config));
+    let table_name = env::var("PRICING_TABLE_NAME").unwrap_or(PRICING_TABLE_NAME.into());
+    let archive_table_name =
+        env::var("PRICE_ARCHIVE_TABLE_NAME").unwrap_or(PRICE_ARCHIVE_TABLE_NAME.into());
+    let pricing_repository: Arc<dyn PricingRepository> = Arc::new(
+        DynamoPricingRepository::new(dynamodb::Client::new(&config), table_name)
+            .with_archive_table(archive_table_name),
+    );
     let reqwest_client = Arc::new(Client::new());
 
     run(service_fn(|event| {
-        handle_store_electricity_pricing(event, dynamo_client.clone(), reqwest_client.clone())
+        handle_store_electricity_pricing(event, pricing_repository.clone(), reqwest_client.clone())
     }))
     .await
 }
@@ -4,8 +4,8 @@ use chrono_tz::Tz;
 
 use wh_core::types::BiddingZone;
 
-use crate::time_provider::TimeProvider;
 use crate::types::{EnergyChartApiResponse, WorkerError};
+use wh_core::time_provider::TimeProvider;
 
 pub fn unix_timestamp_to_datetime(tz: &Tz, unix_timestamp: &u32) -> Result<DateTime<Tz>, BoxError> {
     match tz.timestamp_opt(unix_timestamp.to_owned() as i64, 0) {
@@ -35,6 +35,7 @@ pub fn has_new_results<T: TimeProvider>(
 #[cfg(test)]
 mod tests {
     use chrono::Utc;
+    use rust_decimal_macros::dec;
     use std::sync::Arc;
     use wh_core::test_utils::MockTimeProvider;
 
@@ -48,7 +49,7 @@ mod tests {
         // Call the function with the mock time provider
         let data = EnergyChartApiResponse {
             unix_seconds: Arc::new([1726689600]),
-            price: Arc::new([38.43]),
+            price: Arc::new([dec!(38.43)]),
         };
 
         let result = has_new_results(data, &mock_provider).unwrap();
@@ -64,7 +65,7 @@ mod tests {
         // Call the function with the mock time provider
         let data = EnergyChartApiResponse {
             unix_seconds: Arc::new([1726689600]),
-            price: Arc::new([38.43]),
+            price: Arc::new([dec!(38.43)]),
         };
 
         let result = has_new_results(data, &mock_provider).unwrap();
@@ -1,11 +1,10 @@
 use std::sync::Arc;
 
-use aws_sdk_dynamodb::operation::put_item::PutItemError;
-use chrono::DateTime;
-use chrono_tz::Tz;
-use serde::{Deserialize, Serialize};
+use rust_decimal::Decimal;
+use serde::Deserialize;
 use thiserror::Error;
 use tokio::task::JoinError;
+use wh_core::repository::RepositoryError;
 
 #[derive(Error, Debug)]
 pub enum WorkerError {
@@ -15,8 +14,8 @@ pub enum WorkerError {
     Data(String),
     #[error("Error parsing data: {0}")]
     Parse(String),
-    #[error("DynamoDB Put Item operation failed: {0}")]
-    DatabaseError(#[from] Box<PutItemError>),
+    #[error("Storing pricing data failed: {0}")]
+    RepositoryError(#[from] RepositoryError),
     #[error("Joining futures failed: {0}")]
     JoinError(#[from] JoinError),
 }
@@ -24,8 +23,6 @@ pub enum WorkerError {
 #[derive(Debug, Deserialize)]
 pub struct EnergyChartApiResponse {
     pub unix_seconds: Arc<[u32]>,
-    pub price: Arc<[f32]>,
+    /// Day-ahead prices in €/MWh
+    pub price: Arc<[Decimal]>,
 }
-
-#[derive(Debug, Serialize)]
-pub struct HourlyPrice(pub DateTime<Tz>, pub f32);